@@ -0,0 +1,272 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+pub const CANNED_MESSAGES_FILE_NAME: &str = "canned_messages.json";
+
+/// Firmware stores the joined message list in a 201 byte buffer
+pub const MAX_CANNED_MESSAGES_BYTES: usize = 200;
+
+/// Firmware ignores messages past this count
+pub const MAX_CANNED_MESSAGE_COUNT: usize = 50;
+
+const CANNED_MESSAGE_SEPARATOR: char = '|';
+
+/// Checks that a canned message list can be stored by the firmware.
+pub fn validate_canned_messages(messages: &[String]) -> Result<(), String> {
+    if messages.len() > MAX_CANNED_MESSAGE_COUNT {
+        return Err(format!(
+            "At most {} canned messages are supported, got {}",
+            MAX_CANNED_MESSAGE_COUNT,
+            messages.len()
+        ));
+    }
+
+    for message in messages {
+        if message.trim().is_empty() {
+            return Err("Canned messages can't be empty".into());
+        }
+
+        if message.contains(CANNED_MESSAGE_SEPARATOR) {
+            return Err(format!(
+                "Canned message \"{}\" can't contain \"{}\"",
+                message, CANNED_MESSAGE_SEPARATOR
+            ));
+        }
+    }
+
+    let joined_len = join_canned_messages(messages).len();
+
+    if joined_len > MAX_CANNED_MESSAGES_BYTES {
+        return Err(format!(
+            "Canned messages take {} bytes, at most {} are supported",
+            joined_len, MAX_CANNED_MESSAGES_BYTES
+        ));
+    }
+
+    Ok(())
+}
+
+/// Joins a canned message list into the form the firmware stores.
+pub fn join_canned_messages(messages: &[String]) -> String {
+    messages.join(&CANNED_MESSAGE_SEPARATOR.to_string())
+}
+
+/// Splits the firmware's canned message string into separate messages.
+pub fn split_canned_messages(messages: &str) -> Vec<String> {
+    messages
+        .split(CANNED_MESSAGE_SEPARATOR)
+        .filter(|message| !message.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CannedMessageSet {
+    pub id: u32,
+    pub name: String,
+    pub messages: Vec<String>,
+}
+
+/// Reusable canned message lists that can be written to any node
+#[derive(Debug, Default)]
+pub struct CannedMessageLibrary {
+    sets: HashMap<u32, CannedMessageSet>,
+    next_id: u32,
+    store_path: Option<PathBuf>, // sets are only persisted if set
+}
+
+impl CannedMessageLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the sets stored at `store_path`, creating its directory if it
+    /// doesn't exist. Changes to sets are written back to the file.
+    pub fn open(store_path: PathBuf) -> Result<Self, String> {
+        let mut library = Self::new();
+
+        if store_path.exists() {
+            let contents = fs::read_to_string(&store_path).map_err(|e| e.to_string())?;
+            let sets: Vec<CannedMessageSet> =
+                serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+
+            for set in sets {
+                library.next_id = library.next_id.max(set.id);
+                library.sets.insert(set.id, set);
+            }
+        } else if let Some(parent) = store_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        debug!(
+            "Loaded {} canned message sets from {:?}",
+            library.sets.len(),
+            store_path
+        );
+
+        library.store_path = Some(store_path);
+
+        Ok(library)
+    }
+
+    /// Writes the sets to the store file, if there is one. Failing to persist
+    /// doesn't undo the change, so it's only logged.
+    fn persist(&self) {
+        let store_path = match self.store_path.as_ref() {
+            Some(store_path) => store_path,
+            None => return,
+        };
+
+        if let Err(e) = write_canned_message_sets(store_path, &self.get_sets()) {
+            warn!("Failed to persist canned message sets: {}", e);
+        }
+    }
+
+    pub fn get_sets(&self) -> Vec<CannedMessageSet> {
+        let mut sets: Vec<CannedMessageSet> = self.sets.values().cloned().collect();
+        sets.sort_by_key(|set| set.id);
+        sets
+    }
+
+    pub fn get_set(&self, set_id: u32) -> Option<&CannedMessageSet> {
+        self.sets.get(&set_id)
+    }
+
+    /// Adds a set, or replaces the set with the same id. A set with an id of
+    /// 0 is assigned a new id.
+    pub fn upsert_set(&mut self, mut set: CannedMessageSet) -> Result<CannedMessageSet, String> {
+        validate_canned_messages(&set.messages)?;
+
+        if set.id == 0 {
+            self.next_id += 1;
+            set.id = self.next_id;
+        } else {
+            self.next_id = self.next_id.max(set.id);
+        }
+
+        self.sets.insert(set.id, set.clone());
+        self.persist();
+
+        debug!("Upserted canned message set {}", set.id);
+
+        Ok(set)
+    }
+
+    pub fn remove_set(&mut self, set_id: u32) -> Option<CannedMessageSet> {
+        let set = self.sets.remove(&set_id)?;
+        self.persist();
+
+        Some(set)
+    }
+}
+
+fn write_canned_message_sets(store_path: &Path, sets: &[CannedMessageSet]) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(sets).map_err(|e| e.to_string())?;
+
+    // Write to a temporary file first so a failed write keeps the old sets
+    let temp_path = store_path.with_extension("json.tmp");
+    fs::write(&temp_path, contents).map_err(|e| e.to_string())?;
+
+    fs::rename(&temp_path, store_path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn round_trips_message_list() {
+        let list = messages(&["Hi", "On my way", "Need help"]);
+        let joined = join_canned_messages(&list);
+
+        assert_eq!(joined, "Hi|On my way|Need help");
+        assert_eq!(split_canned_messages(&joined), list);
+        assert!(split_canned_messages("").is_empty());
+    }
+
+    #[test]
+    fn validates_firmware_limits() {
+        assert!(validate_canned_messages(&messages(&["Hi", "Bye"])).is_ok());
+        assert!(validate_canned_messages(&messages(&["Yes|No"])).is_err());
+        assert!(validate_canned_messages(&messages(&[" "])).is_err());
+        assert!(validate_canned_messages(&["a".repeat(MAX_CANNED_MESSAGES_BYTES)]).is_ok());
+        assert!(validate_canned_messages(&["a".repeat(MAX_CANNED_MESSAGES_BYTES + 1)]).is_err());
+        assert!(
+            validate_canned_messages(&vec!["a".to_string(); MAX_CANNED_MESSAGE_COUNT + 1]).is_err()
+        );
+    }
+
+    #[test]
+    fn assigns_set_ids() {
+        let mut library = CannedMessageLibrary::new();
+
+        let set = library
+            .upsert_set(CannedMessageSet {
+                id: 0,
+                name: "Hiking".into(),
+                messages: messages(&["At camp", "Heading back"]),
+            })
+            .unwrap();
+
+        assert_eq!(set.id, 1);
+        assert!(library
+            .upsert_set(CannedMessageSet {
+                id: 0,
+                name: "Invalid".into(),
+                messages: messages(&["a|b"]),
+            })
+            .is_err());
+        assert_eq!(library.get_sets().len(), 1);
+        assert!(library.remove_set(1).is_some());
+    }
+
+    #[test]
+    fn writes_and_reopens_sets_file() {
+        let store_path = std::env::temp_dir().join(format!(
+            "canned_messages_{}.json",
+            crate::device::helpers::generate_rand_id::<u32>()
+        ));
+
+        let mut library = CannedMessageLibrary::open(store_path.clone()).unwrap();
+
+        for name in ["Hiking", "Sailing"] {
+            library
+                .upsert_set(CannedMessageSet {
+                    id: 0,
+                    name: name.into(),
+                    messages: messages(&["On my way"]),
+                })
+                .unwrap();
+        }
+
+        library.remove_set(1).unwrap();
+
+        let mut reopened = CannedMessageLibrary::open(store_path.clone()).unwrap();
+        let names: Vec<String> = reopened.get_sets().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["Sailing".to_string()]);
+
+        // New sets don't reuse the ids of stored ones
+        let set = reopened
+            .upsert_set(CannedMessageSet {
+                id: 0,
+                name: "Climbing".into(),
+                messages: messages(&["Off belay"]),
+            })
+            .unwrap();
+        assert_eq!(set.id, 3);
+
+        let _ = fs::remove_file(store_path);
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use rand::{distributions::Standard, prelude::Distribution, Rng};
 use std::time::UNIX_EPOCH;
 
@@ -12,6 +13,18 @@ pub fn get_current_time_u32() -> u32 {
         .expect("Could not convert u128 to u32")
 }
 
+/// Formats a UTC time as an RFC 3339 timestamp.
+pub fn format_utc_datetime(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Formats a time in seconds since the epoch as an RFC 3339 UTC timestamp.
+pub fn format_utc_time(time: u32) -> String {
+    NaiveDateTime::from_timestamp_opt(time.into(), 0)
+        .map(format_utc_datetime)
+        .unwrap_or_default()
+}
+
 pub fn generate_rand_id<T>() -> T
 where
     Standard: Distribution<T>,
@@ -27,6 +40,13 @@ pub fn get_node_user_name(device: &mut MeshDevice, node_id: &u32) -> Option<Stri
     Some(db_user.long_name.clone())
 }
 
+pub fn get_node_short_name(device: &mut MeshDevice, node_id: &u32) -> Option<String> {
+    let db_node = device.nodes.get(node_id)?;
+    let db_user = db_node.user.as_ref()?;
+
+    Some(db_user.short_name.clone())
+}
+
 pub fn get_channel_name(device: &mut MeshDevice, channel_id: &u32) -> Option<String> {
     let db_channel = device.channels.get(channel_id)?;
     let db_channel_settings = db_channel.config.settings.as_ref()?;
@@ -47,17 +67,17 @@ pub fn get_channel_name(device: &mut MeshDevice, channel_id: &u32) -> Option<Str
 ///
 /// # Returns
 ///
-/// * `f32` - The converted mesh location field.
+/// * `f64` - The converted mesh location field.
 ///
 /// # Example
 ///
 /// ```
 /// let lat = 27_030_000; // Represents 2.703 latitude
-/// let norm_lat = normalize_location_field(latitude);
+/// let norm_lat = normalize_location_field(lat);
 /// assert_eq!(norm_lat, 2.703);
 /// ```
-pub fn normalize_location_field(field: i32) -> f32 {
-    (field as f64 / 1e7) as f32
+pub fn normalize_location_field(field: i32) -> f64 {
+    f64::from(field) / 1e7
 }
 
 /// Converts a location field (e.g., latitude) to the
@@ -69,7 +89,8 @@ pub fn normalize_location_field(field: i32) -> f32 {
 ///
 /// # Returns
 ///
-/// * `i32` - The converted location field.
+/// * `i32` - The converted location field, rounded to the nearest
+///   integer so negative coordinates aren't biased away from zero.
 ///
 /// # Example
 ///
@@ -78,6 +99,48 @@ pub fn normalize_location_field(field: i32) -> f32 {
 /// let mesh_lat = convert_location_field_to_protos(lat);
 /// assert_eq!(mesh_lat, 27_030_000);
 /// ```
-pub fn convert_location_field_to_protos(field: f32) -> i32 {
-    (field * 1e7).floor() as i32
+pub fn convert_location_field_to_protos(field: f64) -> i32 {
+    (field * 1e7).round() as i32
+}
+
+/// Generates a random channel pre-shared key of the given length.
+///
+/// # Arguments
+///
+/// * `num_bytes` - The length of the key in bytes (16 for AES128, 32 for AES256).
+///
+/// # Returns
+///
+/// * `Vec<u8>` - The generated key.
+pub fn generate_rand_psk(num_bytes: usize) -> Vec<u8> {
+    (0..num_bytes).map(|_| generate_rand_id::<u8>()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn latitude_round_trip(field in -900_000_000i32..=900_000_000) {
+            let normalized = normalize_location_field(field);
+            prop_assert_eq!(convert_location_field_to_protos(normalized), field);
+        }
+
+        #[test]
+        fn longitude_round_trip(field in -1_800_000_000i32..=1_800_000_000) {
+            let normalized = normalize_location_field(field);
+            prop_assert_eq!(convert_location_field_to_protos(normalized), field);
+        }
+    }
+
+    #[test]
+    fn negative_coordinates_round_to_nearest() {
+        assert_eq!(
+            convert_location_field_to_protos(-122.123_456_78),
+            -1_221_234_568
+        );
+        assert_eq!(convert_location_field_to_protos(-0.000_000_04), 0);
+    }
 }
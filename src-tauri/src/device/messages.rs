@@ -0,0 +1,629 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use super::helpers::{format_utc_time, get_channel_name, get_node_short_name, get_node_user_name};
+use super::{ChannelMessagePayload, ChannelMessageState, ChannelMessageWithState, MeshDevice};
+use crate::state::DeviceKey;
+
+/// Where a message is stored within a device's channels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MessageLocation {
+    pub channel_id: u32,
+    pub index: usize, // position in the channel's message list
+}
+
+#[derive(Clone, Debug)]
+struct IndexedMessage {
+    from: u32,
+    time: u32, // secs
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchFilters {
+    pub device_key: Option<DeviceKey>,
+    pub channel_id: Option<u32>,
+    pub from: Option<u32>,                  // sending node
+    pub start: Option<u32>,                 // secs
+    pub end: Option<u32>,                   // secs
+    pub state: Option<ChannelMessageState>, // any error matches an `Error` filter
+    pub limit: Option<u32>,                 // most recent matches are kept
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchResult {
+    pub device_key: DeviceKey,
+    pub channel_id: u32,
+    pub time: u32, // secs
+    pub message: ChannelMessageWithState,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageExportFormat {
+    Csv,
+    JsonLines,
+    Markdown, // readable transcript
+}
+
+/// A message with its sender and channel resolved, ready for export
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageExportRecord {
+    pub time: String, // RFC 3339
+    pub channel_id: u32,
+    pub channel_name: String,
+    pub from: u32,
+    pub from_long_name: String,
+    pub from_short_name: String,
+    pub packet_id: u32,
+    pub text: String,
+    pub state: String,
+    pub hop_limit: u32,          // hops left when the packet was received
+    pub hops_taken: Option<u32>, // unknown for packets from firmware that doesn't set hop start
+    pub rx_snr: f32,
+    pub rx_rssi: i32,
+}
+
+impl MessageExportRecord {
+    pub fn new(device: &mut MeshDevice, result: &MessageSearchResult) -> Self {
+        let (packet, text) = match &result.message.payload {
+            ChannelMessagePayload::Text(t) => (&t.packet, t.data.clone()),
+            ChannelMessagePayload::Waypoint(w) => (
+                &w.packet,
+                format!(
+                    "Waypoint \"{}\" at {}, {}",
+                    w.data.name, w.data.latitude, w.data.longitude
+                ),
+            ),
+        };
+
+        let state = match &result.message.state {
+            ChannelMessageState::Pending => "pending".to_string(),
+            ChannelMessageState::Acknowledged => "acknowledged".to_string(),
+            ChannelMessageState::Error(e) => format!("error: {}", e),
+        };
+
+        Self {
+            time: format_utc_time(result.time),
+            channel_id: result.channel_id,
+            channel_name: get_channel_name(device, &result.channel_id)
+                .unwrap_or_else(|| format!("Channel {}", result.channel_id)),
+            from: packet.from,
+            from_long_name: get_node_user_name(device, &packet.from)
+                .unwrap_or_else(|| packet.from.to_string()),
+            from_short_name: get_node_short_name(device, &packet.from).unwrap_or_default(),
+            packet_id: packet.id,
+            text,
+            state,
+            hop_limit: packet.hop_limit,
+            hops_taken: (packet.hop_start != 0)
+                .then(|| packet.hop_start.saturating_sub(packet.hop_limit)),
+            rx_snr: packet.rx_snr,
+            rx_rssi: packet.rx_rssi,
+        }
+    }
+}
+
+/// Renders messages as CSV, JSON Lines or a Markdown transcript.
+pub fn export_messages(
+    records: &[MessageExportRecord],
+    title: &str,
+    format: MessageExportFormat,
+) -> Result<String, String> {
+    match format {
+        MessageExportFormat::Csv => Ok(to_csv(records)),
+        MessageExportFormat::JsonLines => to_json_lines(records),
+        MessageExportFormat::Markdown => Ok(to_markdown(records, title)),
+    }
+}
+
+fn escape_csv(value: &str) -> String {
+    // Spreadsheets evaluate cells starting with these as formulas
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn to_csv(records: &[MessageExportRecord]) -> String {
+    let mut output = String::from(
+        "time,channel_id,channel_name,from,from_long_name,from_short_name,packet_id,text,state,hop_limit,hops_taken,rx_snr,rx_rssi\n",
+    );
+
+    for record in records {
+        let fields = [
+            record.time.clone(),
+            record.channel_id.to_string(),
+            escape_csv(&record.channel_name),
+            record.from.to_string(),
+            escape_csv(&record.from_long_name),
+            escape_csv(&record.from_short_name),
+            record.packet_id.to_string(),
+            escape_csv(&record.text),
+            escape_csv(&record.state),
+            record.hop_limit.to_string(),
+            record
+                .hops_taken
+                .map(|hops| hops.to_string())
+                .unwrap_or_default(),
+            record.rx_snr.to_string(),
+            record.rx_rssi.to_string(),
+        ];
+
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+
+    output
+}
+
+fn to_json_lines(records: &[MessageExportRecord]) -> Result<String, String> {
+    let mut output = String::new();
+
+    for record in records {
+        output.push_str(&serde_json::to_string(record).map_err(|e| e.to_string())?);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+fn escape_markdown(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    // Keep multi-line messages inside their list item
+    escaped.replace('\n', "  \n  ")
+}
+
+fn to_markdown(records: &[MessageExportRecord], title: &str) -> String {
+    let mut output = format!("# {}\n\n", escape_markdown(title));
+
+    for record in records {
+        let sender = if record.from_short_name.is_empty() {
+            escape_markdown(&record.from_long_name)
+        } else {
+            format!(
+                "{} ({})",
+                escape_markdown(&record.from_long_name),
+                escape_markdown(&record.from_short_name)
+            )
+        };
+
+        let hops = match record.hops_taken {
+            Some(hops_taken) => format!("{} hops taken, {} left", hops_taken, record.hop_limit),
+            None => format!("{} hops left", record.hop_limit),
+        };
+
+        output.push_str(&format!(
+            "- **{}** {} in {}: {}  \n  _{}, {}, SNR {} dB, RSSI {} dBm_\n",
+            record.time,
+            sender,
+            escape_markdown(&record.channel_name),
+            escape_markdown(&record.text),
+            escape_markdown(&record.state),
+            hops,
+            record.rx_snr,
+            record.rx_rssi
+        ));
+    }
+
+    output
+}
+
+/// Splits text into lowercase alphanumeric words for full-text matching.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Lookup tables over the messages stored in a device's channels, so
+/// messages can be found without scanning every channel.
+#[derive(Clone, Debug, Default)]
+pub struct MessageIndex {
+    messages: HashMap<MessageLocation, IndexedMessage>,
+    by_packet_id: HashMap<u32, Vec<MessageLocation>>,
+    by_reply_to: HashMap<u32, Vec<MessageLocation>>, // keyed by the packet id replied to
+    by_channel: HashMap<u32, Vec<MessageLocation>>,
+    by_sender: HashMap<u32, Vec<MessageLocation>>,
+    by_time: BTreeMap<u32, Vec<MessageLocation>>,
+    by_word: BTreeMap<String, Vec<MessageLocation>>,
+}
+
+impl MessageIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a message to the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Where the message is stored.
+    /// * `from` - The node that sent the message.
+    /// * `packet_id` - The id of the packet the message was sent in.
+    /// * `reply_to` - The packet id of the message this message replies to.
+    /// * `time` - When the message was received, in seconds.
+    /// * `text` - The searchable text of the message.
+    pub fn insert(
+        &mut self,
+        location: MessageLocation,
+        from: u32,
+        packet_id: u32,
+        reply_to: Option<u32>,
+        time: u32,
+        text: &str,
+    ) {
+        self.messages
+            .insert(location, IndexedMessage { from, time });
+
+        self.by_packet_id
+            .entry(packet_id)
+            .or_default()
+            .push(location);
+
+        if let Some(reply_to) = reply_to {
+            self.by_reply_to.entry(reply_to).or_default().push(location);
+        }
+
+        self.by_channel
+            .entry(location.channel_id)
+            .or_default()
+            .push(location);
+        self.by_sender.entry(from).or_default().push(location);
+        self.by_time.entry(time).or_default().push(location);
+
+        let words: HashSet<String> = tokenize(text).into_iter().collect();
+
+        for word in words {
+            self.by_word.entry(word).or_default().push(location);
+        }
+    }
+
+    /// Gets the locations of all messages sent in a packet with the given id.
+    pub fn find_by_packet_id(&self, packet_id: u32) -> &[MessageLocation] {
+        self.by_packet_id
+            .get(&packet_id)
+            .map_or(&[], |locations| locations.as_slice())
+    }
+
+    /// Gets the locations of all messages replying to a packet with the given id.
+    pub fn find_replies(&self, packet_id: u32) -> &[MessageLocation] {
+        self.by_reply_to
+            .get(&packet_id)
+            .map_or(&[], |locations| locations.as_slice())
+    }
+
+    /// Gets the location of the message `from` sent in packet `packet_id`.
+    /// Packet ids are only unique per sender, so both are needed to tell
+    /// a rebroadcast apart from a different message.
+    pub fn find(&self, from: u32, packet_id: u32) -> Option<MessageLocation> {
+        self.find_by_packet_id(packet_id)
+            .iter()
+            .find(|location| {
+                self.messages
+                    .get(location)
+                    .map_or(false, |m| m.from == from)
+            })
+            .cloned()
+    }
+
+    pub fn get_time(&self, location: &MessageLocation) -> Option<u32> {
+        self.messages.get(location).map(|m| m.time)
+    }
+
+    fn find_by_word_prefix(&self, prefix: &str) -> HashSet<MessageLocation> {
+        self.by_word
+            .range(prefix.to_string()..)
+            .take_while(|(word, _)| word.starts_with(prefix))
+            .flat_map(|(_, locations)| locations.iter().cloned())
+            .collect()
+    }
+
+    /// Finds messages containing every word in `query`, ordered from oldest
+    /// to newest. Each query word matches any word it's a prefix of, and a
+    /// blank query matches every message. A query with no searchable words,
+    /// like punctuation or emoji, matches nothing.
+    /// Filtering on delivery state and limiting results is left to the
+    /// caller, since the index doesn't track message state.
+    pub fn search(&self, query: &str, filters: &MessageSearchFilters) -> Vec<MessageLocation> {
+        let query_words = tokenize(query);
+
+        if query_words.is_empty() && !query.trim().is_empty() {
+            return vec![];
+        }
+
+        let by_sender = filters.from.map(|from| {
+            self.by_sender
+                .get(&from)
+                .map_or(&[][..], |locations| locations.as_slice())
+        });
+        let by_channel = filters.channel_id.map(|channel_id| {
+            self.by_channel
+                .get(&channel_id)
+                .map_or(&[][..], |locations| locations.as_slice())
+        });
+
+        // Prefer whichever filter narrows the messages down the most
+        let filtered_locations = match (by_sender, by_channel) {
+            (Some(s), Some(c)) => Some(if s.len() <= c.len() { s } else { c }),
+            (s, c) => s.or(c),
+        };
+
+        // Start from the most selective index available
+        let candidates: Vec<MessageLocation> = if !query_words.is_empty() {
+            let mut matches: Option<HashSet<MessageLocation>> = None;
+
+            for word in query_words.iter() {
+                let word_matches = self.find_by_word_prefix(word);
+
+                matches = Some(match matches {
+                    Some(m) => m.intersection(&word_matches).cloned().collect(),
+                    None => word_matches,
+                });
+            }
+
+            matches.unwrap_or_default().into_iter().collect()
+        } else if let Some(locations) = filtered_locations {
+            locations.to_vec()
+        } else {
+            let start = filters.start.unwrap_or(0);
+            let end = filters.end.unwrap_or(u32::MAX);
+
+            if start > end {
+                return vec![];
+            }
+
+            self.by_time
+                .range(start..=end)
+                .flat_map(|(_, locations)| locations.iter().cloned())
+                .collect()
+        };
+
+        let mut results: Vec<(u32, MessageLocation)> = candidates
+            .into_iter()
+            .filter_map(|location| {
+                let message = self.messages.get(&location)?;
+
+                let is_match = filters
+                    .channel_id
+                    .map_or(true, |c| location.channel_id == c)
+                    && filters.from.map_or(true, |from| message.from == from)
+                    && filters.start.map_or(true, |start| message.time >= start)
+                    && filters.end.map_or(true, |end| message.time <= end);
+
+                is_match.then_some((message.time, location))
+            })
+            .collect();
+
+        results.sort();
+        results.into_iter().map(|(_, location)| location).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_record(text: &str) -> MessageExportRecord {
+        MessageExportRecord {
+            time: "2024-01-01T00:00:00Z".into(),
+            channel_id: 0,
+            channel_name: "Primary".into(),
+            from: 1,
+            from_long_name: "Base, Camp".into(),
+            from_short_name: "BASE".into(),
+            packet_id: 100,
+            text: text.into(),
+            state: "acknowledged".into(),
+            hop_limit: 3,
+            hops_taken: Some(2),
+            rx_snr: 5.5,
+            rx_rssi: -90,
+        }
+    }
+
+    fn location(channel_id: u32, index: usize) -> MessageLocation {
+        MessageLocation { channel_id, index }
+    }
+
+    fn build_index() -> MessageIndex {
+        let mut index = MessageIndex::new();
+        index.insert(location(0, 0), 1, 100, None, 1_000, "Meet at the trailhead");
+        index.insert(
+            location(0, 1),
+            2,
+            101,
+            Some(100),
+            2_000,
+            "Running late, at the TRAIL now",
+        );
+        index.insert(location(1, 0), 1, 102, None, 3_000, "Base camp is set up");
+        index
+    }
+
+    #[test]
+    fn full_text_search() {
+        let index = build_index();
+        let filters = MessageSearchFilters::default();
+
+        assert_eq!(
+            index.search("trail", &filters),
+            vec![location(0, 0), location(0, 1)]
+        );
+        assert_eq!(index.search("at trail now", &filters), vec![location(0, 1)]);
+        assert!(index.search("summit", &filters).is_empty());
+    }
+
+    #[test]
+    fn search_without_searchable_words() {
+        let index = build_index();
+        let filters = MessageSearchFilters::default();
+
+        // Punctuation and emoji aren't indexed, so they can't match anything
+        assert!(index.search("👍", &filters).is_empty());
+        assert!(index.search("?!", &filters).is_empty());
+
+        // A blank query lists every message
+        assert_eq!(index.search(" ", &filters).len(), 3);
+    }
+
+    #[test]
+    fn filtered_search() {
+        let index = build_index();
+
+        let from_filter = MessageSearchFilters {
+            from: Some(1),
+            start: Some(2_000),
+            ..Default::default()
+        };
+
+        assert_eq!(index.search("", &from_filter), vec![location(1, 0)]);
+
+        let channel_filter = MessageSearchFilters {
+            channel_id: Some(0),
+            end: Some(1_500),
+            ..Default::default()
+        };
+
+        assert_eq!(index.search("at", &channel_filter), vec![location(0, 0)]);
+
+        let channel_filter = MessageSearchFilters {
+            channel_id: Some(0),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            index.search("", &channel_filter),
+            vec![location(0, 0), location(0, 1)]
+        );
+
+        let sender_and_channel_filter = MessageSearchFilters {
+            from: Some(1),
+            channel_id: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            index.search("", &sender_and_channel_filter),
+            vec![location(1, 0)]
+        );
+        assert!(index
+            .search(
+                "",
+                &MessageSearchFilters {
+                    channel_id: Some(2),
+                    ..Default::default()
+                }
+            )
+            .is_empty());
+        assert_eq!(index.find_by_packet_id(101), &[location(0, 1)]);
+        assert_eq!(index.find(2, 101), Some(location(0, 1)));
+        assert_eq!(index.find(1, 101), None);
+        assert_eq!(index.find_replies(100), &[location(0, 1)]);
+        assert!(index.find_replies(101).is_empty());
+    }
+
+    #[test]
+    fn csv_export_quotes_fields() {
+        let csv = export_messages(
+            &[build_record("Say \"hi\", then leave")],
+            "Messages",
+            MessageExportFormat::Csv,
+        )
+        .unwrap();
+
+        let row = csv.lines().nth(1).unwrap();
+
+        assert!(row.contains(",\"Base, Camp\",BASE,"));
+        assert!(row.contains(",\"Say \"\"hi\"\", then leave\","));
+    }
+
+    #[test]
+    fn csv_export_neutralizes_formulas() {
+        let csv = export_messages(
+            &[
+                build_record("=HYPERLINK(\"http://example.com\")"),
+                build_record("-1+2"),
+                build_record("@SUM(A1)"),
+                build_record("\tTabbed"),
+                build_record("Plain - text"),
+            ],
+            "Messages",
+            MessageExportFormat::Csv,
+        )
+        .unwrap();
+
+        let rows: Vec<&str> = csv.lines().skip(1).collect();
+
+        assert!(rows[0].contains(",\"'=HYPERLINK(\"\"http://example.com\"\")\","));
+        assert!(rows[1].contains(",'-1+2,"));
+        assert!(rows[2].contains(",'@SUM(A1),"));
+        assert!(rows[3].contains(",'\tTabbed,"));
+        assert!(rows[4].contains(",Plain - text,"));
+
+        // Numeric fields are written as numbers
+        assert!(rows[0].ends_with(",3,2,5.5,-90"));
+    }
+
+    #[test]
+    fn markdown_export() {
+        let mut unknown_hops = build_record("Second");
+        unknown_hops.hops_taken = None;
+
+        let markdown = export_messages(
+            &[build_record("Meet at *camp*\nBring water"), unknown_hops],
+            "Primary #1",
+            MessageExportFormat::Markdown,
+        )
+        .unwrap();
+
+        let lines: Vec<&str> = markdown.lines().collect();
+
+        assert_eq!(lines[0], "# Primary \\#1");
+        assert_eq!(
+            lines[2],
+            "- **2024-01-01T00:00:00Z** Base, Camp (BASE) in Primary: Meet at \\*camp\\*  "
+        );
+        assert_eq!(lines[3], "  Bring water  ");
+        assert_eq!(
+            lines[4],
+            "  _acknowledged, 2 hops taken, 3 left, SNR 5.5 dB, RSSI -90 dBm_"
+        );
+        assert!(lines[6].contains("_acknowledged, 3 hops left,"));
+    }
+
+    #[test]
+    fn json_lines_export() {
+        let jsonl = export_messages(
+            &[build_record("one"), build_record("two")],
+            "Messages",
+            MessageExportFormat::JsonLines,
+        )
+        .unwrap();
+
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(jsonl.contains("\"fromLongName\":\"Base, Camp\""));
+        assert!(jsonl.contains("\"hopsTaken\":2"));
+    }
+}
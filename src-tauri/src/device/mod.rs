@@ -6,11 +6,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use self::helpers::{
-    convert_location_field_to_protos, generate_rand_id, get_current_time_u32,
+    convert_location_field_to_protos, generate_rand_id, generate_rand_psk, get_current_time_u32,
     normalize_location_field,
 };
 
 pub mod helpers;
+pub mod messages;
 pub mod state;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Type)]
@@ -39,12 +40,64 @@ pub struct MeshChannel {
     pub messages: Vec<ChannelMessageWithState>,
 }
 
+/// Full position history lives in the track store, so nodes only keep recent fixes
+pub const MAX_POSITION_METRICS: usize = 100;
+
+/// Single-byte PSK that tells the firmware to use its built-in default key
+pub const DEFAULT_CHANNEL_PSK: [u8; 1] = [0x01];
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ChannelPskType {
+    Aes128,  // random 128-bit key
+    Aes256,  // random 256-bit key
+    Default, // firmware default key, publicly known
+    None,    // encryption disabled
+}
+
+impl ChannelPskType {
+    pub fn generate_psk(&self) -> Vec<u8> {
+        match self {
+            ChannelPskType::Aes128 => generate_rand_psk(16),
+            ChannelPskType::Aes256 => generate_rand_psk(32),
+            ChannelPskType::Default => DEFAULT_CHANNEL_PSK.to_vec(),
+            ChannelPskType::None => vec![],
+        }
+    }
+
+    pub fn get_channel_key_warnings(&self, channel_index: u32) -> Vec<String> {
+        let mut warnings = vec![];
+
+        match self {
+            ChannelPskType::Default => {
+                warnings.push(
+                    "The default key is publicly known, channel traffic is not private".into(),
+                );
+            }
+            ChannelPskType::None => {
+                warnings
+                    .push("Encryption is disabled, channel traffic is sent in plaintext".into());
+            }
+            ChannelPskType::Aes128 | ChannelPskType::Aes256 => {}
+        }
+
+        if channel_index == 0 {
+            warnings.push(
+                "Changing the primary channel key disconnects all nodes that don't receive the new key"
+                    .into(),
+            );
+        }
+
+        warnings
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
 pub struct MeshNodeDeviceMetrics {
-    metrics: protobufs::DeviceMetrics,
-    timestamp: u32,
-    snr: f32,
+    pub metrics: protobufs::DeviceMetrics,
+    pub timestamp: u32,
+    pub snr: f32,
     // channel: u32,
 }
 
@@ -117,9 +170,21 @@ impl MeshNode {
         }
 
         if let Some(position) = node_info.position {
-            self.position_metrics.push(position.into());
+            self.add_position(position.into());
         }
     }
+
+    /// Records a position fix, dropping the oldest fixes past `MAX_POSITION_METRICS`.
+    pub fn add_position(&mut self, position: NormalizedPosition) {
+        self.position_metrics.push(position);
+
+        let overflow = self
+            .position_metrics
+            .len()
+            .saturating_sub(MAX_POSITION_METRICS);
+
+        self.position_metrics.drain(..overflow);
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Type)]
@@ -153,8 +218,8 @@ pub struct NormalizedNodeInfo {
 #[derive(Clone, Debug, Default, Serialize, Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
 pub struct NormalizedPosition {
-    pub latitude: f32,
-    pub longitude: f32,
+    pub latitude: f64,
+    pub longitude: f64,
     pub altitude: i32,
     pub time: u32, // secs
     pub location_source: protobufs::position::LocSource,
@@ -256,10 +321,10 @@ pub struct NormalizedWaypoint {
     pub id: u32,
 
     /// Latitude of the waypoint
-    pub latitude: f32,
+    pub latitude: f64,
 
     /// Longitude of the waypoint
-    pub longitude: f32,
+    pub longitude: f64,
 
     /// Expire time of waypoint in seconds since epoch
     pub expire: u32,
@@ -268,10 +333,10 @@ pub struct NormalizedWaypoint {
     /// Waypoint can be edited by any node if this is 0
     pub locked_to: u32,
 
-    /// Name of the waypoint (max 30 chars)
+    /// Name of the waypoint (max 29 bytes)
     pub name: String,
 
-    /// Description of the waypoint (max 100 chars)
+    /// Description of the waypoint (max 99 bytes)
     pub description: String,
 
     /// Icon for the waypoint in the form of a unicode emoji
@@ -282,7 +347,6 @@ impl From<protobufs::Waypoint> for NormalizedWaypoint {
     fn from(waypoint: protobufs::Waypoint) -> Self {
         Self {
             id: waypoint.id,
-            // Converting to f64 first to cover entire i32 range
             latitude: normalize_location_field(waypoint.latitude_i),
             longitude: normalize_location_field(waypoint.longitude_i),
             expire: waypoint.expire,
@@ -294,11 +358,22 @@ impl From<protobufs::Waypoint> for NormalizedWaypoint {
     }
 }
 
+impl NormalizedWaypoint {
+    /// Whether the waypoint has expired. Waypoints with an expire time of 0 never expire.
+    pub fn is_expired(&self, now: u32) -> bool {
+        self.expire != 0 && self.expire <= now
+    }
+
+    /// Whether `node_num` may edit or delete the waypoint.
+    pub fn can_be_edited_by(&self, node_num: u32) -> bool {
+        self.locked_to == 0 || self.locked_to == node_num
+    }
+}
+
 impl Into<protobufs::Waypoint> for NormalizedWaypoint {
     fn into(self) -> protobufs::Waypoint {
         protobufs::Waypoint {
             id: self.id,
-            // Converting to f64 first to cover entire i32 range
             latitude_i: convert_location_field_to_protos(self.latitude),
             longitude_i: convert_location_field_to_protos(self.longitude),
             expire: self.expire,
@@ -331,6 +406,18 @@ pub enum ChannelMessageState {
 pub struct ChannelMessageWithState {
     pub payload: ChannelMessagePayload,
     pub state: ChannelMessageState,
+    pub reply_to: Option<u32>, // packet id of the message this replies to
+    pub reply_ids: Vec<u32>,   // packet ids of replies to this message, oldest first
+    pub reactions: Vec<MessageReaction>,
+    pub heard_count: u32, // times this packet was received, including rebroadcasts
+    pub best_rx_snr: Option<f32>, // best SNR the packet was received with, if heard over the air
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageReaction {
+    pub emoji: String,
+    pub node_nums: Vec<u32>, // nodes that reacted with this emoji
 }
 
 // TODO can't deserialize `SerialConnection`
@@ -350,6 +437,12 @@ pub struct MeshDevice {
     pub waypoints: HashMap<u32, NormalizedWaypoint>, // updatable GPS positions managed by this device
     pub neighbors: HashMap<u32, NeighborInfoPacket>, //updated packets from each node containing their neighbors
     pub config_in_progress: bool, // flag for whether the user has started a configuration transaction
+    pub encrypted_packet_counts: HashMap<u32, u32>, // undecryptable packets seen per channel hash
+    pub canned_messages: HashMap<u32, Vec<String>>, // canned message lists read from each node
+    #[serde(skip)]
+    pub pending_canned_messages: HashMap<u32, (u32, Vec<String>)>, // lists written to nodes by packet id, until confirmed
+    #[serde(skip)]
+    pub message_index: messages::MessageIndex, // lookups into the messages of all channels
 }
 
 impl MeshDevice {
@@ -362,3 +455,74 @@ impl MeshDevice {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_psk_of_expected_length() {
+        assert_eq!(ChannelPskType::Aes128.generate_psk().len(), 16);
+        assert_eq!(ChannelPskType::Aes256.generate_psk().len(), 32);
+        assert_eq!(
+            ChannelPskType::Default.generate_psk(),
+            DEFAULT_CHANNEL_PSK.to_vec()
+        );
+        assert!(ChannelPskType::None.generate_psk().is_empty());
+    }
+
+    #[test]
+    fn warns_about_insecure_and_primary_channel_keys() {
+        assert!(ChannelPskType::Aes256
+            .get_channel_key_warnings(1)
+            .is_empty());
+        assert_eq!(ChannelPskType::Aes128.get_channel_key_warnings(0).len(), 1);
+        assert_eq!(ChannelPskType::Default.get_channel_key_warnings(1).len(), 1);
+        assert_eq!(ChannelPskType::None.get_channel_key_warnings(1).len(), 1);
+        assert_eq!(ChannelPskType::None.get_channel_key_warnings(0).len(), 2);
+    }
+
+    #[test]
+    fn keeps_most_recent_positions() {
+        let mut node = MeshNode::new(1);
+
+        for time in 0..(MAX_POSITION_METRICS as u32 + 10) {
+            node.add_position(NormalizedPosition {
+                time,
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(node.position_metrics.len(), MAX_POSITION_METRICS);
+        assert_eq!(node.position_metrics[0].time, 10);
+        assert_eq!(
+            node.position_metrics.last().map(|p| p.time),
+            Some(MAX_POSITION_METRICS as u32 + 9)
+        );
+    }
+
+    fn build_waypoint(expire: u32, locked_to: u32) -> NormalizedWaypoint {
+        protobufs::Waypoint {
+            id: 1,
+            expire,
+            locked_to,
+            ..Default::default()
+        }
+        .into()
+    }
+
+    #[test]
+    fn waypoint_expiry() {
+        assert!(!build_waypoint(0, 0).is_expired(u32::MAX));
+        assert!(!build_waypoint(1_000, 0).is_expired(999));
+        assert!(build_waypoint(1_000, 0).is_expired(1_000));
+        assert!(build_waypoint(1, 0).is_expired(1_000));
+    }
+
+    #[test]
+    fn locked_waypoints_can_only_be_edited_by_owner() {
+        assert!(build_waypoint(0, 0).can_be_edited_by(2));
+        assert!(build_waypoint(0, 2).can_be_edited_by(2));
+        assert!(!build_waypoint(0, 2).can_be_edited_by(3));
+    }
+}
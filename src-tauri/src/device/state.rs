@@ -4,13 +4,18 @@ use log::{debug, trace};
 use meshtastic::protobufs;
 
 use super::helpers::get_current_time_u32;
+use super::messages::{MessageLocation, MessageSearchFilters, MessageSearchResult};
 use super::{
     ChannelMessagePayload, ChannelMessageWithState, MeshChannel, MeshDevice, MeshNode,
-    MeshNodeDeviceMetrics, MeshNodeEnvironmentMetrics, NeighborInfoPacket, NormalizedWaypoint,
-    PositionPacket, SerialDeviceStatus, TelemetryPacket, TextPacket, UserPacket, WaypointPacket,
+    MeshNodeDeviceMetrics, MeshNodeEnvironmentMetrics, MessageReaction, NeighborInfoPacket,
+    NormalizedWaypoint, PositionPacket, SerialDeviceStatus, TelemetryPacket, TextPacket,
+    UserPacket, WaypointPacket,
 };
 
 use crate::device::{ChannelMessageState, LastHeardMetadata};
+use crate::state::DeviceKey;
+
+const BROADCAST_NODE_NUM: u32 = u32::MAX;
 
 impl MeshDevice {
     pub fn set_ready(&mut self, ready: bool) {
@@ -192,25 +197,132 @@ impl MeshDevice {
         }
     }
 
-    pub fn add_channel(&mut self, channel: MeshChannel) {
+    pub fn add_channel(&mut self, mut channel: MeshChannel) {
         debug!("Adding device channel at index {}", channel.config.index);
         trace!("{:?}", channel);
 
-        self.channels.insert(
-            channel
-                .config
-                .index
-                .try_into()
-                .expect("Channel id out of u32 range"),
-            channel,
+        let channel_id = channel
+            .config
+            .index
+            .try_into()
+            .expect("Channel id out of u32 range");
+
+        // Keep the history of a channel that's being reconfigured, which
+        // also keeps the message index pointing at the right messages
+        if let Some(existing_channel) = self.channels.remove(&channel_id) {
+            channel.messages = existing_channel.messages;
+        }
+
+        self.channels.insert(channel_id, channel);
+    }
+
+    pub fn set_channel_psk(&mut self, channel_id: u32, psk: Vec<u8>) -> Option<protobufs::Channel> {
+        let channel = self.channels.get_mut(&channel_id)?;
+        let settings = channel.config.settings.as_mut()?;
+
+        debug!(
+            "Setting PSK of channel {} to {} byte key",
+            channel_id,
+            psk.len()
         );
+
+        settings.psk = psk;
+
+        Some(channel.config.clone())
+    }
+
+    pub fn set_canned_messages(&mut self, node_num: u32, messages: Vec<String>) {
+        debug!(
+            "Setting {} canned messages of node {}",
+            messages.len(),
+            node_num
+        );
+
+        // The list read from the node supersedes any unconfirmed writes
+        self.pending_canned_messages
+            .retain(|_, (pending_node_num, _)| *pending_node_num != node_num);
+
+        self.canned_messages.insert(node_num, messages);
     }
 
+    /// Records a canned message list written to `node_num` in packet
+    /// `packet_id`. The list is only stored once the node confirms it.
+    pub fn add_pending_canned_messages(
+        &mut self,
+        packet_id: u32,
+        node_num: u32,
+        messages: Vec<String>,
+    ) {
+        debug!(
+            "Waiting for node {} to confirm canned messages sent in packet {}",
+            node_num, packet_id
+        );
+
+        self.pending_canned_messages
+            .insert(packet_id, (node_num, messages));
+    }
+
+    /// Resolves the canned message write sent in packet `packet_id`, storing
+    /// its list if the packet was acknowledged. Returns whether a list was stored.
+    pub fn resolve_pending_canned_messages(&mut self, packet_id: u32, acknowledged: bool) -> bool {
+        let (node_num, messages) = match self.pending_canned_messages.remove(&packet_id) {
+            Some(pending) => pending,
+            None => return false,
+        };
+
+        if !acknowledged {
+            debug!("Node {} didn't confirm its canned messages", node_num);
+            return false;
+        }
+
+        self.set_canned_messages(node_num, messages);
+
+        true
+    }
+
+    /// Adds a waypoint, replacing any existing waypoint with the same id.
+    /// Expired waypoints are treated as deletions, since deletions are sent
+    /// to the mesh as waypoints that have already expired.
     pub fn add_waypoint(&mut self, waypoint: NormalizedWaypoint) {
+        if waypoint.is_expired(get_current_time_u32()) {
+            debug!("Removing expired waypoint: {:?}", waypoint);
+            self.waypoints.remove(&waypoint.id);
+            return;
+        }
+
         debug!("Adding own managed waypoint: {:?}", waypoint);
         self.waypoints.insert(waypoint.id, waypoint);
     }
 
+    /// Checks whether `node_num` may replace or delete the waypoint with the
+    /// given id. Waypoints that don't exist yet can always be created.
+    pub fn check_waypoint_editable(&self, waypoint_id: u32, node_num: u32) -> Result<(), String> {
+        match self.waypoints.get(&waypoint_id) {
+            Some(waypoint) if !waypoint.can_be_edited_by(node_num) => Err(format!(
+                "Waypoint {} is locked to node {}",
+                waypoint_id, waypoint.locked_to
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes all waypoints that have expired as of `now`, returning their ids.
+    pub fn remove_expired_waypoints(&mut self, now: u32) -> Vec<u32> {
+        let expired_ids: Vec<u32> = self
+            .waypoints
+            .values()
+            .filter(|waypoint| waypoint.is_expired(now))
+            .map(|waypoint| waypoint.id)
+            .collect();
+
+        for waypoint_id in expired_ids.iter() {
+            debug!("Waypoint {} expired", waypoint_id);
+            self.waypoints.remove(waypoint_id);
+        }
+
+        expired_ids
+    }
+
     pub fn add_node_info(&mut self, node_info: protobufs::NodeInfo) {
         let found_node = self.nodes.get_mut(&node_info.num);
 
@@ -268,7 +380,7 @@ impl MeshDevice {
                 position.packet.from,
                 position.data
             );
-            node.position_metrics.push(position.data.into());
+            node.add_position(position.data.into());
         } else {
             trace!(
                 "Adding position to new node {:?}: {:?}",
@@ -277,7 +389,7 @@ impl MeshDevice {
             );
 
             let mut new_node = MeshNode::new(self.my_node_info.my_node_num);
-            new_node.position_metrics.push(position.data.into());
+            new_node.add_position(position.data.into());
 
             self.nodes.insert(position.packet.from, new_node);
         }
@@ -303,40 +415,239 @@ impl MeshDevice {
         }
     }
 
-    pub fn add_text_message(&mut self, message: TextPacket) {
-        let channel = self.channels.get_mut(&message.packet.channel);
+    /// Adds a text message to its channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The received or sent text message.
+    /// * `reply_to` - The packet id of the message this replies to, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether this is a new message rather than a repeat of one
+    ///   already received.
+    pub fn add_text_message(&mut self, message: TextPacket, reply_to: Option<u32>) -> bool {
+        debug!(
+            "Adding text message to channel {:?}: {:?}",
+            message.packet.channel, message.data
+        );
 
-        if let Some(ch) = channel {
-            debug!(
-                "Adding text message to channel {:?}: {:?}",
-                message.packet.channel, message.data
-            );
+        let text = message.data.clone();
+        self.push_channel_message(ChannelMessagePayload::Text(message), &text, reply_to)
+    }
 
-            ch.last_interaction = get_current_time_u32();
+    /// Adds the emoji reaction sent in `packet` to the message sent in packet
+    /// `reply_id`. Returns whether the message was found.
+    pub fn add_reaction(
+        &mut self,
+        packet: &protobufs::MeshPacket,
+        reply_id: u32,
+        emoji: String,
+    ) -> bool {
+        let node_num = packet.from;
 
-            ch.messages.push(ChannelMessageWithState {
-                payload: ChannelMessagePayload::Text(message),
-                state: ChannelMessageState::Pending,
-            });
+        let location = match self.find_replied_message(packet, reply_id) {
+            Some(l) => l,
+            None => return false,
+        };
+
+        let message = match self.get_message_mut(&location) {
+            Some(m) => m,
+            None => return false,
+        };
+
+        debug!(
+            "Adding reaction {} from node {} to message {}",
+            emoji, node_num, reply_id
+        );
+
+        match message.reactions.iter_mut().find(|r| r.emoji == emoji) {
+            Some(reaction) => {
+                if !reaction.node_nums.contains(&node_num) {
+                    reaction.node_nums.push(node_num);
+                }
+            }
+            None => message.reactions.push(MessageReaction {
+                emoji,
+                node_nums: vec![node_num],
+            }),
         }
+
+        true
     }
 
-    pub fn add_waypoint_message(&mut self, message: WaypointPacket) {
-        let channel = self.channels.get_mut(&message.packet.channel);
+    pub fn add_waypoint_message(&mut self, message: WaypointPacket) -> bool {
+        debug!(
+            "Adding waypoint message to channel {:?}: {:?}",
+            message.packet.channel, message.data
+        );
 
-        if let Some(ch) = channel {
-            debug!(
-                "Adding waypoint message to channel {:?}: {:?}",
-                message.packet.channel, message.data
-            );
+        let text = format!("{} {}", message.data.name, message.data.description);
+        self.push_channel_message(ChannelMessagePayload::Waypoint(message), &text, None)
+    }
+
+    /// Adds a message to its channel, or records another reception of it if
+    /// the same packet has already been seen. Messages this device sends are
+    /// echoed back here when they're sent, so they're recorded with the
+    /// packet id that routing acknowledgements will refer to.
+    fn push_channel_message(
+        &mut self,
+        payload: ChannelMessagePayload,
+        text: &str,
+        reply_to: Option<u32>,
+    ) -> bool {
+        let packet = match &payload {
+            ChannelMessagePayload::Text(t) => &t.packet,
+            ChannelMessagePayload::Waypoint(w) => &w.packet,
+        };
+
+        let (channel_id, from, packet_id) = (packet.channel, packet.from, packet.id);
+        let now = get_current_time_u32();
+
+        let parent_location =
+            reply_to.and_then(|reply_id| self.find_replied_message(packet, reply_id));
+
+        // Only packets heard over the air are stamped with a receive time
+        let rx_snr = (packet.rx_time != 0).then_some(packet.rx_snr);
+        let time = if packet.rx_time != 0 {
+            packet.rx_time
+        } else {
+            now
+        };
+
+        // Rebroadcasts and store and forward replays repeat the original packet
+        if let Some(location) = self.message_index.find(from, packet_id) {
+            if let Some(message) = self.get_message_mut(&location) {
+                message.heard_count += 1;
+
+                if let Some(snr) = rx_snr {
+                    message.best_rx_snr =
+                        Some(message.best_rx_snr.map_or(snr, |best| best.max(snr)));
+                }
+
+                trace!(
+                    "Heard message {} from {} {} times",
+                    packet_id,
+                    from,
+                    message.heard_count
+                );
+            }
+
+            return false;
+        }
+
+        let channel = match self.channels.get_mut(&channel_id) {
+            Some(ch) => ch,
+            None => return false,
+        };
+
+        channel.last_interaction = now;
+        channel.messages.push(ChannelMessageWithState {
+            payload,
+            state: ChannelMessageState::Pending,
+            reply_to,
+            reply_ids: vec![],
+            reactions: vec![],
+            heard_count: 1,
+            best_rx_snr: rx_snr,
+        });
+
+        let location = MessageLocation {
+            channel_id,
+            index: channel.messages.len() - 1,
+        };
+
+        // Link replies to their parent so threads can be read from either end
+        if let Some(parent_location) = parent_location {
+            if let Some(parent) = self.get_message_mut(&parent_location) {
+                parent.reply_ids.push(packet_id);
+            }
+        }
+
+        self.message_index
+            .insert(location, from, packet_id, reply_to, time, text);
+
+        // Replies can be heard before the message they reply to, so link any
+        // that were stored before their parent arrived
+        let early_reply_ids: Vec<u32> = self
+            .message_index
+            .find_replies(packet_id)
+            .iter()
+            .filter(|reply_location| **reply_location != location)
+            .filter_map(|reply_location| {
+                let reply = match &self.get_message(reply_location)?.payload {
+                    ChannelMessagePayload::Text(t) => &t.packet,
+                    ChannelMessagePayload::Waypoint(w) => &w.packet,
+                };
+
+                (self.find_replied_message(reply, packet_id) == Some(location)).then_some(reply.id)
+            })
+            .collect();
+
+        if !early_reply_ids.is_empty() {
+            if let Some(message) = self.get_message_mut(&location) {
+                message.reply_ids.extend(early_reply_ids);
+            }
+        }
+
+        true
+    }
+
+    /// Finds the message that `reply` replies or reacts to. Replies only carry
+    /// the packet id of their parent, and packet ids are only unique per
+    /// sender, so a parent is only returned when it can't be confused with
+    /// another message that has the same id.
+    fn find_replied_message(
+        &self,
+        reply: &protobufs::MeshPacket,
+        reply_id: u32,
+    ) -> Option<MessageLocation> {
+        let mut candidates = self
+            .message_index
+            .find_by_packet_id(reply_id)
+            .iter()
+            .filter(|location| location.channel_id == reply.channel)
+            .filter(|location| {
+                // A direct message can only reply to a message between the same two nodes
+                if reply.to == BROADCAST_NODE_NUM {
+                    return true;
+                }
 
-            ch.last_interaction = get_current_time_u32();
+                self.get_message(location).map_or(false, |message| {
+                    let sender = match &message.payload {
+                        ChannelMessagePayload::Text(t) => t.packet.from,
+                        ChannelMessagePayload::Waypoint(w) => w.packet.from,
+                    };
 
-            ch.messages.push(ChannelMessageWithState {
-                payload: ChannelMessagePayload::Waypoint(message),
-                state: ChannelMessageState::Pending,
+                    sender == reply.from || sender == reply.to
+                })
             });
+
+        let location = candidates.next()?.clone();
+
+        if candidates.next().is_some() {
+            debug!(
+                "Packet id {} is shared by several messages on channel {}, not linking reply",
+                reply_id, reply.channel
+            );
+            return None;
         }
+
+        Some(location)
+    }
+
+    pub fn increment_encrypted_packet_count(&mut self, channel_hash: u32) {
+        let count = self
+            .encrypted_packet_counts
+            .entry(channel_hash)
+            .or_insert(0);
+        *count += 1;
+
+        trace!(
+            "Received {} undecryptable packets on channel hash {}",
+            count,
+            channel_hash
+        );
     }
 
     // TODO add device metadata
@@ -347,20 +658,467 @@ impl MeshDevice {
         message_id: u32,
         state: ChannelMessageState,
     ) {
-        let channel = self.channels.get_mut(&channel_id);
-
-        if let Some(ch) = channel {
-            let message = ch
-                .messages
-                .iter_mut()
-                .find(|message| match message.payload.clone() {
-                    ChannelMessagePayload::Text(t) => t.packet.id == message_id,
-                    ChannelMessagePayload::Waypoint(w) => w.packet.id == message_id,
+        // Acknowledgements refer to packets this device sent, and other nodes
+        // may have sent messages with the same packet id
+        let location = match self
+            .message_index
+            .find(self.my_node_info.my_node_num, message_id)
+        {
+            Some(l) => l,
+            None => {
+                trace!(
+                    "No sent message {} on channel {} to update",
+                    message_id,
+                    channel_id
+                );
+                return;
+            }
+        };
+
+        if let Some(message) = self.get_message_mut(&location) {
+            message.state = state;
+        }
+    }
+
+    pub fn get_message(&self, location: &MessageLocation) -> Option<&ChannelMessageWithState> {
+        self.channels
+            .get(&location.channel_id)?
+            .messages
+            .get(location.index)
+    }
+
+    fn get_message_mut(
+        &mut self,
+        location: &MessageLocation,
+    ) -> Option<&mut ChannelMessageWithState> {
+        self.channels
+            .get_mut(&location.channel_id)?
+            .messages
+            .get_mut(location.index)
+    }
+
+    /// Searches the messages of all channels by text and filters.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_key` - The key of this device, used to label results.
+    /// * `query` - Words that must all appear in a message. Empty to match every message.
+    /// * `filters` - Restrictions on sender, channel, time and delivery state.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<MessageSearchResult>` - Matching messages from oldest to newest.
+    pub fn search_messages(
+        &self,
+        device_key: &DeviceKey,
+        query: &str,
+        filters: &MessageSearchFilters,
+    ) -> Vec<MessageSearchResult> {
+        let mut results: Vec<MessageSearchResult> = self
+            .message_index
+            .search(query, filters)
+            .into_iter()
+            .filter_map(|location| {
+                let message = self.get_message(&location)?;
+
+                let state_matches = filters.state.as_ref().map_or(true, |state| {
+                    std::mem::discriminant(state) == std::mem::discriminant(&message.state)
                 });
 
-            if let Some(m) = message {
-                m.state = state;
-            }
+                if !state_matches {
+                    return None;
+                }
+
+                Some(MessageSearchResult {
+                    device_key: device_key.clone(),
+                    channel_id: location.channel_id,
+                    time: self.message_index.get_time(&location)?,
+                    message: message.clone(),
+                })
+            })
+            .collect();
+
+        if let Some(limit) = filters.limit {
+            let excess = results.len().saturating_sub(limit as usize);
+            results.drain(..excess);
         }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MY_NODE_NUM: u32 = 1;
+
+    fn build_device() -> MeshDevice {
+        let mut device = MeshDevice::new();
+        device.my_node_info.my_node_num = MY_NODE_NUM;
+
+        for index in 0..2 {
+            device.add_channel(build_channel(index, "Primary"));
+        }
+
+        device
+    }
+
+    fn build_channel(index: i32, name: &str) -> MeshChannel {
+        MeshChannel {
+            config: protobufs::Channel {
+                index,
+                settings: Some(protobufs::ChannelSettings {
+                    name: name.into(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn build_text(channel: u32, from: u32, id: u32, rx_time: u32, text: &str) -> TextPacket {
+        TextPacket {
+            packet: protobufs::MeshPacket {
+                channel,
+                from,
+                to: BROADCAST_NODE_NUM,
+                id,
+                rx_time,
+                ..Default::default()
+            },
+            data: text.into(),
+        }
+    }
+
+    fn search(device: &MeshDevice, filters: MessageSearchFilters) -> Vec<MessageSearchResult> {
+        device.search_messages(&"device".to_string(), "", &filters)
+    }
+
+    #[test]
+    fn search_filters_by_delivery_state() {
+        let mut device = build_device();
+
+        for id in 1..=3 {
+            device.add_text_message(build_text(0, MY_NODE_NUM, id, 0, "Hello"), None);
+        }
+
+        device.set_message_state(0, 2, ChannelMessageState::Acknowledged);
+        device.set_message_state(0, 3, ChannelMessageState::Error("No route".into()));
+
+        let found_ids = |state: ChannelMessageState| -> Vec<u32> {
+            search(
+                &device,
+                MessageSearchFilters {
+                    state: Some(state),
+                    ..Default::default()
+                },
+            )
+            .iter()
+            .map(|result| match &result.message.payload {
+                ChannelMessagePayload::Text(t) => t.packet.id,
+                ChannelMessagePayload::Waypoint(w) => w.packet.id,
+            })
+            .collect()
+        };
+
+        assert_eq!(found_ids(ChannelMessageState::Pending), vec![1]);
+        assert_eq!(found_ids(ChannelMessageState::Acknowledged), vec![2]);
+
+        // Any error matches an error filter, whatever its reason
+        assert_eq!(
+            found_ids(ChannelMessageState::Error(String::new())),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn search_limit_keeps_newest_results() {
+        let mut device = build_device();
+
+        for (id, rx_time) in [(1, 300), (2, 100), (3, 200)] {
+            device.add_text_message(build_text(0, 2, id, rx_time, "Hello"), None);
+        }
+
+        let results = search(
+            &device,
+            MessageSearchFilters {
+                limit: Some(2),
+                ..Default::default()
+            },
+        );
+        let times: Vec<u32> = results.iter().map(|result| result.time).collect();
+
+        assert_eq!(times, vec![200, 300]);
+    }
+
+    #[test]
+    fn reconfiguring_channel_keeps_history() {
+        let mut device = build_device();
+        device.add_text_message(build_text(1, 2, 10, 100, "Before the change"), None);
+
+        device.add_channel(build_channel(1, "Renamed"));
+
+        let channel = &device.channels[&1];
+        assert_eq!(channel.config.settings.as_ref().unwrap().name, "Renamed");
+        assert_eq!(channel.messages.len(), 1);
+
+        // The index still points at the kept message
+        let results = device.search_messages(
+            &"device".to_string(),
+            "before",
+            &MessageSearchFilters::default(),
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].channel_id, 1);
+    }
+
+    #[test]
+    fn set_message_state_finds_message_through_index() {
+        let mut device = build_device();
+        device.add_text_message(build_text(0, 2, 5, 100, "Other channel"), None);
+        device.add_text_message(build_text(1, MY_NODE_NUM, 6, 0, "Sent"), None);
+
+        // The acknowledgement doesn't need to name the channel the message is on
+        device.set_message_state(0, 6, ChannelMessageState::Acknowledged);
+
+        assert!(matches!(
+            device.channels[&1].messages[0].state,
+            ChannelMessageState::Acknowledged
+        ));
+        assert!(matches!(
+            device.channels[&0].messages[0].state,
+            ChannelMessageState::Pending
+        ));
+    }
+
+    #[test]
+    fn counts_repeated_receptions() {
+        let mut device = build_device();
+
+        let mut first = build_text(0, 2, 10, 100, "Hello");
+        first.packet.rx_snr = -4.0;
+        let mut rebroadcast = build_text(0, 2, 10, 110, "Hello");
+        rebroadcast.packet.rx_snr = 6.5;
+        let mut weaker = build_text(0, 2, 10, 120, "Hello");
+        weaker.packet.rx_snr = -10.0;
+
+        assert!(device.add_text_message(first, None));
+        assert!(!device.add_text_message(rebroadcast, None));
+        assert!(!device.add_text_message(weaker, None));
+
+        let messages = &device.channels[&0].messages;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].heard_count, 3);
+        assert_eq!(messages[0].best_rx_snr, Some(6.5));
+    }
+
+    #[test]
+    fn same_packet_id_from_another_sender_is_a_new_message() {
+        let mut device = build_device();
+
+        assert!(device.add_text_message(build_text(0, 2, 10, 100, "From two"), None));
+        assert!(device.add_text_message(build_text(0, 3, 10, 110, "From three"), None));
+
+        let messages = &device.channels[&0].messages;
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|message| message.heard_count == 1));
+    }
+
+    #[test]
+    fn acknowledgements_only_update_sent_messages() {
+        let mut device = build_device();
+        device.add_text_message(build_text(0, 2, 7, 100, "Received"), None);
+        device.add_text_message(build_text(0, MY_NODE_NUM, 7, 0, "Sent"), None);
+        device.add_text_message(build_text(0, 3, 8, 200, "Also received"), None);
+
+        device.set_message_state(0, 7, ChannelMessageState::Acknowledged);
+        device.set_message_state(0, 8, ChannelMessageState::Acknowledged);
+
+        let messages = &device.channels[&0].messages;
+        assert!(matches!(messages[0].state, ChannelMessageState::Pending));
+        assert!(matches!(
+            messages[1].state,
+            ChannelMessageState::Acknowledged
+        ));
+        assert!(matches!(messages[2].state, ChannelMessageState::Pending));
+    }
+
+    #[test]
+    fn canned_messages_are_stored_once_confirmed() {
+        let mut device = build_device();
+        let messages = vec!["On my way".to_string()];
+
+        device.add_pending_canned_messages(30, 2, messages.clone());
+        device.add_pending_canned_messages(31, 3, messages.clone());
+        assert!(device.canned_messages.is_empty());
+
+        assert!(device.resolve_pending_canned_messages(30, true));
+        assert!(!device.resolve_pending_canned_messages(31, false));
+        assert!(!device.resolve_pending_canned_messages(32, true));
+
+        assert_eq!(device.canned_messages.get(&2), Some(&messages));
+        assert!(!device.canned_messages.contains_key(&3));
+        assert!(device.pending_canned_messages.is_empty());
+
+        // A list read from the node replaces an unconfirmed write
+        device.add_pending_canned_messages(33, 2, vec!["Stale".into()]);
+        device.set_canned_messages(2, messages.clone());
+        assert!(!device.resolve_pending_canned_messages(33, true));
+        assert_eq!(device.canned_messages.get(&2), Some(&messages));
+    }
+
+    fn get_text_message(
+        device: &MeshDevice,
+        channel_id: u32,
+        index: usize,
+    ) -> &ChannelMessageWithState {
+        &device.channels[&channel_id].messages[index]
+    }
+
+    #[test]
+    fn links_replies_to_their_parent() {
+        let mut device = build_device();
+        device.add_text_message(build_text(0, 2, 10, 100, "Anyone at camp?"), None);
+        device.add_text_message(build_text(0, 3, 11, 200, "Yes"), Some(10));
+        device.add_text_message(build_text(0, 4, 12, 300, "Me too"), Some(10));
+
+        let parent = get_text_message(&device, 0, 0);
+        assert_eq!(parent.reply_ids, vec![11, 12]);
+        assert_eq!(get_text_message(&device, 0, 1).reply_to, Some(10));
+
+        // A reply to a message on another channel isn't linked
+        device.add_text_message(build_text(1, 3, 13, 400, "Wrong channel"), Some(10));
+        assert_eq!(get_text_message(&device, 0, 0).reply_ids, vec![11, 12]);
+    }
+
+    #[test]
+    fn links_replies_heard_before_their_parent() {
+        let mut device = build_device();
+        device.add_text_message(build_text(0, 3, 11, 200, "Yes"), Some(10));
+        device.add_text_message(build_text(0, 4, 12, 300, "Me too"), Some(10));
+
+        // The reply's parent hasn't been heard yet
+        assert!(get_text_message(&device, 0, 0).reply_ids.is_empty());
+
+        device.add_text_message(build_text(0, 2, 10, 100, "Anyone at camp?"), None);
+        assert_eq!(get_text_message(&device, 0, 2).reply_ids, vec![11, 12]);
+
+        // A message with the same packet id on another channel isn't their parent
+        device.add_text_message(build_text(1, 2, 10, 100, "Anyone at camp?"), None);
+        assert!(get_text_message(&device, 1, 0).reply_ids.is_empty());
+
+        // Neither is a repeat of the parent
+        device.add_text_message(build_text(0, 2, 10, 110, "Anyone at camp?"), None);
+        assert_eq!(get_text_message(&device, 0, 2).reply_ids, vec![11, 12]);
+    }
+
+    #[test]
+    fn aggregates_reactions_once_per_node() {
+        let mut device = build_device();
+        device.add_text_message(build_text(0, 2, 10, 100, "Summit reached"), None);
+
+        for (from, emoji) in [(3, "👍"), (4, "👍"), (3, "👍"), (3, "🎉")] {
+            let reaction = build_text(0, from, 20 + from, 200, emoji).packet;
+            assert!(device.add_reaction(&reaction, 10, emoji.into()));
+        }
+
+        let reactions = &get_text_message(&device, 0, 0).reactions;
+        assert_eq!(reactions.len(), 2);
+        assert_eq!(reactions[0].emoji, "👍");
+        assert_eq!(reactions[0].node_nums, vec![3, 4]);
+        assert_eq!(reactions[1].emoji, "🎉");
+        assert_eq!(reactions[1].node_nums, vec![3]);
+    }
+
+    #[test]
+    fn drops_reactions_to_unknown_messages() {
+        let mut device = build_device();
+        device.add_text_message(build_text(0, 2, 10, 100, "Summit reached"), None);
+
+        let reaction = build_text(0, 3, 20, 200, "👍").packet;
+        assert!(!device.add_reaction(&reaction, 99, "👍".into()));
+
+        let reaction = build_text(1, 3, 21, 200, "👍").packet;
+        assert!(!device.add_reaction(&reaction, 10, "👍".into()));
+
+        assert!(get_text_message(&device, 0, 0).reactions.is_empty());
+    }
+
+    #[test]
+    fn does_not_confuse_messages_sharing_a_packet_id() {
+        let mut device = build_device();
+        device.add_text_message(build_text(0, 2, 10, 100, "From two"), None);
+        device.add_text_message(build_text(0, 3, 10, 200, "From three"), None);
+
+        // A broadcast reaction can't tell which of the two messages it's for
+        let reaction = build_text(0, 4, 20, 300, "👍").packet;
+        assert!(!device.add_reaction(&reaction, 10, "👍".into()));
+
+        // A direct message narrows the parent down to its two nodes
+        let mut reaction = build_text(0, 4, 21, 300, "👍").packet;
+        reaction.to = 3;
+        assert!(device.add_reaction(&reaction, 10, "👍".into()));
+
+        assert!(get_text_message(&device, 0, 0).reactions.is_empty());
+        assert_eq!(
+            get_text_message(&device, 0, 1).reactions[0].node_nums,
+            vec![4]
+        );
+    }
+
+    fn build_waypoint(id: u32, expire: u32, locked_to: u32) -> NormalizedWaypoint {
+        protobufs::Waypoint {
+            id,
+            expire,
+            locked_to,
+            ..Default::default()
+        }
+        .into()
+    }
+
+    #[test]
+    fn expired_waypoints_are_deletions() {
+        let mut device = MeshDevice::new();
+
+        device.add_waypoint(build_waypoint(1, 0, 0));
+        assert!(device.waypoints.contains_key(&1));
+
+        device.add_waypoint(build_waypoint(1, 1, 0));
+        assert!(!device.waypoints.contains_key(&1));
+
+        // Expired waypoints that were never seen aren't added
+        device.add_waypoint(build_waypoint(2, 1, 0));
+        assert!(device.waypoints.is_empty());
+    }
+
+    #[test]
+    fn removes_only_expired_waypoints() {
+        let mut device = MeshDevice::new();
+        let now = get_current_time_u32();
+
+        device.add_waypoint(build_waypoint(1, 0, 0));
+        device.add_waypoint(build_waypoint(2, now + 60, 0));
+        device.add_waypoint(build_waypoint(3, now + 600, 0));
+
+        assert_eq!(device.remove_expired_waypoints(now + 60), vec![2]);
+        assert!(device.remove_expired_waypoints(now + 60).is_empty());
+
+        let mut waypoint_ids: Vec<u32> = device.waypoints.keys().cloned().collect();
+        waypoint_ids.sort();
+        assert_eq!(waypoint_ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn locked_waypoints_are_not_editable_by_other_nodes() {
+        let mut device = MeshDevice::new();
+        device.add_waypoint(build_waypoint(1, 0, 2));
+
+        assert!(device.check_waypoint_editable(1, 2).is_ok());
+        assert!(device.check_waypoint_editable(1, 3).is_err());
+
+        // Waypoints that don't exist yet can be created by anyone
+        assert!(device.check_waypoint_editable(4, 3).is_ok());
     }
 }
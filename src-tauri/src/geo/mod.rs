@@ -0,0 +1,210 @@
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use crate::device::NormalizedPosition;
+
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+const METERS_PER_DEGREE_LATITUDE: f64 = 111_320.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoPoint {
+    pub latitude: f64,  // degrees
+    pub longitude: f64, // degrees
+}
+
+impl From<&NormalizedPosition> for GeoPoint {
+    fn from(position: &NormalizedPosition) -> Self {
+        Self {
+            latitude: position.latitude,
+            longitude: position.longitude,
+        }
+    }
+}
+
+/// A rectangular area between two corners
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoBounds {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+}
+
+impl GeoBounds {
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        (self.min_latitude..=self.max_latitude).contains(&point.latitude)
+            && (self.min_longitude..=self.max_longitude).contains(&point.longitude)
+    }
+
+    /// Number of rows and columns `sample_grid` produces with `spacing_meters`
+    /// between points, without sampling the grid.
+    pub fn grid_size(&self, spacing_meters: f64) -> (usize, usize) {
+        let (latitude_step, longitude_step) = self.grid_steps(spacing_meters);
+
+        (
+            count_grid_steps(self.max_latitude - self.min_latitude, latitude_step),
+            count_grid_steps(self.max_longitude - self.min_longitude, longitude_step),
+        )
+    }
+
+    /// Samples the bounds on a grid with roughly `spacing_meters` between points.
+    pub fn sample_grid(&self, spacing_meters: f64) -> Vec<GeoPoint> {
+        let (latitude_step, longitude_step) = self.grid_steps(spacing_meters);
+        let (rows, columns) = self.grid_size(spacing_meters);
+
+        let mut points = Vec::with_capacity(rows * columns);
+
+        for row in 0..rows {
+            let latitude = self.min_latitude + latitude_step * (row as f64 + 0.5);
+
+            for column in 0..columns {
+                points.push(GeoPoint {
+                    latitude,
+                    longitude: self.min_longitude + longitude_step * (column as f64 + 0.5),
+                });
+            }
+        }
+
+        points
+    }
+
+    fn grid_steps(&self, spacing_meters: f64) -> (f64, f64) {
+        let center_latitude = (self.min_latitude + self.max_latitude) / 2.0;
+
+        (
+            meters_to_latitude_degrees(spacing_meters),
+            meters_to_longitude_degrees(spacing_meters, center_latitude),
+        )
+    }
+}
+
+/// Counts grid points offset half a step from the start of a span. Saturates
+/// rather than overflowing for tiny steps.
+fn count_grid_steps(span: f64, step: f64) -> usize {
+    if span < step / 2.0 {
+        return 0;
+    }
+
+    ((span - step / 2.0) / step).floor() as usize + 1
+}
+
+pub fn meters_to_latitude_degrees(meters: f64) -> f64 {
+    meters / METERS_PER_DEGREE_LATITUDE
+}
+
+pub fn meters_to_longitude_degrees(meters: f64, latitude: f64) -> f64 {
+    // Clamp to avoid dividing by zero at the poles
+    meters / (METERS_PER_DEGREE_LATITUDE * latitude.to_radians().cos().max(0.01))
+}
+
+/// Great-circle distance between two points, in meters.
+pub fn distance_meters(from: &GeoPoint, to: &GeoPoint) -> f64 {
+    let from_latitude = from.latitude.to_radians();
+    let to_latitude = to.latitude.to_radians();
+    let delta_latitude = (to.latitude - from.latitude).to_radians();
+    let delta_longitude = (to.longitude - from.longitude).to_radians();
+
+    let a = (delta_latitude / 2.0).sin().powi(2)
+        + from_latitude.cos() * to_latitude.cos() * (delta_longitude / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Initial bearing from one point to another, in degrees clockwise from north.
+pub fn bearing_degrees(from: &GeoPoint, to: &GeoPoint) -> f64 {
+    let from_latitude = from.latitude.to_radians();
+    let to_latitude = to.latitude.to_radians();
+    let delta_longitude = (to.longitude - from.longitude).to_radians();
+
+    let y = delta_longitude.sin() * to_latitude.cos();
+    let x = from_latitude.cos() * to_latitude.sin()
+        - from_latitude.sin() * to_latitude.cos() * delta_longitude.cos();
+
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+/// The point reached by travelling a distance along a bearing from a start point.
+pub fn destination_point(from: &GeoPoint, bearing_degrees: f64, distance_meters: f64) -> GeoPoint {
+    let angular_distance = distance_meters / EARTH_RADIUS_METERS;
+    let bearing = bearing_degrees.to_radians();
+    let from_latitude = from.latitude.to_radians();
+    let from_longitude = from.longitude.to_radians();
+
+    let latitude = (from_latitude.sin() * angular_distance.cos()
+        + from_latitude.cos() * angular_distance.sin() * bearing.cos())
+    .asin();
+
+    let longitude = from_longitude
+        + (bearing.sin() * angular_distance.sin() * from_latitude.cos())
+            .atan2(angular_distance.cos() - from_latitude.sin() * latitude.sin());
+
+    GeoPoint {
+        latitude: latitude.to_degrees(),
+        longitude: (longitude.to_degrees() + 540.0) % 360.0 - 180.0,
+    }
+}
+
+/// Approximates a circle on the ground as a closed ring of `[longitude, latitude]`
+/// coordinates, as used by GeoJSON polygons.
+pub fn circle_ring(center: &GeoPoint, radius_meters: f64, vertex_count: u32) -> Vec<Vec<f64>> {
+    let mut ring: Vec<Vec<f64>> = (0..vertex_count)
+        .map(|i| {
+            let bearing = 360.0 * f64::from(i) / f64::from(vertex_count);
+            let point = destination_point(center, bearing, radius_meters);
+            vec![point.longitude, point.latitude]
+        })
+        .collect();
+
+    if let Some(first) = ring.first().cloned() {
+        ring.push(first);
+    }
+
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_and_destination() {
+        let from = GeoPoint {
+            latitude: 0.0,
+            longitude: 0.0,
+        };
+        let to = GeoPoint {
+            latitude: 0.0,
+            longitude: 1.0,
+        };
+
+        assert!((distance_meters(&from, &to) - 111_195.0).abs() < 10.0);
+        assert!((bearing_degrees(&from, &to) - 90.0).abs() < 1e-6);
+
+        let destination = destination_point(&from, 90.0, distance_meters(&from, &to));
+        assert!((destination.longitude - 1.0).abs() < 1e-6);
+        assert!(destination.latitude.abs() < 1e-6);
+    }
+
+    #[test]
+    fn grid_size_matches_sampled_grid() {
+        let bounds = GeoBounds {
+            min_latitude: 0.0,
+            min_longitude: 0.0,
+            max_latitude: 0.05,
+            max_longitude: 0.02,
+        };
+
+        let (rows, columns) = bounds.grid_size(1000.0);
+        let points = bounds.sample_grid(1000.0);
+
+        assert_eq!((rows, columns), (6, 2));
+        assert_eq!(points.len(), rows * columns);
+        assert!(points.iter().all(|p| bounds.contains(p)));
+
+        // Tiny spacings saturate instead of overflowing
+        let (rows, columns) = bounds.grid_size(f64::MIN_POSITIVE);
+        assert_eq!(rows.saturating_mul(columns), usize::MAX);
+    }
+}
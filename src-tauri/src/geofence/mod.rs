@@ -0,0 +1,467 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, trace, warn};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use crate::geo::{distance_meters, GeoPoint};
+
+pub const GEOFENCES_FILE_NAME: &str = "geofences.json";
+
+const MAX_GEOFENCE_HISTORY: usize = 10_000;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type")]
+pub enum GeofenceShape {
+    Circle {
+        center: GeoPoint,
+        radius_meters: f64,
+    },
+    Polygon {
+        vertices: Vec<GeoPoint>, // in order around the boundary, closing edge implied
+    },
+}
+
+impl GeofenceShape {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            GeofenceShape::Circle { radius_meters, .. } if *radius_meters <= 0.0 => {
+                Err("Circle radius must be greater than zero".into())
+            }
+            GeofenceShape::Polygon { vertices } if vertices.len() < 3 => {
+                Err("Polygon must have at least three vertices".into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        match self {
+            GeofenceShape::Circle {
+                center,
+                radius_meters,
+            } => distance_meters(center, point) <= *radius_meters,
+            GeofenceShape::Polygon { vertices } => polygon_contains(vertices, point),
+        }
+    }
+}
+
+/// Ray casting point-in-polygon test, treating coordinates as planar. This is
+/// accurate enough for fences that don't span the antimeridian or a pole.
+fn polygon_contains(vertices: &[GeoPoint], point: &GeoPoint) -> bool {
+    let mut inside = false;
+    let mut previous = match vertices.last() {
+        Some(vertex) => vertex,
+        None => return false,
+    };
+
+    for vertex in vertices {
+        let crosses = (vertex.latitude > point.latitude) != (previous.latitude > point.latitude);
+
+        if crosses {
+            let intersect_longitude = vertex.longitude
+                + (point.latitude - vertex.latitude) * (previous.longitude - vertex.longitude)
+                    / (previous.latitude - vertex.latitude);
+
+            if point.longitude < intersect_longitude {
+                inside = !inside;
+            }
+        }
+
+        previous = vertex;
+    }
+
+    inside
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Geofence {
+    pub id: u32,
+    pub name: String,
+    pub shape: GeofenceShape,
+    pub dwell_secs: Option<u32>, // report nodes that stay inside for this long
+    pub node_nums: Option<Vec<u32>>, // nodes to watch, all nodes if `None`
+}
+
+impl Geofence {
+    fn watches(&self, node_num: u32) -> bool {
+        self.node_nums
+            .as_ref()
+            .map_or(true, |node_nums| node_nums.contains(&node_num))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum GeofenceEventKind {
+    Enter,
+    Exit,
+    Dwell,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GeofenceEvent {
+    pub geofence_id: u32,
+    pub geofence_name: String,
+    pub node_num: u32,
+    pub kind: GeofenceEventKind,
+    pub location: GeoPoint,
+    pub timestamp: u32, // secs
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GeofenceHistoryFilter {
+    pub geofence_id: Option<u32>,
+    pub node_num: Option<u32>,
+    pub start: Option<u32>, // secs
+    pub end: Option<u32>,   // secs
+}
+
+#[derive(Clone, Debug)]
+struct NodePresence {
+    inside: bool,
+    entered_at: u32,
+    dwell_reported: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct GeofenceManager {
+    geofences: HashMap<u32, Geofence>,
+    presence: HashMap<(u32, u32), NodePresence>, // by geofence id and node
+    history: Vec<GeofenceEvent>,
+    next_id: u32,
+    store_path: Option<PathBuf>, // geofences are only persisted if set
+}
+
+impl GeofenceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the geofences stored at `store_path`, creating its directory if
+    /// it doesn't exist. Changes to geofences are written back to the file.
+    pub fn open(store_path: PathBuf) -> Result<Self, String> {
+        let mut manager = Self::new();
+
+        if store_path.exists() {
+            let contents = fs::read_to_string(&store_path).map_err(|e| e.to_string())?;
+            let geofences: Vec<Geofence> =
+                serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+
+            for geofence in geofences {
+                manager.next_id = manager.next_id.max(geofence.id);
+                manager.geofences.insert(geofence.id, geofence);
+            }
+        } else if let Some(parent) = store_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        debug!(
+            "Loaded {} geofences from {:?}",
+            manager.geofences.len(),
+            store_path
+        );
+
+        manager.store_path = Some(store_path);
+
+        Ok(manager)
+    }
+
+    /// Writes the geofences to the store file, if there is one. Failing to
+    /// persist doesn't undo the change, so it's only logged.
+    fn persist(&self) {
+        let store_path = match self.store_path.as_ref() {
+            Some(store_path) => store_path,
+            None => return,
+        };
+
+        if let Err(e) = write_geofences(store_path, &self.get_geofences()) {
+            warn!("Failed to persist geofences: {}", e);
+        }
+    }
+
+    pub fn get_geofences(&self) -> Vec<Geofence> {
+        let mut geofences: Vec<Geofence> = self.geofences.values().cloned().collect();
+        geofences.sort_by_key(|geofence| geofence.id);
+        geofences
+    }
+
+    /// Adds a geofence, or replaces the geofence with the same id. A geofence
+    /// with an id of 0 is assigned a new id.
+    pub fn upsert_geofence(&mut self, mut geofence: Geofence) -> Result<Geofence, String> {
+        geofence.shape.validate()?;
+
+        if geofence.id == 0 {
+            self.next_id += 1;
+            geofence.id = self.next_id;
+        } else {
+            self.next_id = self.next_id.max(geofence.id);
+        }
+
+        // Presence is re-evaluated against the new shape on the next position
+        self.presence.retain(|(id, _), _| *id != geofence.id);
+        self.geofences.insert(geofence.id, geofence.clone());
+        self.persist();
+
+        debug!("Upserted geofence {}", geofence.id);
+
+        Ok(geofence)
+    }
+
+    pub fn remove_geofence(&mut self, geofence_id: u32) -> Option<Geofence> {
+        self.presence.retain(|(id, _), _| *id != geofence_id);
+
+        let removed = self.geofences.remove(&geofence_id)?;
+        self.persist();
+
+        Some(removed)
+    }
+
+    /// Evaluates a node's position against every geofence watching it.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_num` - The node that reported the position.
+    /// * `location` - The reported position.
+    /// * `timestamp` - When the position was reported, in seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<GeofenceEvent>` - Enter, exit and dwell events caused by this position.
+    pub fn evaluate(
+        &mut self,
+        node_num: u32,
+        location: GeoPoint,
+        timestamp: u32,
+    ) -> Vec<GeofenceEvent> {
+        let mut events = vec![];
+
+        for geofence in self.geofences.values().filter(|g| g.watches(node_num)) {
+            let inside = geofence.shape.contains(&location);
+            let previous = self.presence.get(&(geofence.id, node_num)).cloned();
+
+            trace!(
+                "Node {} is {} geofence {}",
+                node_num,
+                if inside { "inside" } else { "outside" },
+                geofence.id
+            );
+
+            let build_event = |kind: GeofenceEventKind| GeofenceEvent {
+                geofence_id: geofence.id,
+                geofence_name: geofence.name.clone(),
+                node_num,
+                kind,
+                location,
+                timestamp,
+            };
+
+            let presence = match (previous, inside) {
+                // Nodes first seen outside a fence never entered it
+                (None, false) => NodePresence {
+                    inside: false,
+                    entered_at: 0,
+                    dwell_reported: false,
+                },
+                (None, true) | (Some(NodePresence { inside: false, .. }), true) => {
+                    events.push(build_event(GeofenceEventKind::Enter));
+
+                    NodePresence {
+                        inside: true,
+                        entered_at: timestamp,
+                        dwell_reported: false,
+                    }
+                }
+                (Some(NodePresence { inside: true, .. }), false) => {
+                    events.push(build_event(GeofenceEventKind::Exit));
+
+                    NodePresence {
+                        inside: false,
+                        entered_at: 0,
+                        dwell_reported: false,
+                    }
+                }
+                (Some(mut presence), _) => {
+                    let dwelled = geofence.dwell_secs.map_or(false, |dwell_secs| {
+                        timestamp.saturating_sub(presence.entered_at) >= dwell_secs
+                    });
+
+                    if presence.inside && dwelled && !presence.dwell_reported {
+                        events.push(build_event(GeofenceEventKind::Dwell));
+                        presence.dwell_reported = true;
+                    }
+
+                    presence
+                }
+            };
+
+            self.presence.insert((geofence.id, node_num), presence);
+        }
+
+        self.history.extend(events.iter().cloned());
+
+        if self.history.len() > MAX_GEOFENCE_HISTORY {
+            let excess = self.history.len() - MAX_GEOFENCE_HISTORY;
+            self.history.drain(..excess);
+        }
+
+        events
+    }
+
+    pub fn get_history(&self, filter: &GeofenceHistoryFilter) -> Vec<GeofenceEvent> {
+        self.history
+            .iter()
+            .filter(|e| filter.geofence_id.map_or(true, |id| e.geofence_id == id))
+            .filter(|e| filter.node_num.map_or(true, |n| e.node_num == n))
+            .filter(|e| filter.start.map_or(true, |start| e.timestamp >= start))
+            .filter(|e| filter.end.map_or(true, |end| e.timestamp <= end))
+            .cloned()
+            .collect()
+    }
+}
+
+fn write_geofences(store_path: &Path, geofences: &[Geofence]) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(geofences).map_err(|e| e.to_string())?;
+
+    // Write to a temporary file first so a failed write keeps the old geofences
+    let temp_path = store_path.with_extension("json.tmp");
+    fs::write(&temp_path, contents).map_err(|e| e.to_string())?;
+
+    fs::rename(&temp_path, store_path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(latitude: f64, longitude: f64) -> GeoPoint {
+        GeoPoint {
+            latitude,
+            longitude,
+        }
+    }
+
+    fn build_manager() -> GeofenceManager {
+        let mut manager = GeofenceManager::new();
+
+        manager
+            .upsert_geofence(Geofence {
+                id: 0,
+                name: "Course".into(),
+                shape: GeofenceShape::Polygon {
+                    vertices: vec![
+                        point(0.0, 0.0),
+                        point(0.0, 1.0),
+                        point(1.0, 1.0),
+                        point(1.0, 0.0),
+                    ],
+                },
+                dwell_secs: Some(600),
+                node_nums: None,
+            })
+            .unwrap();
+
+        manager
+    }
+
+    #[test]
+    fn enter_dwell_exit() {
+        let mut manager = build_manager();
+
+        let kinds = |events: Vec<GeofenceEvent>| -> Vec<GeofenceEventKind> {
+            events.into_iter().map(|e| e.kind).collect()
+        };
+
+        assert_eq!(kinds(manager.evaluate(1, point(-0.5, 0.5), 0)), vec![]);
+        assert_eq!(
+            kinds(manager.evaluate(1, point(0.5, 0.5), 100)),
+            vec![GeofenceEventKind::Enter]
+        );
+        assert_eq!(kinds(manager.evaluate(1, point(0.6, 0.5), 400)), vec![]);
+        assert_eq!(
+            kinds(manager.evaluate(1, point(0.6, 0.5), 700)),
+            vec![GeofenceEventKind::Dwell]
+        );
+        assert_eq!(kinds(manager.evaluate(1, point(0.6, 0.5), 800)), vec![]);
+        assert_eq!(
+            kinds(manager.evaluate(1, point(1.5, 0.5), 900)),
+            vec![GeofenceEventKind::Exit]
+        );
+
+        let history = manager.get_history(&GeofenceHistoryFilter {
+            start: Some(500),
+            ..Default::default()
+        });
+
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn circle_contains() {
+        let shape = GeofenceShape::Circle {
+            center: point(45.0, -122.0),
+            radius_meters: 1000.0,
+        };
+
+        assert!(shape.contains(&point(45.005, -122.0)));
+        assert!(!shape.contains(&point(45.02, -122.0)));
+    }
+
+    #[test]
+    fn writes_and_reopens_geofences_file() {
+        let store_path = std::env::temp_dir().join(format!(
+            "geofences_{}.json",
+            crate::device::helpers::generate_rand_id::<u32>()
+        ));
+
+        let mut manager = GeofenceManager::open(store_path.clone()).unwrap();
+        let circle = GeofenceShape::Circle {
+            center: point(45.0, -122.0),
+            radius_meters: 1000.0,
+        };
+
+        for name in ["Base", "Camp"] {
+            manager
+                .upsert_geofence(Geofence {
+                    id: 0,
+                    name: name.into(),
+                    shape: circle.clone(),
+                    dwell_secs: None,
+                    node_nums: None,
+                })
+                .unwrap();
+        }
+
+        manager.remove_geofence(1).unwrap();
+
+        let mut reopened = GeofenceManager::open(store_path.clone()).unwrap();
+        let names: Vec<String> = reopened
+            .get_geofences()
+            .into_iter()
+            .map(|g| g.name)
+            .collect();
+        assert_eq!(names, vec!["Camp".to_string()]);
+
+        // New geofences don't reuse the ids of stored ones
+        let geofence = reopened
+            .upsert_geofence(Geofence {
+                id: 0,
+                name: "Summit".into(),
+                shape: circle,
+                dwell_secs: None,
+                node_nums: None,
+            })
+            .unwrap();
+        assert_eq!(geofence.id, 3);
+
+        let _ = fs::remove_file(store_path);
+    }
+}
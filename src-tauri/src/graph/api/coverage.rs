@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, Value};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use crate::geo::{
+    circle_ring, distance_meters, meters_to_latitude_degrees, meters_to_longitude_degrees,
+    GeoBounds, GeoPoint,
+};
+use crate::graph::api::export::NodeExportMetadata;
+use crate::graph::ds::graph::MeshGraph;
+
+const REFERENCE_DISTANCE_METERS: f64 = 1000.0;
+const MIN_LINK_DISTANCE_METERS: f64 = 10.0; // shorter links are dominated by GPS error
+const MIN_PATH_LOSS_EXPONENT: f64 = 1.5;
+const MAX_PATH_LOSS_EXPONENT: f64 = 6.0;
+const COVERAGE_CIRCLE_VERTICES: u32 = 64;
+const MAX_RASTER_CELLS: usize = 1_000_000;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageConfig {
+    pub min_snr: f64,                    // weakest SNR a link can still decode, in dB
+    pub default_path_loss_exponent: f64, // used when a node's links can't be fitted
+    pub max_range_meters: f64,
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            min_snr: -15.0,
+            default_path_loss_exponent: 2.7,
+            max_range_meters: 30_000.0,
+        }
+    }
+}
+
+/// Log-distance path loss model fitted to the links of a single node, where
+/// `snr(d) = reference_snr - 10 * path_loss_exponent * log10(d / 1 km)`
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeCoverageModel {
+    pub node_num: u32,
+    pub location: GeoPoint,
+    pub reference_snr: f64, // predicted SNR at 1 km, in dB
+    pub path_loss_exponent: f64,
+    pub link_count: u32,   // links with known positions used to fit the model
+    pub range_meters: f64, // distance at which the predicted SNR reaches the minimum
+}
+
+impl NodeCoverageModel {
+    pub fn predict_snr(&self, distance_meters: f64) -> f64 {
+        let distance = distance_meters.max(1.0) / REFERENCE_DISTANCE_METERS;
+        self.reference_snr - 10.0 * self.path_loss_exponent * distance.log10()
+    }
+}
+
+/// Best predicted SNR from any node, sampled on a grid. Cells are stored row by
+/// row from the south-west corner, and are `None` where no node is predicted to reach.
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageRaster {
+    pub bounds: GeoBounds,
+    pub rows: u32,
+    pub columns: u32,
+    pub cell_size_meters: f64,
+    pub cells: Vec<Option<f64>>,
+}
+
+/// Fits `snr = reference_snr - path_loss_exponent * 10 * log10(d)` to link samples
+/// by least squares, falling back to the default exponent when the samples can't
+/// constrain the slope.
+fn fit_path_loss(samples: &[(f64, f64)], config: &CoverageConfig) -> (f64, f64) {
+    let xs: Vec<f64> = samples
+        .iter()
+        .map(|(distance, _)| -10.0 * (distance / REFERENCE_DISTANCE_METERS).log10())
+        .collect();
+    let ys: Vec<f64> = samples.iter().map(|(_, snr)| *snr).collect();
+
+    let count = samples.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / count;
+    let mean_y = ys.iter().sum::<f64>() / count;
+
+    let covariance: f64 = xs
+        .iter()
+        .zip(ys.iter())
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+
+    if variance > f64::EPSILON {
+        let exponent = covariance / variance;
+
+        if (MIN_PATH_LOSS_EXPONENT..=MAX_PATH_LOSS_EXPONENT).contains(&exponent) {
+            return (mean_y - exponent * mean_x, exponent);
+        }
+    }
+
+    let exponent = config.default_path_loss_exponent;
+    (mean_y - exponent * mean_x, exponent)
+}
+
+impl MeshGraph {
+    /// Fits a path loss model for every node with a known position and at least
+    /// one link to another node with a known position.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - Latest known positions of nodes.
+    /// * `config` - Limits of the model.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<NodeCoverageModel>` - The fitted models, ordered by node number.
+    pub fn estimate_coverage(
+        &self,
+        metadata: &HashMap<u32, NodeExportMetadata>,
+        config: &CoverageConfig,
+    ) -> Vec<NodeCoverageModel> {
+        let get_location = |node_num: u32| -> Option<GeoPoint> {
+            metadata
+                .get(&node_num)?
+                .position
+                .as_ref()
+                .map(GeoPoint::from)
+        };
+
+        // Links are assumed to be roughly symmetric, so each link is a sample for both ends
+        let mut samples: HashMap<u32, Vec<(f64, f64)>> = HashMap::new();
+
+        for (source, target, edge) in self.get_edges() {
+            let (source_location, target_location) =
+                match (get_location(source.node_num), get_location(target.node_num)) {
+                    (Some(s), Some(t)) => (s, t),
+                    _ => continue,
+                };
+
+            let distance = distance_meters(&source_location, &target_location);
+
+            if distance < MIN_LINK_DISTANCE_METERS {
+                continue;
+            }
+
+            for node_num in [source.node_num, target.node_num] {
+                samples
+                    .entry(node_num)
+                    .or_default()
+                    .push((distance, edge.snr));
+            }
+        }
+
+        let mut models: Vec<NodeCoverageModel> = samples
+            .into_iter()
+            .filter_map(|(node_num, node_samples)| {
+                let location = get_location(node_num)?;
+                let (reference_snr, path_loss_exponent) = fit_path_loss(&node_samples, config);
+
+                let range_meters = REFERENCE_DISTANCE_METERS
+                    * 10f64.powf((reference_snr - config.min_snr) / (10.0 * path_loss_exponent));
+
+                Some(NodeCoverageModel {
+                    node_num,
+                    location,
+                    reference_snr,
+                    path_loss_exponent,
+                    link_count: node_samples.len() as u32,
+                    range_meters: range_meters.min(config.max_range_meters),
+                })
+            })
+            .collect();
+
+        models.sort_by_key(|model| model.node_num);
+
+        log::debug!("Estimated coverage of {} nodes", models.len());
+
+        models
+    }
+}
+
+/// Builds a polygon of the estimated reachable area around each node, and a
+/// multi-polygon covering the whole mesh.
+pub fn coverage_to_geojson(models: &[NodeCoverageModel]) -> FeatureCollection {
+    let mut features = vec![];
+    let mut mesh_polygons = vec![];
+
+    for model in models {
+        let polygon = vec![circle_ring(
+            &model.location,
+            model.range_meters,
+            COVERAGE_CIRCLE_VERTICES,
+        )];
+
+        let mut properties = JsonObject::new();
+        properties.insert("type".into(), "node".into());
+        properties.insert("nodeNum".into(), model.node_num.into());
+        properties.insert("rangeMeters".into(), model.range_meters.into());
+        properties.insert("referenceSnr".into(), model.reference_snr.into());
+        properties.insert("pathLossExponent".into(), model.path_loss_exponent.into());
+        properties.insert("linkCount".into(), model.link_count.into());
+
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::Polygon(polygon.clone()))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        });
+
+        mesh_polygons.push(polygon);
+    }
+
+    if !mesh_polygons.is_empty() {
+        let mut properties = JsonObject::new();
+        properties.insert("type".into(), "mesh".into());
+
+        features.push(Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::MultiPolygon(mesh_polygons))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        });
+    }
+
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+/// Samples the best predicted SNR from any node on a grid spanning the
+/// estimated coverage of the whole mesh.
+pub fn coverage_to_raster(
+    models: &[NodeCoverageModel],
+    cell_size_meters: f64,
+) -> Result<CoverageRaster, String> {
+    if cell_size_meters <= 0.0 {
+        return Err("Cell size must be greater than zero".into());
+    }
+
+    let mut bounds: Option<GeoBounds> = None;
+
+    for model in models {
+        let latitude_margin = meters_to_latitude_degrees(model.range_meters);
+        let longitude_margin =
+            meters_to_longitude_degrees(model.range_meters, model.location.latitude);
+
+        let model_bounds = GeoBounds {
+            min_latitude: model.location.latitude - latitude_margin,
+            min_longitude: model.location.longitude - longitude_margin,
+            max_latitude: model.location.latitude + latitude_margin,
+            max_longitude: model.location.longitude + longitude_margin,
+        };
+
+        bounds = Some(match bounds {
+            Some(b) => GeoBounds {
+                min_latitude: b.min_latitude.min(model_bounds.min_latitude),
+                min_longitude: b.min_longitude.min(model_bounds.min_longitude),
+                max_latitude: b.max_latitude.max(model_bounds.max_latitude),
+                max_longitude: b.max_longitude.max(model_bounds.max_longitude),
+            },
+            None => model_bounds,
+        });
+    }
+
+    let bounds = bounds.ok_or("No nodes with known coverage")?;
+
+    let center_latitude = (bounds.min_latitude + bounds.max_latitude) / 2.0;
+    let latitude_step = meters_to_latitude_degrees(cell_size_meters);
+    let longitude_step = meters_to_longitude_degrees(cell_size_meters, center_latitude);
+
+    let rows = ((bounds.max_latitude - bounds.min_latitude) / latitude_step).ceil() as u32;
+    let columns = ((bounds.max_longitude - bounds.min_longitude) / longitude_step).ceil() as u32;
+
+    if rows as usize * columns as usize > MAX_RASTER_CELLS {
+        return Err(format!(
+            "Raster of {} by {} cells is too large, use a larger cell size",
+            rows, columns
+        ));
+    }
+
+    let mut cells = Vec::with_capacity(rows as usize * columns as usize);
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let cell_center = GeoPoint {
+                latitude: bounds.min_latitude + (f64::from(row) + 0.5) * latitude_step,
+                longitude: bounds.min_longitude + (f64::from(column) + 0.5) * longitude_step,
+            };
+
+            let best_snr = models
+                .iter()
+                .filter_map(|model| {
+                    let distance = distance_meters(&model.location, &cell_center);
+
+                    if distance > model.range_meters {
+                        return None;
+                    }
+
+                    Some(model.predict_snr(distance))
+                })
+                .reduce(f64::max);
+
+            cells.push(best_snr);
+        }
+    }
+
+    Ok(CoverageRaster {
+        bounds,
+        rows,
+        columns,
+        cell_size_meters,
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_known_path_loss() {
+        // Samples generated with a reference SNR of 5 dB and an exponent of 3
+        let samples: Vec<(f64, f64)> = [500.0, 1000.0, 2000.0, 4000.0]
+            .iter()
+            .map(|distance: &f64| (*distance, 5.0 - 30.0 * (distance / 1000.0).log10()))
+            .collect();
+
+        let (reference_snr, exponent) = fit_path_loss(&samples, &CoverageConfig::default());
+
+        assert!((reference_snr - 5.0).abs() < 1e-9);
+        assert!((exponent - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn single_link_uses_default_exponent() {
+        let config = CoverageConfig::default();
+        let (reference_snr, exponent) = fit_path_loss(&[(1000.0, -2.0)], &config);
+
+        assert_eq!(exponent, config.default_path_loss_exponent);
+        assert!((reference_snr + 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn raster_covers_node() {
+        let model = NodeCoverageModel {
+            node_num: 1,
+            location: GeoPoint {
+                latitude: 45.0,
+                longitude: -122.0,
+            },
+            reference_snr: 0.0,
+            path_loss_exponent: 3.0,
+            link_count: 1,
+            range_meters: 2000.0,
+        };
+
+        let raster = coverage_to_raster(&[model], 500.0).unwrap();
+
+        assert_eq!(raster.cells.len(), (raster.rows * raster.columns) as usize);
+        assert!(raster.cells.iter().any(|cell| cell.is_some()));
+        assert!(raster.cells.iter().any(|cell| cell.is_none()));
+    }
+}
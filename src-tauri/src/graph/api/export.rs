@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, Value};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use crate::device::{helpers::format_utc_datetime, MeshDevice, NormalizedPosition};
+use crate::graph::ds::graph::MeshGraph;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum GraphExportFormat {
+    GraphMl, // for Gephi, NetworkX, yEd
+    Dot,     // Graphviz
+    GeoJson, // nodes at their latest position, edges as line strings
+}
+
+/// Information about a node that isn't stored in the graph itself
+#[derive(Clone, Debug, Default)]
+pub struct NodeExportMetadata {
+    pub long_name: Option<String>,
+    pub short_name: Option<String>,
+    pub position: Option<NormalizedPosition>,
+}
+
+/// Collects names and latest positions of nodes across all connected devices.
+pub fn collect_node_metadata<'a>(
+    devices: impl Iterator<Item = &'a MeshDevice>,
+) -> HashMap<u32, NodeExportMetadata> {
+    let mut metadata: HashMap<u32, NodeExportMetadata> = HashMap::new();
+
+    for device in devices {
+        for (node_num, node) in device.nodes.iter() {
+            let entry = metadata.entry(*node_num).or_default();
+
+            if let Some(user) = node.user.as_ref() {
+                entry.long_name = Some(user.long_name.clone());
+                entry.short_name = Some(user.short_name.clone());
+            }
+
+            // Prefer the most recent position fix seen by any device
+            if let Some(position) = node.position_metrics.last() {
+                let is_newer = entry
+                    .position
+                    .as_ref()
+                    .map_or(true, |current| position.time >= current.time);
+
+                if is_newer {
+                    entry.position = Some(position.clone());
+                }
+            }
+        }
+    }
+
+    metadata
+}
+
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl MeshGraph {
+    pub fn export(
+        &self,
+        format: GraphExportFormat,
+        metadata: &HashMap<u32, NodeExportMetadata>,
+    ) -> String {
+        match format {
+            GraphExportFormat::GraphMl => self.to_graphml(metadata),
+            GraphExportFormat::Dot => self.to_dot(metadata),
+            GraphExportFormat::GeoJson => self.to_geojson(metadata).to_string(),
+        }
+    }
+
+    pub fn to_graphml(&self, metadata: &HashMap<u32, NodeExportMetadata>) -> String {
+        let mut nodes = self.get_nodes();
+        nodes.sort_by_key(|node| node.node_num);
+
+        let mut output = String::new();
+
+        output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        output.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        output.push_str(
+            "  <key id=\"longName\" for=\"node\" attr.name=\"longName\" attr.type=\"string\"/>\n",
+        );
+        output.push_str(
+            "  <key id=\"shortName\" for=\"node\" attr.name=\"shortName\" attr.type=\"string\"/>\n",
+        );
+        output.push_str(
+            "  <key id=\"latitude\" for=\"node\" attr.name=\"latitude\" attr.type=\"double\"/>\n",
+        );
+        output.push_str(
+            "  <key id=\"longitude\" for=\"node\" attr.name=\"longitude\" attr.type=\"double\"/>\n",
+        );
+        output.push_str("  <key id=\"nodeLastHeard\" for=\"node\" attr.name=\"lastHeard\" attr.type=\"string\"/>\n");
+        output
+            .push_str("  <key id=\"snr\" for=\"edge\" attr.name=\"snr\" attr.type=\"double\"/>\n");
+        output.push_str("  <key id=\"edgeLastHeard\" for=\"edge\" attr.name=\"lastHeard\" attr.type=\"string\"/>\n");
+        output.push_str("  <graph id=\"mesh\" edgedefault=\"directed\">\n");
+
+        for node in nodes {
+            output.push_str(&format!("    <node id=\"{}\">\n", node.node_num));
+
+            if let Some(node_metadata) = metadata.get(&node.node_num) {
+                if let Some(long_name) = node_metadata.long_name.as_ref() {
+                    output.push_str(&format!(
+                        "      <data key=\"longName\">{}</data>\n",
+                        escape_xml(long_name)
+                    ));
+                }
+
+                if let Some(short_name) = node_metadata.short_name.as_ref() {
+                    output.push_str(&format!(
+                        "      <data key=\"shortName\">{}</data>\n",
+                        escape_xml(short_name)
+                    ));
+                }
+
+                if let Some(position) = node_metadata.position.as_ref() {
+                    output.push_str(&format!(
+                        "      <data key=\"latitude\">{}</data>\n",
+                        position.latitude
+                    ));
+                    output.push_str(&format!(
+                        "      <data key=\"longitude\">{}</data>\n",
+                        position.longitude
+                    ));
+                }
+            }
+
+            output.push_str(&format!(
+                "      <data key=\"nodeLastHeard\">{}</data>\n",
+                format_utc_datetime(node.last_heard)
+            ));
+            output.push_str("    </node>\n");
+        }
+
+        for (source, target, edge) in self.get_edges() {
+            output.push_str(&format!(
+                "    <edge source=\"{}\" target=\"{}\">\n",
+                source.node_num, target.node_num
+            ));
+            output.push_str(&format!("      <data key=\"snr\">{}</data>\n", edge.snr));
+            output.push_str(&format!(
+                "      <data key=\"edgeLastHeard\">{}</data>\n",
+                format_utc_datetime(edge.last_heard)
+            ));
+            output.push_str("    </edge>\n");
+        }
+
+        output.push_str("  </graph>\n");
+        output.push_str("</graphml>\n");
+
+        output
+    }
+
+    pub fn to_dot(&self, metadata: &HashMap<u32, NodeExportMetadata>) -> String {
+        let mut nodes = self.get_nodes();
+        nodes.sort_by_key(|node| node.node_num);
+
+        let mut output = String::from("digraph mesh {\n");
+
+        for node in nodes {
+            let label = metadata
+                .get(&node.node_num)
+                .and_then(|m| m.long_name.clone())
+                .unwrap_or_else(|| node.node_num.to_string());
+
+            output.push_str(&format!(
+                "  \"{}\" [label=\"{}\", last_heard=\"{}\"];\n",
+                node.node_num,
+                escape_dot(&label),
+                format_utc_datetime(node.last_heard)
+            ));
+        }
+
+        for (source, target, edge) in self.get_edges() {
+            output.push_str(&format!(
+                "  \"{}\" -> \"{}\" [label=\"{} dB\", snr={}, last_heard=\"{}\"];\n",
+                source.node_num,
+                target.node_num,
+                edge.snr,
+                edge.snr,
+                format_utc_datetime(edge.last_heard)
+            ));
+        }
+
+        output.push_str("}\n");
+
+        output
+    }
+
+    /// Nodes without a known position can't be placed on a map, so they and
+    /// their edges are left out of the GeoJSON export.
+    pub fn to_geojson(&self, metadata: &HashMap<u32, NodeExportMetadata>) -> FeatureCollection {
+        let get_coordinates = |node_num: u32| -> Option<Vec<f64>> {
+            let position = metadata.get(&node_num)?.position.as_ref()?;
+            Some(vec![position.longitude, position.latitude])
+        };
+
+        let mut nodes = self.get_nodes();
+        nodes.sort_by_key(|node| node.node_num);
+
+        let mut features = vec![];
+
+        for node in nodes {
+            let coordinates = match get_coordinates(node.node_num) {
+                Some(c) => c,
+                None => continue,
+            };
+
+            let mut properties = JsonObject::new();
+            properties.insert("type".into(), "node".into());
+            properties.insert("nodeNum".into(), node.node_num.into());
+            properties.insert(
+                "lastHeard".into(),
+                format_utc_datetime(node.last_heard).into(),
+            );
+
+            if let Some(long_name) = metadata
+                .get(&node.node_num)
+                .and_then(|m| m.long_name.clone())
+            {
+                properties.insert("longName".into(), long_name.into());
+            }
+
+            features.push(Feature {
+                bbox: None,
+                geometry: Some(Geometry::new(Value::Point(coordinates))),
+                id: None,
+                properties: Some(properties),
+                foreign_members: None,
+            });
+        }
+
+        for (source, target, edge) in self.get_edges() {
+            let (source_coordinates, target_coordinates) = match (
+                get_coordinates(source.node_num),
+                get_coordinates(target.node_num),
+            ) {
+                (Some(s), Some(t)) => (s, t),
+                _ => continue,
+            };
+
+            let mut properties = JsonObject::new();
+            properties.insert("type".into(), "edge".into());
+            properties.insert("from".into(), source.node_num.into());
+            properties.insert("to".into(), target.node_num.into());
+            properties.insert("snr".into(), edge.snr.into());
+            properties.insert(
+                "lastHeard".into(),
+                format_utc_datetime(edge.last_heard).into(),
+            );
+
+            features.push(Feature {
+                bbox: None,
+                geometry: Some(Geometry::new(Value::LineString(vec![
+                    source_coordinates,
+                    target_coordinates,
+                ]))),
+                id: None,
+                properties: Some(properties),
+                foreign_members: None,
+            });
+        }
+
+        FeatureCollection {
+            bbox: None,
+            features,
+            foreign_members: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::ds::test_utils;
+
+    fn build_graph() -> MeshGraph {
+        test_utils::build_graph(&[1, 2], &[(1, 2, 5.5)])
+    }
+
+    #[test]
+    fn export_graphml() {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            1,
+            NodeExportMetadata {
+                long_name: Some("Base <HQ> & \"Relay\"".into()),
+                short_name: Some("B'Q".into()),
+                position: Some(NormalizedPosition {
+                    latitude: 10.5,
+                    longitude: -20.25,
+                    ..Default::default()
+                }),
+            },
+        );
+
+        let graphml = build_graph().to_graphml(&metadata);
+
+        assert!(graphml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(graphml.contains("<graph id=\"mesh\" edgedefault=\"directed\">"));
+        assert!(graphml
+            .contains("<data key=\"longName\">Base &lt;HQ&gt; &amp; &quot;Relay&quot;</data>"));
+        assert!(graphml.contains("<data key=\"shortName\">B&apos;Q</data>"));
+        assert!(graphml.contains("<data key=\"latitude\">10.5</data>"));
+        assert!(graphml.contains("<data key=\"longitude\">-20.25</data>"));
+        assert!(graphml.contains("<data key=\"nodeLastHeard\">1970-01-01T00:00:00Z</data>"));
+        assert_eq!(graphml.matches("<node id=").count(), 2);
+
+        assert!(graphml.contains("<edge source=\"1\" target=\"2\">"));
+        assert!(graphml.contains("<data key=\"snr\">5.5</data>"));
+        assert!(graphml.contains("<data key=\"edgeLastHeard\">1970-01-01T00:00:00Z</data>"));
+        assert!(graphml.trim_end().ends_with("</graphml>"));
+    }
+
+    #[test]
+    fn export_dot() {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            1,
+            NodeExportMetadata {
+                long_name: Some("Base \"HQ\"".into()),
+                ..Default::default()
+            },
+        );
+
+        let dot = build_graph().to_dot(&metadata);
+
+        assert!(dot.starts_with("digraph mesh {"));
+        assert!(dot.contains("\"1\" [label=\"Base \\\"HQ\\\"\""));
+        assert!(dot.contains("\"1\" -> \"2\" [label=\"5.5 dB\""));
+    }
+
+    #[test]
+    fn export_geojson_skips_unplaced_nodes() {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            1,
+            NodeExportMetadata {
+                position: Some(NormalizedPosition {
+                    latitude: 10.0,
+                    longitude: 20.0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let collection = build_graph().to_geojson(&metadata);
+
+        // Node 2 has no position, so neither it nor the edge to it can be drawn
+        assert_eq!(collection.features.len(), 1);
+    }
+}
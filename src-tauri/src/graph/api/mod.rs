@@ -1 +1,5 @@
+pub mod coverage;
+pub mod export;
+pub mod relay;
+pub mod route;
 pub mod update_from_packet;
@@ -0,0 +1,382 @@
+use std::collections::{HashMap, HashSet};
+
+use meshtastic::ts::specta::{self, Type};
+use petgraph::{
+    algo::{connected_components, dijkstra},
+    visit::EdgeRef,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::geo::{distance_meters, GeoBounds, GeoPoint};
+use crate::graph::api::export::NodeExportMetadata;
+use crate::graph::api::route::RoutingGraph;
+use crate::graph::ds::{edge::link_cost_from_snr, graph::MeshGraph};
+
+const REFERENCE_DISTANCE_METERS: f64 = 1000.0;
+const MAX_RELAY_CANDIDATES: usize = 10_000;
+
+// 0xFFFFFFFF is the broadcast address, so no real node uses the top of the range
+const FIRST_RELAY_NODE_NUM: u32 = u32::MAX - 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayPlacementConfig {
+    pub reference_snr: f64,       // expected SNR of a relay link at 1 km, in dB
+    pub path_loss_exponent: f64,  // 2 in free space, higher in cluttered terrain
+    pub min_snr: f64,             // weakest SNR a link can still decode, in dB
+    pub grid_spacing_meters: f64, // spacing of sampled candidates when none are given
+}
+
+impl Default for RelayPlacementConfig {
+    fn default() -> Self {
+        Self {
+            reference_snr: 5.0,
+            path_loss_exponent: 2.7,
+            min_snr: -15.0,
+            grid_spacing_meters: 1000.0,
+        }
+    }
+}
+
+impl RelayPlacementConfig {
+    fn predict_snr(&self, distance_meters: f64) -> f64 {
+        let distance = distance_meters.max(1.0) / REFERENCE_DISTANCE_METERS;
+        self.reference_snr - 10.0 * self.path_loss_exponent * distance.log10()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectivityMetrics {
+    pub component_count: u32,
+    pub articulation_point_count: u32, // nodes whose failure would split the mesh
+    pub worst_case_reliability: f64,   // delivery probability of the least reliable node pair
+}
+
+impl ConnectivityMetrics {
+    /// Whether these metrics describe better connectivity than `other`. Fewer
+    /// components matter most, then fewer single points of failure.
+    fn is_better_than(&self, other: &ConnectivityMetrics) -> bool {
+        (
+            other.component_count,
+            other.articulation_point_count,
+            self.worst_case_reliability,
+        )
+            .partial_cmp(&(
+                self.component_count,
+                self.articulation_point_count,
+                other.worst_case_reliability,
+            ))
+            .map_or(false, |ordering| ordering.is_gt())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RelaySuggestion {
+    pub location: GeoPoint,
+    pub linked_node_nums: Vec<u32>, // nodes predicted to be in range of the relay
+    pub metrics: ConnectivityMetrics, // connectivity after adding this and all earlier relays
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayPlacementResult {
+    pub baseline: ConnectivityMetrics,
+    pub suggestions: Vec<RelaySuggestion>,
+}
+
+fn find_articulation_points(graph: &RoutingGraph) -> HashSet<u32> {
+    fn visit(
+        graph: &RoutingGraph,
+        node: u32,
+        parent: Option<u32>,
+        depth: u32,
+        depths: &mut HashMap<u32, u32>,
+        low: &mut HashMap<u32, u32>,
+        points: &mut HashSet<u32>,
+    ) {
+        depths.insert(node, depth);
+        low.insert(node, depth);
+
+        let mut child_count = 0;
+
+        for neighbor in graph.neighbors(node) {
+            if Some(neighbor) == parent {
+                continue;
+            }
+
+            if let Some(neighbor_depth) = depths.get(&neighbor).cloned() {
+                low.insert(node, low[&node].min(neighbor_depth));
+                continue;
+            }
+
+            visit(graph, neighbor, Some(node), depth + 1, depths, low, points);
+            child_count += 1;
+
+            low.insert(node, low[&node].min(low[&neighbor]));
+
+            if parent.is_some() && low[&neighbor] >= depth {
+                points.insert(node);
+            }
+        }
+
+        if parent.is_none() && child_count > 1 {
+            points.insert(node);
+        }
+    }
+
+    let mut depths = HashMap::new();
+    let mut low = HashMap::new();
+    let mut points = HashSet::new();
+
+    for node in graph.nodes() {
+        if !depths.contains_key(&node) {
+            visit(graph, node, None, 0, &mut depths, &mut low, &mut points);
+        }
+    }
+
+    points
+}
+
+/// Measures the connectivity between the nodes in `node_nums`, routing
+/// through any node in the graph.
+fn measure_connectivity(graph: &RoutingGraph, node_nums: &[u32]) -> ConnectivityMetrics {
+    let mut worst_case_cost: f64 = 0.0;
+
+    for node_num in node_nums {
+        let costs = dijkstra(graph, *node_num, None, |edge| *edge.weight());
+
+        for other in node_nums {
+            if let Some(cost) = costs.get(other) {
+                worst_case_cost = worst_case_cost.max(*cost);
+            }
+        }
+    }
+
+    ConnectivityMetrics {
+        component_count: connected_components(graph) as u32,
+        articulation_point_count: find_articulation_points(graph).len() as u32,
+        worst_case_reliability: (-worst_case_cost).exp(),
+    }
+}
+
+/// Adds a relay at `location` to the graph, linked to every positioned node in range.
+fn add_relay(
+    graph: &mut RoutingGraph,
+    relay_node_num: u32,
+    location: &GeoPoint,
+    node_locations: &[(u32, GeoPoint)],
+    config: &RelayPlacementConfig,
+) -> Vec<u32> {
+    let mut linked_node_nums = vec![];
+
+    graph.add_node(relay_node_num);
+
+    for (node_num, node_location) in node_locations {
+        let snr = config.predict_snr(distance_meters(location, node_location));
+
+        if snr >= config.min_snr {
+            graph.add_edge(relay_node_num, *node_num, link_cost_from_snr(snr));
+            linked_node_nums.push(*node_num);
+        }
+    }
+
+    linked_node_nums
+}
+
+fn check_candidate_count(candidate_count: usize) -> Result<(), String> {
+    if candidate_count > MAX_RELAY_CANDIDATES {
+        return Err(format!(
+            "{} candidate locations is too many, use a larger grid spacing",
+            candidate_count
+        ));
+    }
+
+    Ok(())
+}
+
+impl MeshGraph {
+    /// Ranks candidate relay locations by how much they improve connectivity,
+    /// choosing relays one at a time so each suggestion builds on the previous ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `k` - The number of relays to suggest.
+    /// * `bounds` - The area relays may be placed in.
+    /// * `candidates` - Locations to consider. When `None`, the bounds are sampled on a grid.
+    /// * `metadata` - Latest known positions of nodes.
+    /// * `config` - The link model used to predict which nodes a relay reaches.
+    ///
+    /// # Returns
+    ///
+    /// * `RelayPlacementResult` - Connectivity of the current mesh, and up to
+    ///   `k` suggested relays that each improve on it.
+    pub fn suggest_relay_placement(
+        &self,
+        k: u32,
+        bounds: &GeoBounds,
+        candidates: Option<Vec<GeoPoint>>,
+        metadata: &HashMap<u32, NodeExportMetadata>,
+        config: &RelayPlacementConfig,
+    ) -> Result<RelayPlacementResult, String> {
+        let mut candidates: Vec<GeoPoint> = match candidates {
+            Some(candidates) => candidates
+                .into_iter()
+                .filter(|c| bounds.contains(c))
+                .collect(),
+            None => {
+                if config.grid_spacing_meters <= 0.0 {
+                    return Err("Grid spacing must be greater than zero".into());
+                }
+
+                // Reject oversized grids before allocating them
+                let (rows, columns) = bounds.grid_size(config.grid_spacing_meters);
+                check_candidate_count(rows.saturating_mul(columns))?;
+
+                bounds.sample_grid(config.grid_spacing_meters)
+            }
+        };
+
+        check_candidate_count(candidates.len())?;
+
+        let mut node_nums: Vec<u32> = self.get_nodes().iter().map(|n| n.node_num).collect();
+        node_nums.sort();
+
+        let node_locations: Vec<(u32, GeoPoint)> = node_nums
+            .iter()
+            .filter_map(|node_num| {
+                let position = metadata.get(node_num)?.position.as_ref()?;
+                Some((*node_num, GeoPoint::from(position)))
+            })
+            .collect();
+
+        let mut graph = self.build_routing_graph();
+        let baseline = measure_connectivity(&graph, &node_nums);
+
+        let mut current = baseline.clone();
+        let mut suggestions = vec![];
+
+        for relay_index in 0..k {
+            let relay_node_num = FIRST_RELAY_NODE_NUM - relay_index;
+            let mut best: Option<(usize, Vec<u32>, ConnectivityMetrics)> = None;
+
+            for (candidate_index, candidate) in candidates.iter().enumerate() {
+                let mut candidate_graph = graph.clone();
+                let linked_node_nums = add_relay(
+                    &mut candidate_graph,
+                    relay_node_num,
+                    candidate,
+                    &node_locations,
+                    config,
+                );
+
+                if linked_node_nums.is_empty() {
+                    continue;
+                }
+
+                let metrics = measure_connectivity(&candidate_graph, &node_nums);
+
+                let best_metrics = best.as_ref().map_or(&current, |(_, _, m)| m);
+
+                if metrics.is_better_than(best_metrics) {
+                    best = Some((candidate_index, linked_node_nums, metrics));
+                }
+            }
+
+            // Stop once no remaining candidate improves connectivity
+            let (candidate_index, linked_node_nums, metrics) = match best {
+                Some(best) => best,
+                None => break,
+            };
+
+            let location = candidates.remove(candidate_index);
+            add_relay(
+                &mut graph,
+                relay_node_num,
+                &location,
+                &node_locations,
+                config,
+            );
+
+            current = metrics.clone();
+
+            suggestions.push(RelaySuggestion {
+                location,
+                linked_node_nums,
+                metrics,
+            });
+        }
+
+        log::debug!(
+            "Suggested {} relays from baseline {:?}",
+            suggestions.len(),
+            baseline
+        );
+
+        Ok(RelayPlacementResult {
+            baseline,
+            suggestions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn articulation_points_of_chain() {
+        let mut graph = RoutingGraph::new();
+        graph.add_edge(1, 2, 0.0);
+        graph.add_edge(2, 3, 0.0);
+        graph.add_edge(3, 1, 0.0);
+        graph.add_edge(3, 4, 0.0);
+
+        assert_eq!(find_articulation_points(&graph), HashSet::from([3]));
+    }
+
+    #[test]
+    fn relay_joins_components() {
+        let mut graph = RoutingGraph::new();
+        graph.add_node(1);
+        graph.add_node(2);
+
+        let node_locations = vec![
+            (
+                1,
+                GeoPoint {
+                    latitude: 0.0,
+                    longitude: 0.0,
+                },
+            ),
+            (
+                2,
+                GeoPoint {
+                    latitude: 0.0,
+                    longitude: 0.02,
+                },
+            ),
+        ];
+
+        let baseline = measure_connectivity(&graph, &[1, 2]);
+        assert_eq!(baseline.component_count, 2);
+
+        let linked_node_nums = add_relay(
+            &mut graph,
+            FIRST_RELAY_NODE_NUM,
+            &GeoPoint {
+                latitude: 0.0,
+                longitude: 0.01,
+            },
+            &node_locations,
+            &RelayPlacementConfig::default(),
+        );
+
+        let metrics = measure_connectivity(&graph, &[1, 2]);
+
+        assert_eq!(linked_node_nums, vec![1, 2]);
+        assert_eq!(metrics.component_count, 1);
+        assert!(metrics.is_better_than(&baseline));
+    }
+}
@@ -0,0 +1,219 @@
+use meshtastic::ts::specta::{self, Type};
+use petgraph::{algo::astar, graphmap::UnGraphMap, visit::EdgeRef};
+use serde::{Deserialize, Serialize};
+
+use crate::graph::ds::graph::MeshGraph;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum RouteMetric {
+    HopCount,    // fewest relays
+    Reliability, // highest chance of delivery, derived from link SNR
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Route {
+    pub node_nums: Vec<u32>, // from source to destination, inclusive
+    pub hops: u32,
+    pub cost: f64,                 // in units of the requested metric
+    pub delivery_probability: f64, // estimated from the link quality of every hop
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteResult {
+    pub from: u32,
+    pub to: u32,
+    pub metric: RouteMetric,
+    pub routes: Vec<Route>, // best route first, followed by node-disjoint alternatives
+}
+
+// Links are treated as bidirectional, weighted by their link cost
+pub type RoutingGraph = UnGraphMap<u32, f64>;
+
+impl MeshGraph {
+    /// Finds the best routes between two nodes in the graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The node a message is sent from.
+    /// * `to` - The node a message should reach.
+    /// * `metric` - What the best route minimizes.
+    /// * `max_routes` - The maximum number of routes to return. Every route after
+    ///   the first shares no relay nodes with the routes before it.
+    ///
+    /// # Returns
+    ///
+    /// * `RouteResult` - The routes found, empty if `to` can't be reached.
+    pub fn compute_route(
+        &self,
+        from: u32,
+        to: u32,
+        metric: RouteMetric,
+        max_routes: u32,
+    ) -> Result<RouteResult, String> {
+        if !self.contains_node(from) {
+            return Err(format!("Node {} is not in the graph", from));
+        }
+
+        if !self.contains_node(to) {
+            return Err(format!("Node {} is not in the graph", to));
+        }
+
+        if from == to {
+            return Err("Source and destination nodes must be different".into());
+        }
+
+        let mut routing_graph = self.build_routing_graph();
+        let mut routes = vec![];
+
+        // Greedily removes the relays of each route found, so alternatives are
+        // independent of each other but not necessarily globally optimal
+        while routes.len() < max_routes as usize {
+            let route = match find_route(&routing_graph, from, to, metric) {
+                Some(route) => route,
+                None => break,
+            };
+
+            match route.node_nums.as_slice() {
+                [source, target] => {
+                    routing_graph.remove_edge(*source, *target);
+                }
+                node_nums => {
+                    for node_num in &node_nums[1..node_nums.len() - 1] {
+                        routing_graph.remove_node(*node_num);
+                    }
+                }
+            }
+
+            routes.push(route);
+        }
+
+        log::debug!(
+            "Found {} routes from {} to {} by {:?}",
+            routes.len(),
+            from,
+            to,
+            metric
+        );
+
+        Ok(RouteResult {
+            from,
+            to,
+            metric,
+            routes,
+        })
+    }
+
+    pub fn build_routing_graph(&self) -> RoutingGraph {
+        let mut routing_graph = RoutingGraph::new();
+
+        for node in self.get_nodes() {
+            routing_graph.add_node(node.node_num);
+        }
+
+        for (source, target, edge) in self.get_edges() {
+            // Timed out links are unlikely to carry traffic
+            if edge.stale {
+                continue;
+            }
+
+            let cost = edge.link_cost();
+
+            // Keep the better direction when both directions of a link are known
+            if let Some(existing_cost) = routing_graph.edge_weight(source.node_num, target.node_num)
+            {
+                if *existing_cost <= cost {
+                    continue;
+                }
+            }
+
+            routing_graph.add_edge(source.node_num, target.node_num, cost);
+        }
+
+        routing_graph
+    }
+}
+
+fn find_route(
+    routing_graph: &RoutingGraph,
+    from: u32,
+    to: u32,
+    metric: RouteMetric,
+) -> Option<Route> {
+    let (cost, node_nums) = astar(
+        routing_graph,
+        from,
+        |node_num| node_num == to,
+        |edge| match metric {
+            RouteMetric::HopCount => 1.0,
+            RouteMetric::Reliability => *edge.weight(),
+        },
+        |_| 0.0,
+    )?;
+
+    let link_cost: f64 = node_nums
+        .windows(2)
+        .filter_map(|pair| routing_graph.edge_weight(pair[0], pair[1]))
+        .sum();
+
+    Some(Route {
+        hops: node_nums.len() as u32 - 1,
+        node_nums,
+        cost,
+        delivery_probability: (-link_cost).exp(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::ds::test_utils;
+
+    // Node 1 reaches node 4 directly over a weak link, or through relays 2 and 3
+    fn build_graph() -> MeshGraph {
+        test_utils::build_graph(
+            &[1, 2, 3, 4],
+            &[
+                (1, 4, -18.0),
+                (1, 2, 8.0),
+                (2, 4, 6.0),
+                (1, 3, 0.0),
+                (3, 4, 0.0),
+            ],
+        )
+    }
+
+    #[test]
+    fn fewest_hops() {
+        let result = build_graph()
+            .compute_route(1, 4, RouteMetric::HopCount, 1)
+            .unwrap();
+
+        assert_eq!(result.routes.len(), 1);
+        assert_eq!(result.routes[0].node_nums, vec![1, 4]);
+        assert_eq!(result.routes[0].hops, 1);
+    }
+
+    #[test]
+    fn most_reliable() {
+        let result = build_graph()
+            .compute_route(1, 4, RouteMetric::Reliability, 1)
+            .unwrap();
+
+        assert_eq!(result.routes[0].node_nums, vec![1, 2, 4]);
+        assert!(result.routes[0].delivery_probability > 0.5);
+    }
+
+    #[test]
+    fn disjoint_alternatives() {
+        let result = build_graph()
+            .compute_route(4, 1, RouteMetric::Reliability, 5)
+            .unwrap();
+
+        let node_nums: Vec<Vec<u32>> = result.routes.into_iter().map(|r| r.node_nums).collect();
+
+        assert_eq!(node_nums, vec![vec![4, 2, 1], vec![4, 3, 1], vec![4, 1]]);
+    }
+}
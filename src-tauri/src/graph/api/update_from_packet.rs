@@ -3,6 +3,7 @@ use std::time::Duration;
 use meshtastic::protobufs::{self, MeshPacket};
 
 use crate::graph::ds::{edge::GraphEdge, graph::MeshGraph, node::GraphNode};
+use crate::state::DeviceKey;
 
 pub const DEFAULT_NODE_TIMEOUT_DURATION: Duration = Duration::from_secs(15 * 60);
 
@@ -11,6 +12,7 @@ impl MeshGraph {
         &mut self,
         packet: MeshPacket,
         neighbor_info: protobufs::NeighborInfo,
+        observer: &DeviceKey,
     ) {
         log::info!(
             "Updating graph from neighbor info packet from node {}",
@@ -27,7 +29,7 @@ impl MeshGraph {
             None => neighbor_info.clone().into(),
         };
 
-        self.upsert_node(own_node.clone());
+        let own_node = self.observe_node(own_node, observer);
 
         // Update neighbor nodes, don't insert as this isn't how neighbor info works
         for neighbor in neighbor_info.neighbors {
@@ -40,15 +42,16 @@ impl MeshGraph {
                 }
             };
 
-            self.upsert_edge(
-                own_node.clone(),
+            self.observe_edge(
+                own_node,
                 remote_node,
                 GraphEdge::from_neighbor(own_node.node_num, neighbor),
+                observer,
             );
         }
     }
 
-    pub fn update_from_node_info(&mut self, node_info: protobufs::NodeInfo) {
+    pub fn update_from_node_info(&mut self, node_info: protobufs::NodeInfo, observer: &DeviceKey) {
         log::info!(
             "Updating graph from node info packet from node {}",
             node_info.num
@@ -71,13 +74,19 @@ impl MeshGraph {
                 node_num: node_info.num,
                 last_heard: chrono::Utc::now().naive_utc(),
                 timeout_duration: DEFAULT_NODE_TIMEOUT_DURATION,
+                stale: false,
             },
         };
 
-        self.upsert_node(own_node);
+        self.observe_node(own_node, observer);
     }
 
-    pub fn update_from_position(&mut self, packet: MeshPacket, _position: protobufs::Position) {
+    pub fn update_from_position(
+        &mut self,
+        packet: MeshPacket,
+        _position: protobufs::Position,
+        observer: &DeviceKey,
+    ) {
         log::info!(
             "Updating graph from position packet from node {}",
             packet.from
@@ -92,9 +101,10 @@ impl MeshGraph {
                 node_num: packet.from,
                 last_heard: chrono::Utc::now().naive_utc(),
                 timeout_duration: DEFAULT_NODE_TIMEOUT_DURATION,
+                stale: false,
             },
         };
 
-        self.upsert_node(own_node);
+        self.observe_node(own_node, observer);
     }
 }
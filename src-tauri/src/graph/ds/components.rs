@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use meshtastic::ts::specta::{self, Type};
+use petgraph::{algo::kosaraju_scc, graphmap::DiGraphMap, unionfind::UnionFind};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphComponent {
+    pub node_nums: Vec<u32>,
+    pub size: u32,
+}
+
+impl GraphComponent {
+    fn new(mut node_nums: Vec<u32>) -> Self {
+        node_nums.sort();
+
+        Self {
+            size: node_nums.len() as u32,
+            node_nums,
+        }
+    }
+}
+
+/// Groups of nodes that can reach each other, largest first
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphComponents {
+    pub weak: Vec<GraphComponent>, // connected when ignoring link direction
+    pub strong: Vec<GraphComponent>, // every node can reach every other along link direction
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ComponentChangeKind {
+    Split,
+    Merge,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentChange {
+    pub kind: ComponentChangeKind,
+    pub node_groups: Vec<Vec<u32>>, // groups a component split into, or groups that merged
+}
+
+fn sort_components(components: &mut [GraphComponent]) {
+    components.sort_by(|a, b| {
+        b.size
+            .cmp(&a.size)
+            .then_with(|| a.node_nums.first().cmp(&b.node_nums.first()))
+    });
+}
+
+/// Computes the weakly and strongly connected components of a directed graph.
+pub fn compute_components(
+    node_nums: impl Iterator<Item = u32>,
+    edges: impl Iterator<Item = (u32, u32)>,
+) -> GraphComponents {
+    let mut graph = DiGraphMap::<u32, ()>::new();
+
+    for node_num in node_nums {
+        graph.add_node(node_num);
+    }
+
+    for (from, to) in edges {
+        graph.add_edge(from, to, ());
+    }
+
+    let indices: HashMap<u32, usize> = graph
+        .nodes()
+        .enumerate()
+        .map(|(index, node_num)| (node_num, index))
+        .collect();
+
+    let mut union_find = UnionFind::<usize>::new(indices.len());
+
+    for (from, to, _) in graph.all_edges() {
+        union_find.union(indices[&from], indices[&to]);
+    }
+
+    let mut weak_groups: HashMap<usize, Vec<u32>> = HashMap::new();
+
+    for (node_num, index) in indices.iter() {
+        weak_groups
+            .entry(union_find.find(*index))
+            .or_default()
+            .push(*node_num);
+    }
+
+    let mut weak: Vec<GraphComponent> =
+        weak_groups.into_values().map(GraphComponent::new).collect();
+    let mut strong: Vec<GraphComponent> = kosaraju_scc(&graph)
+        .into_iter()
+        .map(GraphComponent::new)
+        .collect();
+
+    sort_components(&mut weak);
+    sort_components(&mut strong);
+
+    GraphComponents { weak, strong }
+}
+
+fn build_membership(components: &[GraphComponent]) -> HashMap<u32, usize> {
+    components
+        .iter()
+        .enumerate()
+        .flat_map(|(index, component)| component.node_nums.iter().map(move |n| (*n, index)))
+        .collect()
+}
+
+/// Groups the nodes of `components` by which of `other` they belong to,
+/// ignoring nodes that only exist in one of the two.
+fn find_regrouped(components: &[GraphComponent], other: &[GraphComponent]) -> Vec<Vec<Vec<u32>>> {
+    let other_membership = build_membership(other);
+    let mut regrouped = vec![];
+
+    for component in components {
+        let mut groups: HashMap<usize, Vec<u32>> = HashMap::new();
+
+        for node_num in component.node_nums.iter() {
+            if let Some(index) = other_membership.get(node_num) {
+                groups.entry(*index).or_default().push(*node_num);
+            }
+        }
+
+        if groups.len() > 1 {
+            let mut groups: Vec<Vec<u32>> = groups.into_values().collect();
+            groups.sort();
+            regrouped.push(groups);
+        }
+    }
+
+    regrouped
+}
+
+/// Finds weak components that split apart or merged together. Nodes that
+/// appeared or disappeared between the two don't count as splits or merges.
+pub fn detect_component_changes(
+    previous: &GraphComponents,
+    current: &GraphComponents,
+) -> Vec<ComponentChange> {
+    let splits = find_regrouped(&previous.weak, &current.weak)
+        .into_iter()
+        .map(|node_groups| ComponentChange {
+            kind: ComponentChangeKind::Split,
+            node_groups,
+        });
+
+    let merges = find_regrouped(&current.weak, &previous.weak)
+        .into_iter()
+        .map(|node_groups| ComponentChange {
+            kind: ComponentChangeKind::Merge,
+            node_groups,
+        });
+
+    splits.chain(merges).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak_and_strong_components() {
+        let components = compute_components(
+            [1, 2, 3, 4].into_iter(),
+            [(1, 2), (2, 1), (2, 3)].into_iter(),
+        );
+
+        assert_eq!(
+            components.weak,
+            vec![
+                GraphComponent::new(vec![1, 2, 3]),
+                GraphComponent::new(vec![4])
+            ]
+        );
+        assert_eq!(components.strong[0], GraphComponent::new(vec![1, 2]));
+        assert_eq!(components.strong.len(), 3);
+    }
+
+    #[test]
+    fn split_and_merge() {
+        let joined = compute_components([1, 2, 3].into_iter(), [(1, 2), (2, 3)].into_iter());
+        let split = compute_components([1, 2, 3].into_iter(), [(1, 2)].into_iter());
+
+        let changes = detect_component_changes(&joined, &split);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ComponentChangeKind::Split);
+        assert_eq!(changes[0].node_groups, vec![vec![1, 2], vec![3]]);
+
+        let changes = detect_component_changes(&split, &joined);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ComponentChangeKind::Merge);
+
+        // A new, unconnected node is neither a split nor a merge
+        let grown = compute_components([1, 2, 3, 4].into_iter(), [(1, 2), (2, 3)].into_iter());
+        assert!(detect_component_changes(&joined, &grown).is_empty());
+    }
+}
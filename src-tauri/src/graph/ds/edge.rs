@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use chrono::NaiveDateTime;
 use log::trace;
@@ -9,15 +9,34 @@ use meshtastic::{
 use serde::{Deserialize, Serialize};
 
 use crate::graph::api::update_from_packet::DEFAULT_NODE_TIMEOUT_DURATION;
+use crate::state::DeviceKey;
+
+use super::observation::EdgeObservation;
+
+// LoRa can decode packets well below the noise floor, so SNR is mapped onto
+// link quality between these bounds
+const MIN_USABLE_SNR: f64 = -20.0;
+const MAX_USEFUL_SNR: f64 = 10.0;
+const MIN_LINK_QUALITY: f64 = 0.01;
+
+pub fn link_quality_from_snr(snr: f64) -> f64 {
+    ((snr - MIN_USABLE_SNR) / (MAX_USEFUL_SNR - MIN_USABLE_SNR)).clamp(MIN_LINK_QUALITY, 1.0)
+}
+
+pub fn link_cost_from_snr(snr: f64) -> f64 {
+    -link_quality_from_snr(snr).ln()
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
 pub struct GraphEdge {
-    snr: f64,
-    from: u32,
-    to: u32,
+    pub snr: f64,
+    pub from: u32,
+    pub to: u32,
     pub last_heard: NaiveDateTime,
     pub timeout_duration: Duration,
+    pub stale: bool, // timed out, but not yet removed
+    pub observers: HashMap<DeviceKey, EdgeObservation>, // radios that have reported this edge
 }
 
 impl GraphEdge {
@@ -46,6 +65,35 @@ impl GraphEdge {
             to: to_node_id,
             last_heard: chrono::Utc::now().naive_utc(),
             timeout_duration: Duration::from_secs(timeout_secs),
+            stale: false,
+            observers: HashMap::new(),
+        }
+    }
+
+    /// Estimates how likely a packet is to cross this link, from 0 to 1.
+    pub fn link_quality(&self) -> f64 {
+        link_quality_from_snr(self.snr)
+    }
+
+    /// Routing cost of this link. Costs are additive along a path, so the path
+    /// with the lowest total cost is the one most likely to deliver a packet.
+    pub fn link_cost(&self) -> f64 {
+        link_cost_from_snr(self.snr)
+    }
+
+    /// Records an observation of this edge by a radio, and resolves the
+    /// merged SNR and last heard time across all observers. The most recent
+    /// observation wins, since older SNR readings describe stale link conditions.
+    pub fn add_observation(&mut self, observer: &DeviceKey, observation: EdgeObservation) {
+        self.observers.insert(observer.clone(), observation);
+
+        if let Some(latest) = self
+            .observers
+            .values()
+            .max_by_key(|observation| observation.last_heard)
+        {
+            self.snr = latest.snr;
+            self.last_heard = latest.last_heard;
         }
     }
 }
@@ -1,12 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use petgraph::graphmap::GraphMap;
+use petgraph::{graphmap::GraphMap, Direction};
 use serde::{Deserialize, Serialize};
 use tauri::async_runtime::JoinHandle;
 
+use crate::state::DeviceKey;
+
 use super::{
+    components::{compute_components, detect_component_changes, ComponentChange, GraphComponents},
     edge,
+    history::{GraphChange, GraphHistory},
     node::{self, GraphNode},
+    observation::{EdgeObservation, NodeObservation},
+    timeout::{GraphCleanResult, GraphTimeoutConfig, TimeoutState},
 };
 
 pub type InternalGraph = GraphMap<node::GraphNode, edge::GraphEdge, petgraph::Directed>;
@@ -16,8 +22,17 @@ pub type InternalGraph = GraphMap<node::GraphNode, edge::GraphEdge, petgraph::Di
 pub struct MeshGraph {
     graph: InternalGraph,
     pub nodes_lookup: HashMap<u32, GraphNode>, // TODO use NodeId -- need to implement serialize and deserialize
+    pub node_observations: HashMap<u32, HashMap<DeviceKey, NodeObservation>>, // radios that have heard each node
     #[serde(skip)]
     pub timeout_handle: Option<JoinHandle<()>>,
+    #[serde(skip)]
+    pub timeout_config: GraphTimeoutConfig,
+    #[serde(skip)]
+    pub history: GraphHistory,
+    #[serde(skip)]
+    pub components: GraphComponents,
+    #[serde(skip)]
+    component_changes: Vec<ComponentChange>, // splits and merges not yet dispatched
 }
 
 impl Clone for MeshGraph {
@@ -25,7 +40,12 @@ impl Clone for MeshGraph {
         Self {
             graph: self.graph.clone(),
             nodes_lookup: self.nodes_lookup.clone(),
+            node_observations: self.node_observations.clone(),
             timeout_handle: None,
+            timeout_config: self.timeout_config.clone(),
+            history: GraphHistory::default(), // only the shared graph state keeps history
+            components: self.components.clone(),
+            component_changes: vec![],
         }
     }
 }
@@ -35,7 +55,12 @@ impl MeshGraph {
         Self {
             graph: GraphMap::new(),
             nodes_lookup: HashMap::new(),
+            node_observations: HashMap::new(),
             timeout_handle: None,
+            timeout_config: GraphTimeoutConfig::default(),
+            history: GraphHistory::default(),
+            components: GraphComponents::default(),
+            component_changes: vec![],
         }
     }
 }
@@ -51,19 +76,52 @@ impl MeshGraph {
         self.nodes_lookup.get(&node_num).cloned()
     }
 
+    pub fn get_nodes(&self) -> Vec<GraphNode> {
+        self.nodes_lookup.values().cloned().collect()
+    }
+
     pub fn contains_node(&self, node_num: u32) -> bool {
         self.nodes_lookup.contains_key(&node_num)
     }
 
     pub fn upsert_node(&mut self, node: GraphNode) -> GraphNode {
-        if self.contains_node(node.node_num) {
-            self.remove_node(node.node_num);
+        let mut connected_edges = vec![];
+
+        if let Some(existing_node) = self.get_node(node.node_num) {
+            // Removing a node also removes its edges, so they need to be re-added
+            connected_edges = self
+                .graph
+                .all_edges()
+                .filter(|(source, target, _)| *source == existing_node || *target == existing_node)
+                .map(|(source, target, edge)| (source.node_num, target.node_num, edge.clone()))
+                .collect();
+
+            self.remove_graph_node(node.node_num);
+        }
+
+        let created_node = self.add_node(node);
+
+        for (source_num, target_num, edge) in connected_edges {
+            if let (Some(source), Some(target)) =
+                (self.get_node(source_num), self.get_node(target_num))
+            {
+                self.graph.add_edge(source, target, edge);
+            }
         }
 
-        self.add_node(node)
+        created_node
     }
 
     pub fn remove_node(&mut self, node_num: u32) -> Option<GraphNode> {
+        let removed_node = self.remove_graph_node(node_num)?;
+
+        self.node_observations.remove(&node_num);
+        self.record_change(GraphChange::NodeRemoved { node_num });
+
+        Some(removed_node)
+    }
+
+    fn remove_graph_node(&mut self, node_num: u32) -> Option<GraphNode> {
         let graph_node = self.get_node(node_num)?;
 
         if self.graph.remove_node(graph_node) == false {
@@ -73,6 +131,76 @@ impl MeshGraph {
 
         self.nodes_lookup.remove(&node_num)
     }
+
+    /// Records that a radio has heard a node, and upserts the node with the
+    /// most recent last heard time across all radios.
+    pub fn observe_node(&mut self, node: GraphNode, observer: &DeviceKey) -> GraphNode {
+        let is_new_node = !self.contains_node(node.node_num);
+
+        let observations = self.node_observations.entry(node.node_num).or_default();
+
+        observations.insert(
+            observer.clone(),
+            NodeObservation {
+                last_heard: node.last_heard,
+            },
+        );
+
+        let last_heard = observations
+            .values()
+            .map(|observation| observation.last_heard)
+            .max()
+            .unwrap_or(node.last_heard);
+
+        let upserted_node = self.upsert_node(GraphNode {
+            last_heard,
+            stale: false,
+            ..node
+        });
+
+        if is_new_node {
+            self.record_change(GraphChange::NodeAdded {
+                node_num: node.node_num,
+            });
+        }
+
+        upserted_node
+    }
+
+    pub fn get_node_observers(&self, node_num: u32) -> Vec<DeviceKey> {
+        self.node_observations
+            .get(&node_num)
+            .map(|observations| observations.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Counts the distinct nodes linked to a node in either direction.
+    pub fn count_neighbors(&self, node_num: u32) -> u32 {
+        let node = match self.get_node(node_num) {
+            Some(node) => node,
+            None => return 0,
+        };
+
+        let neighbors: HashSet<u32> = self
+            .graph
+            .neighbors_directed(node, Direction::Outgoing)
+            .chain(self.graph.neighbors_directed(node, Direction::Incoming))
+            .map(|neighbor| neighbor.node_num)
+            .collect();
+
+        neighbors.len() as u32
+    }
+
+    /// Counts groups of linked nodes that have no links between them, ignoring
+    /// link direction. Nodes without any links, such as nodes only known from
+    /// NodeInfo packets, don't count as partitions.
+    pub fn count_partitions(&self) -> u32 {
+        self.components
+            .weak
+            .iter()
+            .filter(|component| component.size > 1)
+            .count() as u32
+    }
 }
 
 impl MeshGraph {
@@ -83,39 +211,326 @@ impl MeshGraph {
         edge: edge::GraphEdge,
     ) -> Option<edge::GraphEdge> {
         if self.graph.contains_edge(source, target) {
-            self.remove_edge(source, target); // Remove the edge if it exists
+            self.graph.remove_edge(source, target); // Remove the edge if it exists
         }
 
         self.graph.add_edge(source, target, edge)
     }
 
     pub fn remove_edge(&mut self, from: GraphNode, to: GraphNode) -> Option<edge::GraphEdge> {
-        self.graph.remove_edge(from, to)
+        let removed_edge = self.graph.remove_edge(from, to)?;
+
+        self.record_change(GraphChange::EdgeRemoved {
+            from: from.node_num,
+            to: to.node_num,
+        });
+
+        Some(removed_edge)
+    }
+
+    pub fn get_edge(&self, from: GraphNode, to: GraphNode) -> Option<&edge::GraphEdge> {
+        self.graph.edge_weight(from, to)
+    }
+
+    pub fn get_edges(&self) -> Vec<(GraphNode, GraphNode, &edge::GraphEdge)> {
+        self.graph.all_edges().collect()
+    }
+
+    /// Records that a radio has observed an edge, merging the observation
+    /// with those previously made by other radios.
+    pub fn observe_edge(
+        &mut self,
+        source: GraphNode,
+        target: GraphNode,
+        edge: edge::GraphEdge,
+        observer: &DeviceKey,
+    ) -> Option<edge::GraphEdge> {
+        let observation = EdgeObservation {
+            snr: edge.snr,
+            last_heard: edge.last_heard,
+        };
+
+        let is_new_edge = !self.graph.contains_edge(source, target);
+
+        let mut merged_edge = match self.graph.edge_weight(source, target) {
+            Some(existing_edge) => edge::GraphEdge {
+                observers: existing_edge.observers.clone(),
+                ..edge
+            },
+            None => edge,
+        };
+
+        merged_edge.add_observation(observer, observation);
+
+        let snr = merged_edge.snr;
+        let previous_edge = self.upsert_edge(source, target, merged_edge);
+
+        if is_new_edge {
+            self.record_change(GraphChange::EdgeAdded {
+                from: source.node_num,
+                to: target.node_num,
+                snr,
+            });
+        }
+
+        previous_edge
+    }
+}
+
+impl MeshGraph {
+    /// Records a change to the topology of the graph, and checks whether it
+    /// split or merged any components.
+    fn record_change(&mut self, change: GraphChange) {
+        self.history.record(change);
+
+        let components = compute_components(
+            self.nodes_lookup.keys().cloned(),
+            self.graph
+                .all_edges()
+                .map(|(source, target, _)| (source.node_num, target.node_num)),
+        );
+
+        for change in detect_component_changes(&self.components, &components) {
+            log::info!("Mesh components changed: {:?}", change);
+            self.component_changes.push(change);
+        }
+
+        self.components = components;
+    }
+
+    /// Takes the component splits and merges recorded since this was last called.
+    pub fn take_component_changes(&mut self) -> Vec<ComponentChange> {
+        std::mem::take(&mut self.component_changes)
     }
 }
 
 impl MeshGraph {
-    pub fn clean(&mut self) {
+    /// Builds the graph as seen by a single radio, using that radio's own
+    /// SNR and last heard values instead of the merged ones.
+    pub fn filter_by_observer(&self, observer: &DeviceKey) -> MeshGraph {
+        let mut filtered_graph = MeshGraph::new();
+
+        for (node_num, observations) in self.node_observations.iter() {
+            let (node, observation) = match (self.get_node(*node_num), observations.get(observer)) {
+                (Some(n), Some(o)) => (n, o),
+                _ => continue,
+            };
+
+            filtered_graph.add_node(GraphNode {
+                last_heard: observation.last_heard,
+                ..node
+            });
+
+            filtered_graph
+                .node_observations
+                .entry(*node_num)
+                .or_default()
+                .insert(observer.clone(), observation.clone());
+        }
+
+        for (source, target, edge) in self.graph.all_edges() {
+            let observation = match edge.observers.get(observer) {
+                Some(o) => o.clone(),
+                None => continue,
+            };
+
+            let (filtered_source, filtered_target) = match (
+                filtered_graph.get_node(source.node_num),
+                filtered_graph.get_node(target.node_num),
+            ) {
+                (Some(s), Some(t)) => (s, t),
+                _ => continue,
+            };
+
+            let mut filtered_edge = edge::GraphEdge {
+                observers: HashMap::new(),
+                ..edge.clone()
+            };
+
+            filtered_edge.add_observation(observer, observation);
+
+            filtered_graph
+                .graph
+                .add_edge(filtered_source, filtered_target, filtered_edge);
+        }
+
+        filtered_graph
+    }
+}
+
+impl MeshGraph {
+    pub fn clean(&mut self) -> GraphCleanResult {
         let now = chrono::Utc::now().naive_utc();
+        let mut result = GraphCleanResult::default();
 
         // Edges will be removed if either the source or target node is removed
         let mut nodes_to_remove = vec![];
+        let mut nodes_to_mark_stale = vec![];
 
         for node in self.nodes_lookup.values() {
-            if now - node.last_heard
-                > chrono::TimeDelta::from_std(node.timeout_duration)
-                    .expect("Duration out of range of TimeDelta")
+            let timeout = self.timeout_config.get_node_timeout(node.timeout_duration);
+
+            match self
+                .timeout_config
+                .get_timeout_state(now - node.last_heard, timeout)
             {
-                log::trace!("Node {} has timed out", node.node_num);
-                nodes_to_remove.push(node.node_num);
-            } else {
-                log::trace!("Node {} has not timed out", node.node_num);
+                TimeoutState::Active => {
+                    log::trace!("Node {} has not timed out", node.node_num);
+                }
+                TimeoutState::Stale => {
+                    if !node.stale {
+                        log::trace!("Node {} is stale", node.node_num);
+                        nodes_to_mark_stale.push(*node);
+                    }
+                }
+                TimeoutState::Expired => {
+                    log::trace!("Node {} has timed out", node.node_num);
+                    nodes_to_remove.push(node.node_num);
+                }
             }
         }
 
+        for node in nodes_to_mark_stale {
+            self.upsert_node(GraphNode {
+                stale: true,
+                ..node
+            });
+            result.stale_nodes.push(node.node_num);
+        }
+
         for node_num in nodes_to_remove {
             self.remove_node(node_num);
+            result.removed_nodes.push(node_num);
             log::debug!("Node {} removed from graph", node_num);
         }
+
+        // Edges time out independently of their nodes
+
+        let mut edges_to_remove = vec![];
+        let mut edges_to_mark_stale = vec![];
+
+        for (source, target, edge) in self.graph.all_edges() {
+            let timeout = self.timeout_config.get_edge_timeout(edge.timeout_duration);
+
+            match self
+                .timeout_config
+                .get_timeout_state(now - edge.last_heard, timeout)
+            {
+                TimeoutState::Active => {}
+                TimeoutState::Stale => {
+                    if !edge.stale {
+                        edges_to_mark_stale.push((source, target));
+                    }
+                }
+                TimeoutState::Expired => edges_to_remove.push((source, target)),
+            }
+        }
+
+        for (source, target) in edges_to_mark_stale {
+            if let Some(edge) = self.graph.edge_weight_mut(source, target) {
+                edge.stale = true;
+                result.stale_edges.push((source.node_num, target.node_num));
+            }
+        }
+
+        for (source, target) in edges_to_remove {
+            self.remove_edge(source, target);
+            result
+                .removed_edges
+                .push((source.node_num, target.node_num));
+            log::debug!(
+                "Edge between {} and {} removed from graph",
+                source.node_num,
+                target.node_num
+            );
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+    use crate::graph::ds::test_utils::{build_edge, build_node};
+
+    fn seconds_ago(secs: i64) -> NaiveDateTime {
+        chrono::Utc::now().naive_utc() - chrono::TimeDelta::seconds(secs)
+    }
+
+    /// Two radios that both heard nodes 1 and 2, with radio "b" hearing the
+    /// link more recently than radio "a"
+    fn build_observed_graph() -> MeshGraph {
+        let mut graph = MeshGraph::new();
+        let (a, b): (DeviceKey, DeviceKey) = ("a".into(), "b".into());
+
+        for node_num in [1, 2] {
+            graph.observe_node(build_node(node_num, seconds_ago(60)), &a);
+            graph.observe_node(build_node(node_num, seconds_ago(10)), &b);
+        }
+
+        let (source, target) = (graph.get_node(1).unwrap(), graph.get_node(2).unwrap());
+
+        graph.observe_edge(source, target, build_edge(1, 2, -5.0, seconds_ago(60)), &a);
+        graph.observe_edge(source, target, build_edge(1, 2, 3.0, seconds_ago(10)), &b);
+
+        graph
+    }
+
+    #[test]
+    fn merges_edge_observations_from_multiple_radios() {
+        let graph = build_observed_graph();
+        let edge = graph
+            .get_edge(graph.get_node(1).unwrap(), graph.get_node(2).unwrap())
+            .expect("Edge not found");
+
+        assert_eq!(edge.observers.len(), 2);
+        assert_eq!(edge.snr, 3.0);
+        assert_eq!(edge.last_heard, edge.observers["b"].last_heard);
+        assert_eq!(graph.get_edges().len(), 1);
+
+        let mut observers = graph.get_node_observers(1);
+        observers.sort();
+        assert_eq!(observers, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn filters_graph_by_observer() {
+        let graph = build_observed_graph();
+        let filtered = graph.filter_by_observer(&"a".to_string());
+
+        let source = filtered.get_node(1).expect("Node not found");
+        let target = filtered.get_node(2).expect("Node not found");
+        let edge = filtered.get_edge(source, target).expect("Edge not found");
+
+        assert_eq!(
+            source.last_heard,
+            graph.node_observations[&1]["a"].last_heard
+        );
+        assert_eq!(edge.snr, -5.0);
+        assert_eq!(edge.observers.len(), 1);
+        assert_eq!(filtered.get_node_observers(1), vec!["a".to_string()]);
+
+        assert_eq!(
+            graph.filter_by_observer(&"c".to_string()).get_nodes().len(),
+            0
+        );
+    }
+
+    #[test]
+    fn upsert_node_keeps_edges() {
+        let mut graph = build_observed_graph();
+        let node = graph.get_node(1).unwrap();
+
+        graph.upsert_node(GraphNode {
+            stale: true,
+            ..node
+        });
+
+        assert!(graph.get_node(1).unwrap().stale);
+        assert_eq!(graph.get_edges().len(), 1);
+        assert_eq!(graph.count_neighbors(1), 1);
     }
 }
@@ -0,0 +1,540 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::NaiveDateTime;
+use log::{debug, warn};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use crate::graph::api::update_from_packet::DEFAULT_NODE_TIMEOUT_DURATION;
+
+use super::{edge::GraphEdge, graph::MeshGraph, node::GraphNode};
+
+pub const GRAPH_HISTORY_FILE_NAME: &str = "graph_history.jsonl";
+
+/// How often recorded events are written to the history file
+pub const GRAPH_HISTORY_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Events older than this are folded into a snapshot of the topology
+pub const GRAPH_HISTORY_RETENTION_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type")]
+pub enum GraphChange {
+    NodeAdded { node_num: u32 },
+    NodeRemoved { node_num: u32 },
+    EdgeAdded { from: u32, to: u32, snr: f64 },
+    EdgeRemoved { from: u32, to: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphHistoryEvent {
+    pub timestamp: NaiveDateTime,
+    pub change: GraphChange,
+}
+
+/// Nodes and links that appeared or disappeared between two points in time
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphDiff {
+    pub added_nodes: Vec<u32>,
+    pub removed_nodes: Vec<u32>,
+    pub added_edges: Vec<(u32, u32)>,
+    pub removed_edges: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Default)]
+struct TopologyAtTime {
+    nodes: HashMap<u32, NaiveDateTime>,
+    edges: HashMap<(u32, u32), (f64, NaiveDateTime)>,
+}
+
+/// Changes to make to the history file. These are taken while the graph is
+/// locked and written once it has been released.
+#[derive(Debug)]
+pub enum GraphHistoryWrite {
+    Append(Vec<GraphHistoryEvent>),
+    Rewrite(Vec<GraphHistoryEvent>), // replaces the file after compaction
+}
+
+/// An append-only log of topology changes, optionally persisted to a
+/// JSON Lines file so history survives restarts.
+#[derive(Debug, Default)]
+pub struct GraphHistory {
+    events: Vec<GraphHistoryEvent>,
+    store_path: Option<PathBuf>,
+    pending_events: Vec<GraphHistoryEvent>, // recorded but not yet written to the file
+    compacted_until: Option<NaiveDateTime>,
+}
+
+impl GraphHistory {
+    /// Opens the history stored at `store_path`, creating it if it doesn't exist.
+    pub fn open(store_path: PathBuf) -> Result<Self, String> {
+        let mut events = vec![];
+
+        if store_path.exists() {
+            let file = File::open(&store_path).map_err(|e| e.to_string())?;
+
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| e.to_string())?;
+
+                match serde_json::from_str::<GraphHistoryEvent>(&line) {
+                    Ok(event) => events.push(event),
+                    Err(e) => warn!("Skipping invalid graph history entry: {}", e),
+                }
+            }
+        } else if let Some(parent) = store_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        debug!(
+            "Loaded {} graph history events from {:?}",
+            events.len(),
+            store_path
+        );
+
+        let mut history = Self {
+            events,
+            store_path: Some(store_path),
+            ..Default::default()
+        };
+
+        history.end_previous_session(chrono::Utc::now().naive_utc());
+
+        Ok(history)
+    }
+
+    /// Records the removal of everything still in the graph when the history
+    /// was last written. The graph starts empty on launch, so replays after
+    /// this point shouldn't include nodes and edges from the previous run.
+    fn end_previous_session(&mut self, now: NaiveDateTime) {
+        let topology = self.replay_until(NaiveDateTime::MAX);
+
+        // Keep the log in order even if the clock went backwards since the last run
+        let timestamp = self
+            .events
+            .last()
+            .map_or(now, |event| event.timestamp.max(now));
+
+        let mut edges: Vec<(u32, u32)> = topology.edges.into_keys().collect();
+        let mut node_nums: Vec<u32> = topology.nodes.into_keys().collect();
+        edges.sort();
+        node_nums.sort();
+
+        for (from, to) in edges {
+            self.record_at(GraphChange::EdgeRemoved { from, to }, timestamp);
+        }
+
+        for node_num in node_nums {
+            self.record_at(GraphChange::NodeRemoved { node_num }, timestamp);
+        }
+    }
+
+    pub fn record(&mut self, change: GraphChange) {
+        self.record_at(change, chrono::Utc::now().naive_utc());
+    }
+
+    fn record_at(&mut self, change: GraphChange, timestamp: NaiveDateTime) {
+        let event = GraphHistoryEvent { timestamp, change };
+
+        // Recording happens with the graph locked, so the file is written later
+        if self.store_path.is_some() {
+            self.pending_events.push(event.clone());
+        }
+
+        self.events.push(event);
+    }
+
+    /// Takes the events that haven't been written to the history file yet,
+    /// compacting the history first if events have passed the retention window.
+    pub fn take_pending_write(
+        &mut self,
+        now: NaiveDateTime,
+    ) -> Option<(PathBuf, GraphHistoryWrite)> {
+        let store_path = self.store_path.clone()?;
+
+        if self.compact(now - chrono::TimeDelta::days(GRAPH_HISTORY_RETENTION_DAYS)) {
+            self.pending_events.clear();
+            return Some((store_path, GraphHistoryWrite::Rewrite(self.events.clone())));
+        }
+
+        if self.pending_events.is_empty() {
+            return None;
+        }
+
+        Some((
+            store_path,
+            GraphHistoryWrite::Append(std::mem::take(&mut self.pending_events)),
+        ))
+    }
+
+    /// Replaces the events up to `cutoff` with the events needed to rebuild
+    /// the topology at that time, so replays don't walk the full history.
+    /// Returns `false` if there was nothing to compact.
+    fn compact(&mut self, cutoff: NaiveDateTime) -> bool {
+        let has_expired_events = self.events.iter().any(|event| {
+            event.timestamp <= cutoff && self.compacted_until.map_or(true, |c| event.timestamp > c)
+        });
+
+        if !has_expired_events {
+            return false;
+        }
+
+        let topology = self.replay_until(cutoff);
+
+        let mut snapshot: Vec<GraphHistoryEvent> = topology
+            .nodes
+            .into_iter()
+            .map(|(node_num, timestamp)| GraphHistoryEvent {
+                timestamp,
+                change: GraphChange::NodeAdded { node_num },
+            })
+            .chain(
+                topology
+                    .edges
+                    .into_iter()
+                    .map(|((from, to), (snr, timestamp))| GraphHistoryEvent {
+                        timestamp,
+                        change: GraphChange::EdgeAdded { from, to, snr },
+                    }),
+            )
+            .collect();
+
+        // Nodes need to be added before the edges between them
+        snapshot.sort_by_key(|event| {
+            (
+                event.timestamp,
+                matches!(event.change, GraphChange::EdgeAdded { .. }),
+            )
+        });
+
+        let retained_start = self
+            .events
+            .iter()
+            .position(|event| event.timestamp > cutoff)
+            .unwrap_or(self.events.len());
+
+        debug!(
+            "Compacted {} graph history events into {}",
+            retained_start,
+            snapshot.len()
+        );
+
+        snapshot.extend(self.events.drain(retained_start..));
+        self.events = snapshot;
+        self.compacted_until = Some(cutoff);
+
+        true
+    }
+
+    pub fn get_events(&self) -> &[GraphHistoryEvent] {
+        &self.events
+    }
+
+    fn replay_until(&self, timestamp: NaiveDateTime) -> TopologyAtTime {
+        let mut topology = TopologyAtTime::default();
+
+        // Events are appended in order, so replay can stop at the first later event
+        for event in self.events.iter().take_while(|e| e.timestamp <= timestamp) {
+            match event.change {
+                GraphChange::NodeAdded { node_num } => {
+                    topology.nodes.insert(node_num, event.timestamp);
+                }
+                GraphChange::NodeRemoved { node_num } => {
+                    topology.nodes.remove(&node_num);
+                    topology
+                        .edges
+                        .retain(|(from, to), _| *from != node_num && *to != node_num);
+                }
+                GraphChange::EdgeAdded { from, to, snr } => {
+                    topology.edges.insert((from, to), (snr, event.timestamp));
+                }
+                GraphChange::EdgeRemoved { from, to } => {
+                    topology.edges.remove(&(from, to));
+                }
+            }
+        }
+
+        topology
+    }
+
+    /// Reconstructs the topology of the graph at a past point in time.
+    pub fn get_graph_at(&self, timestamp: NaiveDateTime) -> MeshGraph {
+        let topology = self.replay_until(timestamp);
+        let mut graph = MeshGraph::new();
+
+        for (node_num, last_heard) in topology.nodes.iter() {
+            graph.upsert_node(GraphNode {
+                node_num: *node_num,
+                last_heard: *last_heard,
+                timeout_duration: DEFAULT_NODE_TIMEOUT_DURATION,
+                stale: false,
+            });
+        }
+
+        for ((from, to), (snr, last_heard)) in topology.edges.iter() {
+            let (source, target) = match (graph.get_node(*from), graph.get_node(*to)) {
+                (Some(s), Some(t)) => (s, t),
+                _ => continue,
+            };
+
+            graph.upsert_edge(
+                source,
+                target,
+                GraphEdge {
+                    snr: *snr,
+                    from: *from,
+                    to: *to,
+                    last_heard: *last_heard,
+                    timeout_duration: DEFAULT_NODE_TIMEOUT_DURATION,
+                    stale: false,
+                    observers: HashMap::new(),
+                },
+            );
+        }
+
+        graph
+    }
+
+    /// Lists the nodes and edges that appeared or disappeared between two points in time.
+    pub fn diff(&self, start: NaiveDateTime, end: NaiveDateTime) -> GraphDiff {
+        let start_topology = self.replay_until(start);
+        let end_topology = self.replay_until(end);
+
+        let start_nodes: HashSet<u32> = start_topology.nodes.keys().cloned().collect();
+        let end_nodes: HashSet<u32> = end_topology.nodes.keys().cloned().collect();
+        let start_edges: HashSet<(u32, u32)> = start_topology.edges.keys().cloned().collect();
+        let end_edges: HashSet<(u32, u32)> = end_topology.edges.keys().cloned().collect();
+
+        let mut diff = GraphDiff {
+            added_nodes: end_nodes.difference(&start_nodes).cloned().collect(),
+            removed_nodes: start_nodes.difference(&end_nodes).cloned().collect(),
+            added_edges: end_edges.difference(&start_edges).cloned().collect(),
+            removed_edges: start_edges.difference(&end_edges).cloned().collect(),
+        };
+
+        diff.added_nodes.sort();
+        diff.removed_nodes.sort();
+        diff.added_edges.sort();
+        diff.removed_edges.sort();
+
+        diff
+    }
+}
+
+/// Writes changes taken from `GraphHistory::take_pending_write` to the history file.
+pub fn write_history(store_path: &Path, write: GraphHistoryWrite) -> Result<(), String> {
+    match write {
+        GraphHistoryWrite::Append(events) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(store_path)
+                .map_err(|e| e.to_string())?;
+
+            write_events(file, &events)
+        }
+        GraphHistoryWrite::Rewrite(events) => {
+            // Write to a temporary file first so a failed write keeps the old history
+            let temp_path = store_path.with_extension("jsonl.tmp");
+            let file = File::create(&temp_path).map_err(|e| e.to_string())?;
+
+            write_events(file, &events)?;
+
+            fs::rename(&temp_path, store_path).map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn write_events(file: File, events: &[GraphHistoryEvent]) -> Result<(), String> {
+    let mut writer = BufWriter::new(file);
+
+    for event in events {
+        let line = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        writeln!(writer, "{}", line).map_err(|e| e.to_string())?;
+    }
+
+    writer.flush().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_between_times() {
+        let mut history = GraphHistory::default();
+
+        history.record(GraphChange::NodeAdded { node_num: 1 });
+        history.record(GraphChange::NodeAdded { node_num: 2 });
+        history.record(GraphChange::EdgeAdded {
+            from: 1,
+            to: 2,
+            snr: 3.0,
+        });
+
+        let middle = chrono::Utc::now().naive_utc();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        history.record(GraphChange::NodeRemoved { node_num: 2 });
+        history.record(GraphChange::NodeAdded { node_num: 3 });
+
+        let end = chrono::Utc::now().naive_utc();
+
+        assert_eq!(history.get_graph_at(middle).get_edges().len(), 1);
+
+        let diff = history.diff(middle, end);
+
+        assert_eq!(diff.added_nodes, vec![3]);
+        assert_eq!(diff.removed_nodes, vec![2]);
+        assert_eq!(diff.removed_edges, vec![(1, 2)]);
+        assert!(diff.added_edges.is_empty());
+    }
+
+    #[test]
+    fn buffers_events_until_taken() {
+        let mut history = GraphHistory {
+            store_path: Some(PathBuf::from("graph_history.jsonl")),
+            ..Default::default()
+        };
+        let now = chrono::Utc::now().naive_utc();
+
+        history.record(GraphChange::NodeAdded { node_num: 1 });
+
+        match history.take_pending_write(now) {
+            Some((_, GraphHistoryWrite::Append(events))) => assert_eq!(events.len(), 1),
+            write => panic!("Expected pending events to be appended, got {:?}", write),
+        }
+
+        assert!(history.take_pending_write(now).is_none());
+        assert!(GraphHistory::default().take_pending_write(now).is_none());
+    }
+
+    #[test]
+    fn compacts_events_past_retention_window() {
+        let mut history = GraphHistory {
+            store_path: Some(PathBuf::from("graph_history.jsonl")),
+            ..Default::default()
+        };
+        let now = chrono::Utc::now().naive_utc();
+        let expired = now - chrono::TimeDelta::days(GRAPH_HISTORY_RETENTION_DAYS + 1);
+
+        history.record_at(GraphChange::NodeAdded { node_num: 1 }, expired);
+        history.record_at(GraphChange::NodeAdded { node_num: 2 }, expired);
+        history.record_at(
+            GraphChange::EdgeAdded {
+                from: 1,
+                to: 2,
+                snr: 3.0,
+            },
+            expired,
+        );
+        history.record_at(
+            GraphChange::NodeRemoved { node_num: 2 },
+            expired + chrono::TimeDelta::seconds(1),
+        );
+        history.record_at(
+            GraphChange::NodeAdded { node_num: 3 },
+            now - chrono::TimeDelta::hours(1),
+        );
+
+        match history.take_pending_write(now) {
+            Some((_, GraphHistoryWrite::Rewrite(events))) => assert_eq!(events.len(), 2),
+            write => panic!("Expected history to be rewritten, got {:?}", write),
+        }
+
+        let mut node_nums: Vec<u32> = history
+            .get_graph_at(now)
+            .get_nodes()
+            .iter()
+            .map(|node| node.node_num)
+            .collect();
+        node_nums.sort();
+
+        assert_eq!(node_nums, vec![1, 3]);
+        assert!(history.take_pending_write(now).is_none());
+    }
+
+    #[test]
+    fn writes_and_reopens_history_file() {
+        let store_path = std::env::temp_dir().join(format!(
+            "graph_history_{}.jsonl",
+            crate::device::helpers::generate_rand_id::<u32>()
+        ));
+        let mut history = GraphHistory::open(store_path.clone()).unwrap();
+        let now = chrono::Utc::now().naive_utc();
+
+        history.record(GraphChange::NodeAdded { node_num: 1 });
+        let (path, write) = history.take_pending_write(now).unwrap();
+        write_history(&path, write).unwrap();
+
+        history.record(GraphChange::NodeAdded { node_num: 2 });
+        let (path, write) = history.take_pending_write(now).unwrap();
+        write_history(&path, write).unwrap();
+
+        let written_at = chrono::Utc::now().naive_utc();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let reopened = GraphHistory::open(store_path.clone()).unwrap();
+        let reopened_at = chrono::Utc::now().naive_utc();
+
+        // Both nodes are marked as removed when the history is reopened
+        assert_eq!(reopened.get_events().len(), 4);
+        assert_eq!(reopened.get_graph_at(written_at).get_nodes().len(), 2);
+        assert!(reopened.get_graph_at(reopened_at).get_nodes().is_empty());
+
+        let _ = fs::remove_file(store_path);
+    }
+
+    #[test]
+    fn reopened_history_excludes_previous_run() {
+        let store_path = std::env::temp_dir().join(format!(
+            "graph_history_{}.jsonl",
+            crate::device::helpers::generate_rand_id::<u32>()
+        ));
+        let mut history = GraphHistory::open(store_path.clone()).unwrap();
+
+        history.record(GraphChange::NodeAdded { node_num: 1 });
+        history.record(GraphChange::NodeAdded { node_num: 2 });
+        history.record(GraphChange::EdgeAdded {
+            from: 1,
+            to: 2,
+            snr: 3.0,
+        });
+        let (path, write) = history
+            .take_pending_write(chrono::Utc::now().naive_utc())
+            .unwrap();
+        write_history(&path, write).unwrap();
+
+        // The next run only hears node 3
+        let mut history = GraphHistory::open(store_path.clone()).unwrap();
+        history.record(GraphChange::NodeAdded { node_num: 3 });
+
+        let graph = history.get_graph_at(chrono::Utc::now().naive_utc());
+
+        assert!(graph.get_node(1).is_none());
+        assert!(graph.get_node(2).is_none());
+        assert!(graph.get_node(3).is_some());
+        assert!(graph.get_edges().is_empty());
+
+        let (path, write) = history
+            .take_pending_write(chrono::Utc::now().naive_utc())
+            .unwrap();
+        write_history(&path, write).unwrap();
+
+        // Closing out an already closed run doesn't record anything new
+        let mut history = GraphHistory::open(store_path.clone()).unwrap();
+        let event_count = history.get_events().len();
+        history.end_previous_session(chrono::Utc::now().naive_utc());
+        assert_eq!(history.get_events().len(), event_count);
+
+        let _ = fs::remove_file(store_path);
+    }
+}
@@ -1,3 +1,10 @@
+pub mod components;
 pub mod edge;
 pub mod graph;
+pub mod history;
 pub mod node;
+pub mod observation;
+pub mod timeout;
+
+#[cfg(test)]
+pub mod test_utils;
@@ -16,6 +16,7 @@ pub struct GraphNode {
     pub node_num: u32,
     pub last_heard: NaiveDateTime,
     pub timeout_duration: Duration,
+    pub stale: bool, // timed out, but not yet removed
 }
 
 impl PartialEq<GraphNode> for GraphNode {
@@ -55,6 +56,7 @@ impl From<protobufs::NeighborInfo> for GraphNode {
             last_heard: NaiveDateTime::from_timestamp_millis(chrono::Utc::now().timestamp_millis())
                 .expect("Failed to convert timestamp to NaiveDateTime"),
             timeout_duration: Duration::from_secs(timeout_secs),
+            stale: false,
         }
     }
 }
@@ -88,6 +90,7 @@ impl From<Neighbor> for GraphNode {
             last_heard: NaiveDateTime::from_timestamp_millis(last_heard_secs * 1000)
                 .expect("Failed to convert timestamp to NaiveDateTime"),
             timeout_duration: Duration::from_secs(timeout_secs),
+            stale: false,
         }
     }
 }
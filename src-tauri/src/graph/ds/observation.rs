@@ -0,0 +1,18 @@
+use chrono::NaiveDateTime;
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+/// A single radio's view of a node
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeObservation {
+    pub last_heard: NaiveDateTime,
+}
+
+/// A single radio's view of an edge
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeObservation {
+    pub snr: f64,
+    pub last_heard: NaiveDateTime,
+}
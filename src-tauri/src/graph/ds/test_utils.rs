@@ -0,0 +1,55 @@
+//! Graph fixtures shared by the graph tests
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+
+use super::{edge::GraphEdge, graph::MeshGraph, node::GraphNode};
+
+pub const TEST_TIMEOUT: Duration = Duration::from_secs(60);
+pub const TEST_OBSERVER: &str = "test";
+
+pub fn build_node(node_num: u32, last_heard: NaiveDateTime) -> GraphNode {
+    GraphNode {
+        node_num,
+        last_heard,
+        timeout_duration: TEST_TIMEOUT,
+        stale: false,
+    }
+}
+
+pub fn build_edge(from: u32, to: u32, snr: f64, last_heard: NaiveDateTime) -> GraphEdge {
+    GraphEdge {
+        snr,
+        from,
+        to,
+        last_heard,
+        timeout_duration: TEST_TIMEOUT,
+        stale: false,
+        observers: HashMap::new(),
+    }
+}
+
+/// Builds a graph observed by a single radio, with every node and edge last
+/// heard at the Unix epoch.
+pub fn build_graph(node_nums: &[u32], edges: &[(u32, u32, f64)]) -> MeshGraph {
+    let mut graph = MeshGraph::new();
+    let observer = TEST_OBSERVER.to_string();
+    let last_heard = NaiveDateTime::from_timestamp_opt(0, 0).unwrap();
+
+    for node_num in node_nums {
+        graph.observe_node(build_node(*node_num, last_heard), &observer);
+    }
+
+    for (from, to, snr) in edges {
+        graph.observe_edge(
+            graph.get_node(*from).unwrap(),
+            graph.get_node(*to).unwrap(),
+            build_edge(*from, *to, *snr, last_heard),
+            &observer,
+        );
+    }
+
+    graph
+}
@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_GRAPH_CLEAN_SECONDS: u32 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphTimeoutConfig {
+    pub clean_interval_secs: u32,
+    pub node_timeout_secs: Option<u32>, // overrides per-node timeouts if set
+    pub edge_timeout_secs: Option<u32>, // overrides per-edge timeouts if set
+    pub stale_grace_period_secs: Option<u32>, // if set, timed out items are marked stale for this long before removal
+}
+
+impl Default for GraphTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            clean_interval_secs: DEFAULT_GRAPH_CLEAN_SECONDS,
+            node_timeout_secs: None,
+            edge_timeout_secs: None,
+            stale_grace_period_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutState {
+    Active,
+    Stale,
+    Expired,
+}
+
+impl GraphTimeoutConfig {
+    pub fn get_clean_interval(&self) -> Duration {
+        Duration::from_secs(self.clean_interval_secs.max(1).into())
+    }
+
+    pub fn get_node_timeout(&self, node_timeout: Duration) -> Duration {
+        self.node_timeout_secs
+            .map(|secs| Duration::from_secs(secs.into()))
+            .unwrap_or(node_timeout)
+    }
+
+    pub fn get_edge_timeout(&self, edge_timeout: Duration) -> Duration {
+        self.edge_timeout_secs
+            .map(|secs| Duration::from_secs(secs.into()))
+            .unwrap_or(edge_timeout)
+    }
+
+    /// Determines whether an item last heard `age` ago has timed out.
+    pub fn get_timeout_state(&self, age: chrono::TimeDelta, timeout: Duration) -> TimeoutState {
+        let timeout =
+            chrono::TimeDelta::from_std(timeout).expect("Duration out of range of TimeDelta");
+
+        if age <= timeout {
+            return TimeoutState::Active;
+        }
+
+        let grace_period = match self.stale_grace_period_secs {
+            Some(secs) => chrono::TimeDelta::seconds(secs.into()),
+            None => return TimeoutState::Expired,
+        };
+
+        if age <= timeout + grace_period {
+            TimeoutState::Stale
+        } else {
+            TimeoutState::Expired
+        }
+    }
+}
+
+/// Items that changed during a single graph clean
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphCleanResult {
+    pub stale_nodes: Vec<u32>,
+    pub stale_edges: Vec<(u32, u32)>,
+    pub removed_nodes: Vec<u32>,
+    pub removed_edges: Vec<(u32, u32)>,
+}
+
+impl GraphCleanResult {
+    pub fn has_stale_items(&self) -> bool {
+        !self.stale_nodes.is_empty() || !self.stale_edges.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::ds::{
+        graph::MeshGraph,
+        node::GraphNode,
+        test_utils::{build_edge, build_node, TEST_TIMEOUT as TIMEOUT},
+    };
+
+    fn config_with_grace_period(stale_grace_period_secs: Option<u32>) -> GraphTimeoutConfig {
+        GraphTimeoutConfig {
+            stale_grace_period_secs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn timeout_state_boundaries() {
+        let config = config_with_grace_period(Some(30));
+
+        assert_eq!(
+            config.get_timeout_state(chrono::TimeDelta::seconds(60), TIMEOUT),
+            TimeoutState::Active
+        );
+        assert_eq!(
+            config.get_timeout_state(chrono::TimeDelta::seconds(61), TIMEOUT),
+            TimeoutState::Stale
+        );
+        assert_eq!(
+            config.get_timeout_state(chrono::TimeDelta::seconds(90), TIMEOUT),
+            TimeoutState::Stale
+        );
+        assert_eq!(
+            config.get_timeout_state(chrono::TimeDelta::seconds(91), TIMEOUT),
+            TimeoutState::Expired
+        );
+    }
+
+    #[test]
+    fn timeout_state_without_grace_period() {
+        let config = config_with_grace_period(None);
+
+        assert_eq!(
+            config.get_timeout_state(chrono::TimeDelta::seconds(60), TIMEOUT),
+            TimeoutState::Active
+        );
+        assert_eq!(
+            config.get_timeout_state(chrono::TimeDelta::seconds(61), TIMEOUT),
+            TimeoutState::Expired
+        );
+    }
+
+    #[test]
+    fn timeout_overrides() {
+        let config = GraphTimeoutConfig {
+            node_timeout_secs: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(config.get_node_timeout(TIMEOUT), Duration::from_secs(10));
+        assert_eq!(config.get_edge_timeout(TIMEOUT), TIMEOUT);
+    }
+
+    #[test]
+    fn clean_marks_items_stale_before_removing_them() {
+        let heard_at = |secs| chrono::Utc::now().naive_utc() - chrono::TimeDelta::seconds(secs);
+
+        let mut graph = MeshGraph::new();
+        graph.timeout_config = config_with_grace_period(Some(120));
+
+        let source = graph.upsert_node(build_node(1, heard_at(90)));
+        let target = graph.upsert_node(build_node(2, heard_at(0)));
+
+        graph.upsert_edge(source, target, build_edge(1, 2, 0.0, heard_at(90)));
+
+        let result = graph.clean();
+
+        assert_eq!(result.stale_nodes, vec![1]);
+        assert_eq!(result.stale_edges, vec![(1, 2)]);
+        assert!(result.removed_nodes.is_empty());
+        assert!(result.removed_edges.is_empty());
+        assert!(graph.get_node(1).unwrap().stale);
+
+        // Items already marked stale aren't reported again
+        assert!(!graph.clean().has_stale_items());
+
+        graph.upsert_node(GraphNode {
+            stale: true,
+            ..build_node(1, heard_at(200))
+        });
+
+        let result = graph.clean();
+
+        assert_eq!(result.removed_nodes, vec![1]);
+        assert!(!result.has_stale_items());
+        assert!(graph.get_node(1).is_none());
+        assert!(graph.get_edges().is_empty());
+    }
+}
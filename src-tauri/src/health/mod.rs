@@ -0,0 +1,470 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use log::{debug, trace};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+
+use crate::device::{helpers::get_current_time_u32, MeshDevice};
+use crate::graph::ds::components::{
+    detect_component_changes, ComponentChangeKind, GraphComponents,
+};
+use crate::graph::ds::graph::MeshGraph;
+
+const MAX_HEALTH_SCORE: f32 = 100.0;
+const LOW_BATTERY_PENALTY: f32 = 30.0;
+const BUSY_CHANNEL_PENALTY: f32 = 20.0;
+const ISOLATED_NODE_PENALTY: f32 = 20.0;
+const PARTITION_PENALTY: f32 = 10.0;
+
+// Battery levels of 0 are unknown, and levels above 100 mean the node is externally powered
+const BATTERY_LEVEL_RANGE: std::ops::RangeInclusive<u32> = 1..=100;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthThresholds {
+    pub check_interval_secs: u32,
+    pub silent_node_secs: u32, // time since last heard before a node counts as silent
+    pub low_battery_level: u32, // percent
+    pub battery_drop_level: u32, // percent lost between two checks
+    pub channel_utilization_limit: f32, // percent
+    pub air_util_tx_limit: f32, // percent
+    pub notifications_enabled: bool,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 60,
+            silent_node_secs: 2 * 60 * 60,
+            low_battery_level: 15,
+            battery_drop_level: 20,
+            channel_utilization_limit: 40.0,
+            air_util_tx_limit: 10.0,
+            notifications_enabled: true,
+        }
+    }
+}
+
+impl HealthThresholds {
+    pub fn get_check_interval(&self) -> Duration {
+        Duration::from_secs(self.check_interval_secs.into())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeHealth {
+    pub node_num: u32,
+    pub score: f32, // 0 to 100
+    pub silent: bool,
+    pub seconds_since_heard: Option<u32>,
+    pub battery_level: Option<u32>,
+    pub channel_utilization: Option<f32>,
+    pub air_util_tx: Option<f32>,
+    pub neighbor_count: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkHealth {
+    pub timestamp: u32,
+    pub score: f32, // 0 to 100
+    pub node_count: u32,
+    pub silent_node_count: u32,
+    pub partition_count: u32, // groups of nodes with no links between them
+    pub nodes: Vec<NodeHealth>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum NetworkAlertKind {
+    NodeSilent,
+    BatteryCollapse,
+    ChannelUtilizationSpike,
+    AirUtilTxSpike,
+    PartitionFormed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkAlert {
+    pub kind: NetworkAlertKind,
+    pub node_num: Option<u32>, // `None` for alerts about the whole network
+    pub message: String,
+    pub timestamp: u32,
+}
+
+/// Latest telemetry and last heard time of a node across all connected devices
+#[derive(Clone, Debug, Default)]
+struct NodeReadings {
+    last_heard: Option<u32>,
+    battery_level: Option<u32>,
+    channel_utilization: Option<f32>,
+    air_util_tx: Option<f32>,
+    metrics_timestamp: u32,
+}
+
+fn collect_node_readings<'a>(
+    devices: impl Iterator<Item = &'a MeshDevice>,
+) -> HashMap<u32, NodeReadings> {
+    let mut readings: HashMap<u32, NodeReadings> = HashMap::new();
+
+    for device in devices {
+        for (node_num, node) in device.nodes.iter() {
+            let entry = readings.entry(*node_num).or_default();
+
+            if let Some(last_heard) = node.last_heard.as_ref() {
+                entry.last_heard = entry.last_heard.max(Some(last_heard.timestamp));
+            }
+
+            // Prefer the most recent telemetry reported through any device
+            if let Some(latest) = node.device_metrics.last() {
+                if latest.timestamp >= entry.metrics_timestamp {
+                    entry.metrics_timestamp = latest.timestamp;
+                    entry.battery_level = Some(latest.metrics.battery_level);
+                    entry.channel_utilization = Some(latest.metrics.channel_utilization);
+                    entry.air_util_tx = Some(latest.metrics.air_util_tx);
+                }
+            }
+        }
+    }
+
+    readings
+}
+
+/// Periodically scores the health of the mesh and raises alerts when
+/// conditions worsen. Alerts are only raised when a condition starts, not
+/// on every check while it persists.
+#[derive(Default)]
+pub struct HealthMonitor {
+    pub thresholds: HealthThresholds,
+    pub latest_health: Option<NetworkHealth>,
+    pub monitor_handle: Option<JoinHandle<()>>,
+    active_alerts: HashSet<(NetworkAlertKind, Option<u32>)>,
+    previous_battery_levels: HashMap<u32, u32>,
+    previous_components: GraphComponents,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scores every known node and the network as a whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `devices` - The connected devices, which hold node telemetry.
+    /// * `graph` - The mesh graph, which holds the links between nodes.
+    ///
+    /// # Returns
+    ///
+    /// * `(NetworkHealth, Vec<NetworkAlert>)` - The current health of the
+    ///   network, and any alerts for conditions that started since the last check.
+    pub fn evaluate<'a>(
+        &mut self,
+        devices: impl Iterator<Item = &'a MeshDevice>,
+        graph: &MeshGraph,
+    ) -> (NetworkHealth, Vec<NetworkAlert>) {
+        let now = get_current_time_u32();
+        let readings = collect_node_readings(devices);
+
+        let mut alerts = vec![];
+        let mut conditions = HashSet::new();
+        let mut nodes = vec![];
+
+        let mut node_nums: Vec<&u32> = readings.keys().collect();
+        node_nums.sort();
+
+        for node_num in node_nums {
+            let node_readings = &readings[node_num];
+            let node_health = self.evaluate_node(*node_num, node_readings, graph, now);
+
+            if node_health.silent {
+                conditions.insert((NetworkAlertKind::NodeSilent, Some(*node_num)));
+            }
+
+            if let Some(battery_level) = node_health
+                .battery_level
+                .filter(|b| BATTERY_LEVEL_RANGE.contains(b))
+            {
+                let previous_level = self
+                    .previous_battery_levels
+                    .insert(*node_num, battery_level);
+
+                let collapsed = previous_level.map_or(false, |previous| {
+                    previous.saturating_sub(battery_level) >= self.thresholds.battery_drop_level
+                }) || battery_level < self.thresholds.low_battery_level;
+
+                if collapsed {
+                    conditions.insert((NetworkAlertKind::BatteryCollapse, Some(*node_num)));
+                }
+            }
+
+            if node_health
+                .channel_utilization
+                .map_or(false, |u| u > self.thresholds.channel_utilization_limit)
+            {
+                conditions.insert((NetworkAlertKind::ChannelUtilizationSpike, Some(*node_num)));
+            }
+
+            if node_health
+                .air_util_tx
+                .map_or(false, |u| u > self.thresholds.air_util_tx_limit)
+            {
+                conditions.insert((NetworkAlertKind::AirUtilTxSpike, Some(*node_num)));
+            }
+
+            nodes.push(node_health);
+        }
+
+        let partition_count = graph.count_partitions();
+
+        // Nodes dropping off on their own aren't partitions, so only splits
+        // that leave more than one group of linked nodes raise an alert
+        for change in detect_component_changes(&self.previous_components, &graph.components) {
+            if change.kind != ComponentChangeKind::Split {
+                continue;
+            }
+
+            let linked_group_count = change
+                .node_groups
+                .iter()
+                .filter(|group| group.len() > 1)
+                .count();
+
+            if linked_group_count > 1 {
+                conditions.insert((NetworkAlertKind::PartitionFormed, None));
+            }
+        }
+
+        self.previous_components = graph.components.clone();
+
+        for condition in conditions.iter() {
+            if self.active_alerts.contains(condition) {
+                trace!("Alert {:?} is still active", condition);
+                continue;
+            }
+
+            let node_health = condition
+                .1
+                .and_then(|node_num| nodes.iter().find(|n| n.node_num == node_num));
+
+            alerts.push(NetworkAlert {
+                kind: condition.0,
+                node_num: condition.1,
+                message: build_alert_message(condition.0, node_health),
+                timestamp: now,
+            });
+        }
+
+        self.active_alerts = conditions;
+
+        let node_count = nodes.len() as u32;
+        let silent_node_count = nodes.iter().filter(|n| n.silent).count() as u32;
+
+        let average_score = if nodes.is_empty() {
+            MAX_HEALTH_SCORE
+        } else {
+            nodes.iter().map(|n| n.score).sum::<f32>() / nodes.len() as f32
+        };
+
+        let extra_partitions = partition_count.saturating_sub(1) as f32;
+
+        let health = NetworkHealth {
+            timestamp: now,
+            score: (average_score - extra_partitions * PARTITION_PENALTY).max(0.0),
+            node_count,
+            silent_node_count,
+            partition_count,
+            nodes,
+        };
+
+        debug!(
+            "Network health score {:.1} across {} nodes, {} new alerts",
+            health.score,
+            health.node_count,
+            alerts.len()
+        );
+
+        self.latest_health = Some(health.clone());
+
+        (health, alerts)
+    }
+
+    fn evaluate_node(
+        &self,
+        node_num: u32,
+        readings: &NodeReadings,
+        graph: &MeshGraph,
+        now: u32,
+    ) -> NodeHealth {
+        let seconds_since_heard = readings
+            .last_heard
+            .map(|last_heard| now.saturating_sub(last_heard));
+
+        let silent =
+            seconds_since_heard.map_or(false, |secs| secs > self.thresholds.silent_node_secs);
+
+        let neighbor_count = graph.count_neighbors(node_num);
+
+        let score = if silent {
+            0.0
+        } else {
+            let mut score = MAX_HEALTH_SCORE;
+
+            if readings.battery_level.map_or(false, |b| {
+                BATTERY_LEVEL_RANGE.contains(&b) && b < self.thresholds.low_battery_level
+            }) {
+                score -= LOW_BATTERY_PENALTY;
+            }
+
+            if readings
+                .channel_utilization
+                .map_or(false, |u| u > self.thresholds.channel_utilization_limit)
+                || readings
+                    .air_util_tx
+                    .map_or(false, |u| u > self.thresholds.air_util_tx_limit)
+            {
+                score -= BUSY_CHANNEL_PENALTY;
+            }
+
+            if graph.contains_node(node_num) && neighbor_count == 0 {
+                score -= ISOLATED_NODE_PENALTY;
+            }
+
+            score.max(0.0)
+        };
+
+        NodeHealth {
+            node_num,
+            score,
+            silent,
+            seconds_since_heard,
+            battery_level: readings.battery_level,
+            channel_utilization: readings.channel_utilization,
+            air_util_tx: readings.air_util_tx,
+            neighbor_count,
+        }
+    }
+}
+
+fn build_alert_message(kind: NetworkAlertKind, node_health: Option<&NodeHealth>) -> String {
+    let node_num = node_health.map_or(0, |n| n.node_num);
+
+    match kind {
+        NetworkAlertKind::NodeSilent => format!(
+            "Node {} has not been heard for {} minutes",
+            node_num,
+            node_health.and_then(|n| n.seconds_since_heard).unwrap_or(0) / 60
+        ),
+        NetworkAlertKind::BatteryCollapse => format!(
+            "Battery of node {} dropped to {}%",
+            node_num,
+            node_health.and_then(|n| n.battery_level).unwrap_or(0)
+        ),
+        NetworkAlertKind::ChannelUtilizationSpike => format!(
+            "Channel utilization at node {} reached {:.1}%",
+            node_num,
+            node_health
+                .and_then(|n| n.channel_utilization)
+                .unwrap_or(0.0)
+        ),
+        NetworkAlertKind::AirUtilTxSpike => format!(
+            "Transmit airtime of node {} reached {:.1}%",
+            node_num,
+            node_health.and_then(|n| n.air_util_tx).unwrap_or(0.0)
+        ),
+        NetworkAlertKind::PartitionFormed => "Mesh split into partitions".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{LastHeardMetadata, MeshNode, MeshNodeDeviceMetrics};
+    use meshtastic::protobufs;
+
+    fn build_device(node_num: u32, battery_level: u32, channel_utilization: f32) -> MeshDevice {
+        let mut device = MeshDevice::new();
+        let mut node = MeshNode::new(node_num);
+
+        node.last_heard = Some(LastHeardMetadata {
+            timestamp: get_current_time_u32(),
+            snr: 0.0,
+            channel: 0,
+        });
+
+        node.device_metrics.push(MeshNodeDeviceMetrics {
+            metrics: protobufs::DeviceMetrics {
+                battery_level,
+                channel_utilization,
+                ..Default::default()
+            },
+            timestamp: get_current_time_u32(),
+            snr: 0.0,
+        });
+
+        device.nodes.insert(node_num, node);
+        device
+    }
+
+    #[test]
+    fn alerts_only_when_condition_starts() {
+        let mut monitor = HealthMonitor::new();
+        let graph = MeshGraph::new();
+
+        let (health, alerts) = monitor.evaluate([build_device(1, 80, 10.0)].iter(), &graph);
+        assert!(alerts.is_empty());
+        assert_eq!(health.score, MAX_HEALTH_SCORE);
+
+        let (_, alerts) = monitor.evaluate([build_device(1, 50, 60.0)].iter(), &graph);
+        let mut kinds: Vec<NetworkAlertKind> = alerts.iter().map(|a| a.kind).collect();
+        kinds.sort_by_key(|k| *k as u8);
+
+        assert_eq!(
+            kinds,
+            vec![
+                NetworkAlertKind::BatteryCollapse,
+                NetworkAlertKind::ChannelUtilizationSpike
+            ]
+        );
+
+        let (_, alerts) = monitor.evaluate([build_device(1, 50, 60.0)].iter(), &graph);
+        assert!(alerts.is_empty());
+    }
+
+    #[test]
+    fn alerts_when_linked_groups_split() {
+        let mut monitor = HealthMonitor::new();
+        let mut graph = crate::graph::ds::test_utils::build_graph(
+            &[1, 2, 3, 4, 5],
+            &[(1, 2, 0.0), (2, 3, 0.0), (3, 4, 0.0)],
+        );
+
+        // Node 5 has no links, so it isn't a partition of its own
+        let (health, alerts) = monitor.evaluate(std::iter::empty(), &graph);
+        assert_eq!(health.partition_count, 1);
+        assert_eq!(health.score, MAX_HEALTH_SCORE);
+        assert!(alerts.is_empty());
+
+        graph.remove_edge(graph.get_node(2).unwrap(), graph.get_node(3).unwrap());
+
+        let (health, alerts) = monitor.evaluate(std::iter::empty(), &graph);
+        assert_eq!(health.partition_count, 2);
+        assert_eq!(health.score, MAX_HEALTH_SCORE - PARTITION_PENALTY);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, NetworkAlertKind::PartitionFormed);
+
+        // Splitting off a single node leaves one linked group
+        graph.remove_edge(graph.get_node(3).unwrap(), graph.get_node(4).unwrap());
+
+        let (health, alerts) = monitor.evaluate(std::iter::empty(), &graph);
+        assert_eq!(health.partition_count, 1);
+        assert!(alerts.is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::canned_messages::{self, CannedMessageSet};
+use crate::ipc::events;
+use crate::ipc::CommandError;
+use crate::packet_api::MeshPacketApi;
+use crate::state::{self, DeviceKey};
+
+use log::{debug, trace};
+use meshtastic::api::ConnectedStreamApi;
+use meshtastic::packet::PacketDestination;
+use meshtastic::protobufs;
+use meshtastic::types::{EncodedMeshPacketData, MeshChannel, NodeId};
+use meshtastic::Message;
+
+/// Sends an admin message to the connected node, or to a remote node over
+/// the channel named "admin", which firmware requires for remote admin.
+/// Echoed messages are also passed to the packet handlers, so they can be
+/// matched with their acknowledgement.
+async fn send_admin_message(
+    packet_api: &mut MeshPacketApi,
+    connection: &mut ConnectedStreamApi,
+    node_num: Option<u32>,
+    payload_variant: protobufs::admin_message::PayloadVariant,
+    want_response: bool,
+    echo_response: bool,
+) -> Result<(), String> {
+    let my_node_num = packet_api.device.my_node_info.my_node_num;
+
+    let (destination, channel) = match node_num.filter(|n| *n != my_node_num) {
+        Some(node_num) => {
+            let admin_channel = packet_api
+                .device
+                .channels
+                .iter()
+                .find(|(_, channel)| {
+                    channel
+                        .config
+                        .settings
+                        .as_ref()
+                        .map_or(false, |s| s.name.eq_ignore_ascii_case("admin"))
+                })
+                .map(|(index, _)| *index)
+                .ok_or("Remote admin requires a channel named \"admin\"")?;
+
+            (
+                PacketDestination::Node(NodeId::new(node_num)),
+                admin_channel,
+            )
+        }
+        None => (PacketDestination::Local, 0),
+    };
+
+    let admin_message = protobufs::AdminMessage {
+        payload_variant: Some(payload_variant),
+    };
+
+    connection
+        .send_mesh_packet(
+            packet_api,
+            EncodedMeshPacketData::new(admin_message.encode_to_vec()),
+            protobufs::PortNum::AdminApp,
+            destination,
+            MeshChannel::new(channel).map_err(|e| e.to_string())?,
+            true,
+            want_response,
+            echo_response,
+            None,
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Requests the canned message list of a node. The list is stored on the
+/// device once the node responds.
+#[tauri::command]
+pub async fn request_canned_messages(
+    device_key: DeviceKey,
+    node_num: Option<u32>,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<(), CommandError> {
+    debug!("Called request_canned_messages command");
+    trace!("Called on node {:?}", node_num);
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut connections_guard = radio_connections.inner.lock().await;
+    let connection = connections_guard
+        .get_mut(&device_key)
+        .ok_or("Radio connection not initialized")?;
+
+    send_admin_message(
+        packet_api,
+        connection,
+        node_num,
+        protobufs::admin_message::PayloadVariant::GetCannedMessageModuleMessagesRequest(true),
+        true,
+        false,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Writes a canned message list to a node. The list is stored on the device
+/// once the node acknowledges the write, or responds to a request for it.
+async fn write_canned_messages(
+    packet_api: &mut MeshPacketApi,
+    connection: &mut ConnectedStreamApi,
+    node_num: Option<u32>,
+    messages: Vec<String>,
+) -> Result<(), String> {
+    canned_messages::validate_canned_messages(&messages)?;
+
+    send_admin_message(
+        packet_api,
+        connection,
+        node_num,
+        protobufs::admin_message::PayloadVariant::SetCannedMessageModuleMessages(
+            canned_messages::join_canned_messages(&messages),
+        ),
+        false,
+        true,
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_canned_messages(
+    device_key: DeviceKey,
+    node_num: Option<u32>,
+    messages: Vec<String>,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<(), CommandError> {
+    debug!("Called set_canned_messages command");
+    trace!("Called on node {:?} with messages {:?}", node_num, messages);
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut connections_guard = radio_connections.inner.lock().await;
+    let connection = connections_guard
+        .get_mut(&device_key)
+        .ok_or("Radio connection not initialized")?;
+
+    write_canned_messages(packet_api, connection, node_num, messages).await?;
+
+    events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_canned_message_sets(
+    canned_message_sets: tauri::State<'_, state::canned_messages::CannedMessagesState>,
+) -> Result<Vec<CannedMessageSet>, CommandError> {
+    debug!("Called get_canned_message_sets command");
+
+    let sets_guard = canned_message_sets
+        .inner
+        .lock()
+        .map_err(|e| e.to_string())?;
+
+    Ok(sets_guard.get_sets())
+}
+
+#[tauri::command]
+pub async fn save_canned_message_set(
+    set: CannedMessageSet,
+    canned_message_sets: tauri::State<'_, state::canned_messages::CannedMessagesState>,
+) -> Result<CannedMessageSet, CommandError> {
+    debug!("Called save_canned_message_set command");
+    trace!("Called with set {:?}", set);
+
+    let mut sets_guard = canned_message_sets
+        .inner
+        .lock()
+        .map_err(|e| e.to_string())?;
+
+    let set = sets_guard.upsert_set(set)?;
+
+    Ok(set)
+}
+
+#[tauri::command]
+pub async fn delete_canned_message_set(
+    set_id: u32,
+    canned_message_sets: tauri::State<'_, state::canned_messages::CannedMessagesState>,
+) -> Result<(), CommandError> {
+    debug!("Called delete_canned_message_set command");
+    trace!("Called with set {}", set_id);
+
+    let mut sets_guard = canned_message_sets
+        .inner
+        .lock()
+        .map_err(|e| e.to_string())?;
+
+    sets_guard
+        .remove_set(set_id)
+        .ok_or("Canned message set not found")?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn apply_canned_message_set(
+    device_key: DeviceKey,
+    node_num: Option<u32>,
+    set_id: u32,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+    canned_message_sets: tauri::State<'_, state::canned_messages::CannedMessagesState>,
+) -> Result<(), CommandError> {
+    debug!("Called apply_canned_message_set command");
+    trace!("Called on node {:?} with set {}", node_num, set_id);
+
+    let messages = {
+        let sets_guard = canned_message_sets
+            .inner
+            .lock()
+            .map_err(|e| e.to_string())?;
+
+        sets_guard
+            .get_set(set_id)
+            .ok_or("Canned message set not found")?
+            .messages
+            .clone()
+    };
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut connections_guard = radio_connections.inner.lock().await;
+    let connection = connections_guard
+        .get_mut(&device_key)
+        .ok_or("Radio connection not initialized")?;
+
+    write_canned_messages(packet_api, connection, node_num, messages).await?;
+
+    events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -1,14 +1,26 @@
 use crate::device;
+use crate::device::helpers::get_current_time_u32;
+use crate::device::MeshChannel;
 use crate::device::SerialDeviceStatus;
+use crate::device::DEFAULT_CHANNEL_PSK;
+use crate::ipc::events;
 use crate::ipc::helpers::spawn_configuration_timeout_handler;
 use crate::ipc::helpers::spawn_decoded_handler;
+use crate::ipc::helpers::spawn_mqtt_packet_handler;
+use crate::ipc::helpers::spawn_mqtt_proxy_downlink_handler;
+use crate::ipc::helpers::spawn_mqtt_proxy_uplink_handler;
+use crate::ipc::helpers::spawn_waypoint_expiry_handler;
 use crate::ipc::CommandError;
+use crate::ipc::ConfigurationStatus;
+use crate::mqtt;
+use crate::mqtt::MqttBrokerConfig;
 use crate::packet_api::MeshPacketApi;
 use crate::state;
 use crate::state::DeviceKey;
 
 use log::debug;
 use meshtastic::api::{StreamApi, StreamHandle};
+use meshtastic::protobufs;
 use meshtastic::utils::stream::build_serial_stream;
 use meshtastic::utils::stream::build_tcp_stream;
 use std::time::Duration;
@@ -54,6 +66,7 @@ async fn create_new_connection<S>(
     mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
     radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
     mesh_graph: tauri::State<'_, state::graph::GraphState>,
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
 ) -> Result<(), CommandError>
 where
     S: AsyncReadExt + AsyncWriteExt + Send + 'static,
@@ -66,6 +79,7 @@ where
         device_key.clone(),
         device,
         mesh_graph.inner.clone(),
+        geofences.inner.clone(),
     );
 
     let stream_api = StreamApi::new();
@@ -116,7 +130,15 @@ where
 
     // Spawn decoded packet handler to route decoded packets
 
-    spawn_decoded_handler(decoded_listener, mesh_devices_arc, device_key);
+    spawn_decoded_handler(
+        decoded_listener,
+        mesh_devices_arc.clone(),
+        device_key.clone(),
+    );
+
+    // Spawn expiry handler to drop waypoints once they expire
+
+    spawn_waypoint_expiry_handler(handle, mesh_devices_arc, device_key);
 
     Ok(())
 }
@@ -131,6 +153,7 @@ pub async fn connect_to_serial_port(
     mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
     radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
     mesh_graph: tauri::State<'_, state::graph::GraphState>,
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
 ) -> Result<(), CommandError> {
     debug!(
         "Called connect_to_serial_port command with port \"{}\"",
@@ -152,6 +175,7 @@ pub async fn connect_to_serial_port(
         mesh_devices,
         radio_connections,
         mesh_graph,
+        geofences,
     )
     .await?;
 
@@ -165,6 +189,7 @@ pub async fn connect_to_tcp_port(
     mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
     radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
     mesh_graph: tauri::State<'_, state::graph::GraphState>,
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
 ) -> Result<(), CommandError> {
     debug!(
         "Called connect_to_tcp_port command with address \"{}\"",
@@ -187,23 +212,250 @@ pub async fn connect_to_tcp_port(
         mesh_devices,
         radio_connections,
         mesh_graph,
+        geofences,
     )
     .await?;
 
     Ok(())
 }
 
+#[tauri::command]
+pub async fn connect_to_mqtt_broker(
+    config: MqttBrokerConfig,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    mqtt_connections: tauri::State<'_, state::mqtt_connections::MqttConnectionsState>,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
+) -> Result<(), CommandError> {
+    let device_key = config.get_device_key();
+
+    debug!(
+        "Called connect_to_mqtt_broker command with broker \"{}\"",
+        device_key
+    );
+
+    if mqtt_connections
+        .inner
+        .lock()
+        .await
+        .contains_key(&device_key)
+        || mesh_devices.inner.lock().await.contains_key(&device_key)
+    {
+        return Err("MQTT broker already connected".into());
+    }
+
+    // Brokers don't send a configuration, so seed the device with the
+    // default primary channel to decrypt default-key traffic
+
+    let mut device = device::MeshDevice::new();
+
+    device.add_channel(MeshChannel {
+        config: protobufs::Channel {
+            index: 0,
+            role: protobufs::channel::Role::Primary as i32,
+            settings: Some(protobufs::ChannelSettings {
+                psk: DEFAULT_CHANNEL_PSK.to_vec(),
+                ..Default::default()
+            }),
+        },
+        last_interaction: get_current_time_u32(),
+        messages: vec![],
+    });
+
+    let mut packet_api = MeshPacketApi::new(
+        app_handle.app_handle(),
+        device_key.clone(),
+        device,
+        mesh_graph.inner.clone(),
+        geofences.inner.clone(),
+    );
+
+    // Connect to broker and subscribe to gateway topics
+
+    packet_api.device.set_status(SerialDeviceStatus::Connecting);
+
+    let (connection, message_listener) =
+        mqtt::connect_to_broker(&config, vec![config.get_root_topic()]).await?;
+
+    packet_api.device.set_status(SerialDeviceStatus::Connected);
+
+    // Persist device struct and connection in Tauri state, unless the same
+    // broker was connected while this connection was being established
+
+    let mesh_devices_arc = mesh_devices.inner.clone();
+
+    {
+        let mut devices_guard = mesh_devices_arc.lock().await;
+        let mut mqtt_connections_guard = mqtt_connections.inner.lock().await;
+
+        if mqtt_connections_guard.contains_key(&device_key)
+            || devices_guard.contains_key(&device_key)
+        {
+            drop(mqtt_connections_guard);
+            drop(devices_guard);
+            connection.disconnect().await?;
+            return Err("MQTT broker already connected".into());
+        }
+
+        events::dispatch_updated_device(&app_handle, &packet_api.device)
+            .map_err(|e| e.to_string())?;
+
+        devices_guard.insert(device_key.clone(), packet_api);
+        mqtt_connections_guard.insert(device_key.clone(), connection);
+    }
+
+    // Spawn packet handler to route packets received from the broker
+
+    spawn_mqtt_packet_handler(
+        message_listener,
+        mesh_devices_arc.clone(),
+        device_key.clone(),
+    );
+
+    // Spawn expiry handler to drop waypoints once they expire
+
+    spawn_waypoint_expiry_handler(app_handle.clone(), mesh_devices_arc, device_key.clone());
+
+    events::dispatch_configuration_status(
+        &app_handle,
+        ConfigurationStatus {
+            device_key,
+            successful: true,
+            message: None,
+        },
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn enable_mqtt_client_proxy(
+    device_key: DeviceKey,
+    config: MqttBrokerConfig,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+    mqtt_proxies: tauri::State<'_, state::mqtt_proxies::MqttProxiesState>,
+) -> Result<(), CommandError> {
+    debug!(
+        "Called enable_mqtt_client_proxy command with broker \"{}\"",
+        config.get_device_key()
+    );
+
+    // Subscribe to the topics the radio would subscribe to with its own connection
+
+    let topics = {
+        let devices_guard = mesh_devices.inner.lock().await;
+        let packet_api = devices_guard
+            .get(&device_key)
+            .ok_or("Device not connected")?;
+
+        let proxy_to_client_enabled = packet_api
+            .device
+            .module_config
+            .mqtt
+            .as_ref()
+            .map(|c| c.proxy_to_client_enabled)
+            .unwrap_or(false);
+
+        if !proxy_to_client_enabled {
+            return Err("Device does not have MQTT client proxy enabled".into());
+        }
+
+        mqtt::get_proxy_downlink_topics(&packet_api.device)
+    };
+
+    if mqtt_proxies.inner.lock().await.contains_key(&device_key) {
+        return Err("MQTT client proxy already enabled".into());
+    }
+
+    // Don't hold the state locks while waiting on the broker
+
+    let (connection, message_listener) = mqtt::connect_to_broker(&config, topics).await?;
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let mut mqtt_proxies_guard = mqtt_proxies.inner.lock().await;
+
+    // The device may have disconnected or another proxy may have been enabled
+    // while this connection was being established
+
+    let error = if !devices_guard.contains_key(&device_key) {
+        Some("Device not connected")
+    } else if mqtt_proxies_guard.contains_key(&device_key) {
+        Some("MQTT client proxy already enabled")
+    } else {
+        None
+    };
+
+    if let Some(error) = error {
+        drop(mqtt_proxies_guard);
+        drop(devices_guard);
+        connection.disconnect().await?;
+        return Err(error.into());
+    }
+
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let (uplink_sender, uplink_listener) = tokio::sync::mpsc::unbounded_channel();
+    packet_api.mqtt_proxy_sender = Some(uplink_sender);
+
+    spawn_mqtt_proxy_uplink_handler(connection.client.clone(), uplink_listener);
+    spawn_mqtt_proxy_downlink_handler(
+        message_listener,
+        radio_connections.inner.clone(),
+        device_key.clone(),
+    );
+
+    mqtt_proxies_guard.insert(device_key, connection);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn disable_mqtt_client_proxy(
+    device_key: DeviceKey,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    mqtt_proxies: tauri::State<'_, state::mqtt_proxies::MqttProxiesState>,
+) -> Result<(), CommandError> {
+    debug!("Called disable_mqtt_client_proxy command");
+
+    {
+        let mut devices_guard = mesh_devices.inner.lock().await;
+        let packet_api = devices_guard
+            .get_mut(&device_key)
+            .ok_or("Device not connected")?;
+
+        // Dropping the sender stops the uplink handler
+        packet_api.mqtt_proxy_sender = None;
+    }
+
+    let connection = mqtt_proxies.inner.lock().await.remove(&device_key);
+
+    if let Some(connection) = connection {
+        connection.disconnect().await?;
+    }
+
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn drop_device_connection(
     device_key: DeviceKey,
     mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
     radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+    mqtt_connections: tauri::State<'_, state::mqtt_connections::MqttConnectionsState>,
+    mqtt_proxies: tauri::State<'_, state::mqtt_proxies::MqttProxiesState>,
 ) -> Result<(), CommandError> {
     debug!("Called drop_device_connection command");
 
     {
         let mut state_devices = mesh_devices.inner.lock().await;
         let mut connections_guard = radio_connections.inner.lock().await;
+        let mut mqtt_connections_guard = mqtt_connections.inner.lock().await;
+        let mut mqtt_proxies_guard = mqtt_proxies.inner.lock().await;
 
         // Disconnect from open connection
         // TODO abstract this clearing into a helper function
@@ -217,6 +469,24 @@ pub async fn drop_device_connection(
             };
         }
 
+        if let Some(mqtt_connection) = mqtt_connections_guard.remove(&device_key) {
+            match mqtt_connection.disconnect().await {
+                Ok(_) => (),
+                Err(e) => {
+                    debug!("Failed to disconnect from MQTT broker: {:?}", e);
+                }
+            };
+        }
+
+        if let Some(mqtt_proxy) = mqtt_proxies_guard.remove(&device_key) {
+            match mqtt_proxy.disconnect().await {
+                Ok(_) => (),
+                Err(e) => {
+                    debug!("Failed to disconnect MQTT client proxy: {:?}", e);
+                }
+            };
+        }
+
         // Clear corresponding state device
 
         if let Some(packet_api) = state_devices.get_mut(&device_key) {
@@ -235,6 +505,8 @@ pub async fn drop_device_connection(
 pub async fn drop_all_device_connections(
     mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
     radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+    mqtt_connections: tauri::State<'_, state::mqtt_connections::MqttConnectionsState>,
+    mqtt_proxies: tauri::State<'_, state::mqtt_proxies::MqttProxiesState>,
 ) -> Result<(), CommandError> {
     debug!("Called drop_all_device_connections command");
 
@@ -247,6 +519,21 @@ pub async fn drop_all_device_connections(
             connection.disconnect().await.map_err(|e| e.to_string())?;
         }
 
+        // Release the MQTT locks before locking devices, which commands that
+        // connect to a broker lock first
+
+        let broker_connections = std::mem::take(&mut *mqtt_connections.inner.lock().await);
+
+        for (_, mqtt_connection) in broker_connections {
+            mqtt_connection.disconnect().await?;
+        }
+
+        let proxy_connections = std::mem::take(&mut *mqtt_proxies.inner.lock().await);
+
+        for (_, mqtt_proxy) in proxy_connections {
+            mqtt_proxy.disconnect().await?;
+        }
+
         // Set all state devices as disconnected and empty HashMap
 
         let mut state_devices = mesh_devices.inner.lock().await;
@@ -0,0 +1,60 @@
+use crate::geofence::{Geofence, GeofenceEvent, GeofenceHistoryFilter};
+use crate::ipc::CommandError;
+use crate::state;
+
+use log::{debug, trace};
+
+#[tauri::command]
+pub async fn add_geofence(
+    geofence: Geofence,
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
+) -> Result<Geofence, CommandError> {
+    debug!("Called add_geofence command");
+    trace!("Called with geofence {:?}", geofence);
+
+    let mut geofences_guard = geofences.inner.lock().map_err(|e| e.to_string())?;
+    let geofence = geofences_guard.upsert_geofence(geofence)?;
+
+    Ok(geofence)
+}
+
+#[tauri::command]
+pub async fn remove_geofence(
+    geofence_id: u32,
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
+) -> Result<(), CommandError> {
+    debug!("Called remove_geofence command");
+    trace!("Called with geofence {}", geofence_id);
+
+    let mut geofences_guard = geofences.inner.lock().map_err(|e| e.to_string())?;
+
+    geofences_guard
+        .remove_geofence(geofence_id)
+        .ok_or("Geofence not found")?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_geofences(
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
+) -> Result<Vec<Geofence>, CommandError> {
+    debug!("Called get_geofences command");
+
+    let geofences_guard = geofences.inner.lock().map_err(|e| e.to_string())?;
+
+    Ok(geofences_guard.get_geofences())
+}
+
+#[tauri::command]
+pub async fn get_geofence_history(
+    filter: Option<GeofenceHistoryFilter>,
+    geofences: tauri::State<'_, state::geofences::GeofencesState>,
+) -> Result<Vec<GeofenceEvent>, CommandError> {
+    debug!("Called get_geofence_history command");
+    trace!("Called with filter {:?}", filter);
+
+    let geofences_guard = geofences.inner.lock().map_err(|e| e.to_string())?;
+
+    Ok(geofences_guard.get_history(&filter.unwrap_or_default()))
+}
@@ -1,23 +1,45 @@
-use std::time::Duration;
-
-use log::{debug, error, info};
+use chrono::NaiveDateTime;
+use log::{debug, error, info, trace};
 
 use crate::{
-    graph::ds::graph::MeshGraph,
-    ipc::{events::dispatch_updated_graph, CommandError},
-    state,
+    geo::{GeoBounds, GeoPoint},
+    graph::{
+        api::{
+            coverage::{coverage_to_geojson, coverage_to_raster, CoverageConfig, CoverageRaster},
+            export::{collect_node_metadata, GraphExportFormat},
+            relay::{RelayPlacementConfig, RelayPlacementResult},
+            route::{RouteMetric, RouteResult},
+        },
+        ds::{
+            components::GraphComponents, graph::MeshGraph, history::GraphDiff,
+            timeout::GraphTimeoutConfig,
+        },
+    },
+    ipc::{
+        events::{
+            dispatch_graph_component_changes, dispatch_graph_stale_items, dispatch_updated_graph,
+        },
+        CommandError,
+    },
+    state::{self, DeviceKey},
 };
 
-pub const DEFAULT_GRAPH_CLEAN_SECONDS: u64 = 60;
+const DEFAULT_MAX_ROUTES: u32 = 3;
 
 #[tauri::command]
 pub async fn get_graph_state(
+    observer: Option<DeviceKey>,
     mesh_graph: tauri::State<'_, state::graph::GraphState>,
 ) -> Result<MeshGraph, CommandError> {
     debug!("Called get_graph_state command");
 
     let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
-    let mesh_graph = mesh_graph_handle.clone();
+
+    // Without an observer, return the graph merged across all radios
+    let mesh_graph = match observer {
+        Some(device_key) => mesh_graph_handle.filter_by_observer(&device_key),
+        None => mesh_graph_handle.clone(),
+    };
 
     Ok(mesh_graph)
 }
@@ -38,16 +60,19 @@ pub async fn initialize_timeout_handler(
         return Ok(());
     }
 
+    let initial_clean_interval = mesh_graph_handle.timeout_config.get_clean_interval();
+
     let handle = tauri::async_runtime::spawn(async move {
         info!(
-            "Starting graph timeout handler, sleeping for {:?} seconds",
-            DEFAULT_GRAPH_CLEAN_SECONDS
+            "Starting graph timeout handler, sleeping for {:?}",
+            initial_clean_interval
         );
 
         let app_handle = app_handle;
+        let mut clean_interval = initial_clean_interval;
 
         loop {
-            tokio::time::sleep(Duration::from_secs(DEFAULT_GRAPH_CLEAN_SECONDS)).await;
+            tokio::time::sleep(clean_interval).await;
 
             debug!("Cleaning graph...");
 
@@ -60,16 +85,27 @@ pub async fn initialize_timeout_handler(
                     }
                 };
 
-                mesh_graph_handle.clean();
+                let clean_result = mesh_graph_handle.clean();
+
+                if clean_result.has_stale_items() {
+                    dispatch_graph_stale_items(&app_handle, clean_result)
+                        .expect("Error dispatching stale graph items event");
+                }
+
+                dispatch_graph_component_changes(
+                    &app_handle,
+                    mesh_graph_handle.take_component_changes(),
+                )
+                .expect("Error dispatching graph component changes");
 
                 dispatch_updated_graph(&app_handle, mesh_graph_handle.clone())
                     .expect("Error dispatching updated graph event");
+
+                // Pick up any changes to the clean interval since the last clean
+                clean_interval = mesh_graph_handle.timeout_config.get_clean_interval();
             }
 
-            debug!(
-                "Graph cleaned, sleeping for {:?} seconds",
-                DEFAULT_GRAPH_CLEAN_SECONDS
-            );
+            debug!("Graph cleaned, sleeping for {:?}", clean_interval);
         }
 
         error!("Graph timeout handler stopped");
@@ -96,3 +132,218 @@ pub async fn stop_timeout_handler(
 
     Ok(())
 }
+
+#[tauri::command]
+pub async fn get_graph_timeout_config(
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<GraphTimeoutConfig, CommandError> {
+    debug!("Called get_graph_timeout_config command");
+
+    let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+
+    Ok(mesh_graph_handle.timeout_config.clone())
+}
+
+#[tauri::command]
+pub async fn update_graph_timeout_config(
+    config: GraphTimeoutConfig,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<(), CommandError> {
+    debug!("Called update_graph_timeout_config command");
+    trace!("Called with config {:?}", config);
+
+    if config.clean_interval_secs == 0 {
+        return Err("Clean interval must be at least one second".into());
+    }
+
+    let mut mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+
+    // A running timeout handler uses the new interval after its current sleep
+    mesh_graph_handle.timeout_config = config;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn export_graph(
+    format: GraphExportFormat,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<String, CommandError> {
+    debug!("Called export_graph command");
+    trace!("Called with format {:?}", format);
+
+    let metadata = {
+        let devices_guard = mesh_devices.inner.lock().await;
+        collect_node_metadata(devices_guard.values().map(|packet_api| &packet_api.device))
+    };
+
+    let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+
+    Ok(mesh_graph_handle.export(format, &metadata))
+}
+
+#[tauri::command]
+pub async fn get_graph_at(
+    timestamp: NaiveDateTime,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<MeshGraph, CommandError> {
+    debug!("Called get_graph_at command");
+    trace!("Called with timestamp {}", timestamp);
+
+    let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+
+    Ok(mesh_graph_handle.history.get_graph_at(timestamp))
+}
+
+#[tauri::command]
+pub async fn diff_graphs(
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<GraphDiff, CommandError> {
+    debug!("Called diff_graphs command");
+    trace!("Called with start {} and end {}", start, end);
+
+    if start > end {
+        return Err("Start of range must be before its end".into());
+    }
+
+    let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+
+    Ok(mesh_graph_handle.history.diff(start, end))
+}
+
+#[tauri::command]
+pub async fn compute_route(
+    from: u32,
+    to: u32,
+    metric: RouteMetric,
+    max_routes: Option<u32>,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<RouteResult, CommandError> {
+    debug!("Called compute_route command");
+    trace!(
+        "Called with from {}, to {}, metric {:?}, max routes {:?}",
+        from,
+        to,
+        metric,
+        max_routes
+    );
+
+    let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+
+    let result = mesh_graph_handle.compute_route(
+        from,
+        to,
+        metric,
+        max_routes.unwrap_or(DEFAULT_MAX_ROUTES),
+    )?;
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_components(
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<GraphComponents, CommandError> {
+    debug!("Called get_components command");
+
+    let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+
+    Ok(mesh_graph_handle.components.clone())
+}
+
+#[tauri::command]
+pub async fn get_coverage_geojson(
+    config: Option<CoverageConfig>,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<String, CommandError> {
+    debug!("Called get_coverage_geojson command");
+    trace!("Called with config {:?}", config);
+
+    let metadata = {
+        let devices_guard = mesh_devices.inner.lock().await;
+        collect_node_metadata(devices_guard.values().map(|packet_api| &packet_api.device))
+    };
+
+    let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+    let models = mesh_graph_handle.estimate_coverage(&metadata, &config.unwrap_or_default());
+
+    Ok(coverage_to_geojson(&models).to_string())
+}
+
+#[tauri::command]
+pub async fn get_coverage_raster(
+    cell_size_meters: f64,
+    config: Option<CoverageConfig>,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<CoverageRaster, CommandError> {
+    debug!("Called get_coverage_raster command");
+    trace!(
+        "Called with cell size {} and config {:?}",
+        cell_size_meters,
+        config
+    );
+
+    let metadata = {
+        let devices_guard = mesh_devices.inner.lock().await;
+        collect_node_metadata(devices_guard.values().map(|packet_api| &packet_api.device))
+    };
+
+    let models = {
+        let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+        mesh_graph_handle.estimate_coverage(&metadata, &config.unwrap_or_default())
+    };
+
+    let raster = coverage_to_raster(&models, cell_size_meters)?;
+
+    Ok(raster)
+}
+
+#[tauri::command]
+pub async fn suggest_relay_placement(
+    k: u32,
+    bounds: GeoBounds,
+    candidates: Option<Vec<GeoPoint>>,
+    config: Option<RelayPlacementConfig>,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<RelayPlacementResult, CommandError> {
+    debug!("Called suggest_relay_placement command");
+    trace!(
+        "Called with k {}, bounds {:?}, candidates {:?}, config {:?}",
+        k,
+        bounds,
+        candidates,
+        config
+    );
+
+    let metadata = {
+        let devices_guard = mesh_devices.inner.lock().await;
+        collect_node_metadata(devices_guard.values().map(|packet_api| &packet_api.device))
+    };
+
+    // Evaluate candidates on a snapshot so packet handlers aren't blocked
+    let mesh_graph = {
+        let mesh_graph_handle = mesh_graph.inner.lock().map_err(|e| e.to_string())?;
+        mesh_graph_handle.clone()
+    };
+
+    // Evaluating candidates is CPU-bound, so keep it off the async runtime
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        mesh_graph.suggest_relay_placement(
+            k,
+            &bounds,
+            candidates,
+            &metadata,
+            &config.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    Ok(result)
+}
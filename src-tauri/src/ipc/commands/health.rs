@@ -0,0 +1,158 @@
+use log::{debug, error, info, trace, warn};
+use tauri::api::notification::Notification;
+
+use crate::{
+    health::{HealthThresholds, NetworkHealth},
+    ipc::{
+        events::{dispatch_network_alert, dispatch_network_health},
+        CommandError,
+    },
+    state,
+};
+
+#[tauri::command]
+pub async fn initialize_health_monitor(
+    app_handle: tauri::AppHandle,
+    health_monitor_state: tauri::State<'_, state::health::HealthMonitorState>,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    mesh_graph: tauri::State<'_, state::graph::GraphState>,
+) -> Result<(), CommandError> {
+    debug!("Called initialize_health_monitor command");
+
+    let health_monitor_arc = health_monitor_state.inner.clone();
+    let mesh_devices_arc = mesh_devices.inner.clone();
+    let mesh_graph_arc = mesh_graph.inner.clone();
+
+    let mut health_monitor = health_monitor_state.inner.lock().await;
+
+    if health_monitor.monitor_handle.is_some() {
+        info!("Health monitor already initialized");
+        return Ok(());
+    }
+
+    let initial_check_interval = health_monitor.thresholds.get_check_interval();
+
+    let handle = tauri::async_runtime::spawn(async move {
+        info!(
+            "Starting health monitor, checking every {:?}",
+            initial_check_interval
+        );
+
+        let mut check_interval = initial_check_interval;
+
+        loop {
+            tokio::time::sleep(check_interval).await;
+
+            debug!("Checking network health...");
+
+            let devices_guard = mesh_devices_arc.lock().await;
+            let mut health_monitor = health_monitor_arc.lock().await;
+
+            let (health, alerts) = {
+                let mesh_graph = match mesh_graph_arc.lock() {
+                    Ok(handle) => handle,
+                    Err(e) => {
+                        error!("Error getting graph handle: {}", e);
+                        break;
+                    }
+                };
+
+                health_monitor.evaluate(
+                    devices_guard.values().map(|packet_api| &packet_api.device),
+                    &mesh_graph,
+                )
+            };
+
+            drop(devices_guard);
+
+            if let Err(e) = dispatch_network_health(&app_handle, health) {
+                warn!("Failed to dispatch network health: {}", e);
+            }
+
+            for alert in alerts {
+                if health_monitor.thresholds.notifications_enabled {
+                    let notification_result =
+                        Notification::new(app_handle.config().tauri.bundle.identifier.clone())
+                            .title("Network alert")
+                            .body(alert.message.clone())
+                            .notify(&app_handle);
+
+                    if let Err(e) = notification_result {
+                        warn!("Failed to show network alert notification: {}", e);
+                    }
+                }
+
+                if let Err(e) = dispatch_network_alert(&app_handle, alert) {
+                    warn!("Failed to dispatch network alert: {}", e);
+                }
+            }
+
+            // Pick up any changes to the check interval since the last check
+            check_interval = health_monitor.thresholds.get_check_interval();
+        }
+
+        error!("Health monitor stopped");
+    });
+
+    health_monitor.monitor_handle = Some(handle);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_health_monitor(
+    health_monitor_state: tauri::State<'_, state::health::HealthMonitorState>,
+) -> Result<(), CommandError> {
+    debug!("Called stop_health_monitor command");
+
+    let mut health_monitor = health_monitor_state.inner.lock().await;
+
+    if let Some(handle) = health_monitor.monitor_handle.take() {
+        info!("Stopping health monitor");
+        handle.abort();
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_network_health(
+    health_monitor_state: tauri::State<'_, state::health::HealthMonitorState>,
+) -> Result<Option<NetworkHealth>, CommandError> {
+    debug!("Called get_network_health command");
+
+    let health_monitor = health_monitor_state.inner.lock().await;
+
+    Ok(health_monitor.latest_health.clone())
+}
+
+#[tauri::command]
+pub async fn get_health_thresholds(
+    health_monitor_state: tauri::State<'_, state::health::HealthMonitorState>,
+) -> Result<HealthThresholds, CommandError> {
+    debug!("Called get_health_thresholds command");
+
+    let health_monitor = health_monitor_state.inner.lock().await;
+
+    Ok(health_monitor.thresholds.clone())
+}
+
+#[tauri::command]
+pub async fn update_health_thresholds(
+    thresholds: HealthThresholds,
+    health_monitor_state: tauri::State<'_, state::health::HealthMonitorState>,
+) -> Result<(), CommandError> {
+    debug!("Called update_health_thresholds command");
+    trace!("Called with thresholds {:?}", thresholds);
+
+    if thresholds.check_interval_secs == 0 {
+        return Err("Check interval must be at least one second".into());
+    }
+
+    let mut health_monitor = health_monitor_state.inner.lock().await;
+
+    // A running monitor uses the new interval after its current sleep
+    health_monitor.thresholds = thresholds;
+
+    Ok(())
+}
@@ -1,23 +1,108 @@
 use crate::device::NormalizedWaypoint;
 use crate::ipc::events;
 use crate::ipc::CommandError;
+use crate::packet_api::compression;
 use crate::state::{self, DeviceKey};
 
 use log::{debug, trace};
 use meshtastic::packet::PacketDestination;
-use meshtastic::types::MeshChannel;
+use meshtastic::protobufs;
+use meshtastic::types::{EncodedMeshPacketData, MeshChannel};
 
 #[tauri::command]
 pub async fn send_text(
     device_key: DeviceKey,
     text: String,
     channel: u32,
+    compress: Option<bool>,
     app_handle: tauri::AppHandle,
     mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
     radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
 ) -> Result<(), CommandError> {
     debug!("Called send_text command",);
-    trace!("Called with text {} on channel {}", text, channel);
+    trace!(
+        "Called with text {} on channel {} and compression {:?}",
+        text,
+        channel,
+        compress
+    );
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut connections_guard = radio_connections.inner.lock().await;
+    let connection = connections_guard
+        .get_mut(&device_key)
+        .ok_or("Radio connection not initialized")?;
+
+    // Compression is only worth it when it leaves room for more text
+    let compressed_text = compress
+        .unwrap_or(false)
+        .then(|| compression::compress(&text))
+        .filter(|compressed| compressed.len() < text.len());
+
+    match compressed_text {
+        Some(compressed) => {
+            debug!(
+                "Sending text compressed from {} to {} bytes",
+                text.len(),
+                compressed.len()
+            );
+
+            connection
+                .send_mesh_packet(
+                    packet_api,
+                    EncodedMeshPacketData::new(compressed),
+                    protobufs::PortNum::TextMessageCompressedApp,
+                    PacketDestination::Broadcast,
+                    MeshChannel::new(channel).map_err(|e| e.to_string())?,
+                    true,
+                    false,
+                    true,
+                    None,
+                    None,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        None => {
+            connection
+                .send_text(
+                    packet_api,
+                    text.clone(),
+                    PacketDestination::Broadcast,
+                    true,
+                    MeshChannel::new(channel).map_err(|e| e.to_string())?,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn send_reply(
+    device_key: DeviceKey,
+    text: String,
+    channel: u32,
+    reply_id: u32,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<(), CommandError> {
+    debug!("Called send_reply command");
+    trace!(
+        "Called with text {} on channel {} in reply to {}",
+        text,
+        channel,
+        reply_id
+    );
 
     let mut devices_guard = mesh_devices.inner.lock().await;
     let packet_api = devices_guard
@@ -30,12 +115,71 @@ pub async fn send_text(
         .ok_or("Radio connection not initialized")?;
 
     connection
-        .send_text(
+        .send_mesh_packet(
             packet_api,
-            text.clone(),
+            EncodedMeshPacketData::new(text.into_bytes()),
+            protobufs::PortNum::TextMessageApp,
             PacketDestination::Broadcast,
+            MeshChannel::new(channel).map_err(|e| e.to_string())?,
+            true,
+            false,
             true,
+            Some(reply_id),
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn send_reaction(
+    device_key: DeviceKey,
+    emoji: String,
+    channel: u32,
+    reply_id: u32,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<(), CommandError> {
+    debug!("Called send_reaction command");
+    trace!(
+        "Called with emoji {} on channel {} in reply to {}",
+        emoji,
+        channel,
+        reply_id
+    );
+
+    if emoji.is_empty() {
+        return Err("Reaction must not be empty".into());
+    }
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut connections_guard = radio_connections.inner.lock().await;
+    let connection = connections_guard
+        .get_mut(&device_key)
+        .ok_or("Radio connection not initialized")?;
+
+    // The emoji flag marks the payload as a reaction rather than a reply
+    connection
+        .send_mesh_packet(
+            packet_api,
+            EncodedMeshPacketData::new(emoji.into_bytes()),
+            protobufs::PortNum::TextMessageApp,
+            PacketDestination::Broadcast,
             MeshChannel::new(channel).map_err(|e| e.to_string())?,
+            true,
+            false,
+            true,
+            Some(reply_id),
+            Some(1),
         )
         .await
         .map_err(|e| e.to_string())?;
@@ -67,6 +211,10 @@ pub async fn send_waypoint(
         .get_mut(&device_key)
         .ok_or("Radio connection not initialized")?;
 
+    packet_api
+        .device
+        .check_waypoint_editable(waypoint.id, packet_api.device.my_node_info.my_node_num)?;
+
     connection
         .send_waypoint(
             packet_api,
@@ -83,24 +231,116 @@ pub async fn send_waypoint(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn update_waypoint(
+    device_key: DeviceKey,
+    waypoint: NormalizedWaypoint,
+    channel: u32,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<(), CommandError> {
+    debug!("Called update_waypoint command");
+    trace!("Called on channel {} with waypoint {:?}", channel, waypoint);
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut connections_guard = radio_connections.inner.lock().await;
+    let connection = connections_guard
+        .get_mut(&device_key)
+        .ok_or("Radio connection not initialized")?;
+
+    if !packet_api.device.waypoints.contains_key(&waypoint.id) {
+        return Err("Waypoint not found".into());
+    }
+
+    packet_api
+        .device
+        .check_waypoint_editable(waypoint.id, packet_api.device.my_node_info.my_node_num)?;
+
+    // Re-sending with the same id replaces the waypoint on every node
+    connection
+        .send_waypoint(
+            packet_api,
+            waypoint.clone().into(),
+            PacketDestination::Broadcast,
+            true,
+            MeshChannel::new(channel).map_err(|e| e.to_string())?,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    packet_api.device.add_waypoint(waypoint);
+
+    events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn delete_waypoint(
     device_key: DeviceKey,
     waypoint_id: u32,
+    channel: Option<u32>,
     app_handle: tauri::AppHandle,
     mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
 ) -> Result<(), CommandError> {
     debug!("Called delete_waypoint command");
+    trace!(
+        "Called on channel {:?} with waypoint {}",
+        channel,
+        waypoint_id
+    );
 
     let mut devices_guard = mesh_devices.inner.lock().await;
     let packet_api = devices_guard
         .get_mut(&device_key)
         .ok_or("Device not connected")?;
 
-    if packet_api.device.waypoints.contains_key(&waypoint_id) {
-        let _removed_waypoint = packet_api.device.waypoints.remove(&waypoint_id);
+    let mut connections_guard = radio_connections.inner.lock().await;
+
+    let waypoint = packet_api
+        .device
+        .waypoints
+        .get(&waypoint_id)
+        .cloned()
+        .ok_or("Waypoint not found")?;
+
+    packet_api
+        .device
+        .check_waypoint_editable(waypoint_id, packet_api.device.my_node_info.my_node_num)?;
+
+    // Nodes delete waypoints when they receive one that has already expired.
+    // Devices without a radio, such as MQTT clients, can only delete locally.
+    match connections_guard.get_mut(&device_key) {
+        Some(connection) => {
+            connection
+                .send_waypoint(
+                    packet_api,
+                    NormalizedWaypoint {
+                        expire: 1,
+                        ..waypoint
+                    }
+                    .into(),
+                    PacketDestination::Broadcast,
+                    true,
+                    MeshChannel::new(channel.unwrap_or(0)).map_err(|e| e.to_string())?,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        None => debug!(
+            "No radio connection for device {}, deleting waypoint {} locally",
+            device_key, waypoint_id
+        ),
     }
 
+    packet_api.device.waypoints.remove(&waypoint_id);
+
     events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
 
     Ok(())
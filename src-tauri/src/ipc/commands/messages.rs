@@ -0,0 +1,94 @@
+use crate::device::helpers::get_channel_name;
+use crate::device::messages::{
+    self, MessageExportFormat, MessageExportRecord, MessageSearchFilters, MessageSearchResult,
+};
+use crate::ipc::CommandError;
+use crate::state::{self, DeviceKey};
+
+use log::{debug, trace};
+
+#[tauri::command]
+pub async fn search_messages(
+    query: String,
+    filters: Option<MessageSearchFilters>,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+) -> Result<Vec<MessageSearchResult>, CommandError> {
+    debug!("Called search_messages command");
+    trace!("Called with query \"{}\" and filters {:?}", query, filters);
+
+    let filters = filters.unwrap_or_default();
+    let devices_guard = mesh_devices.inner.lock().await;
+
+    let mut results: Vec<MessageSearchResult> = devices_guard
+        .iter()
+        .filter(|(device_key, _)| {
+            filters
+                .device_key
+                .as_ref()
+                .map_or(true, |filter_key| filter_key == *device_key)
+        })
+        .flat_map(|(device_key, packet_api)| {
+            packet_api
+                .device
+                .search_messages(device_key, &query, &filters)
+        })
+        .collect();
+
+    results.sort_by_key(|result| result.time);
+
+    if let Some(limit) = filters.limit {
+        let excess = results.len().saturating_sub(limit as usize);
+        results.drain(..excess);
+    }
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn export_messages(
+    device_key: DeviceKey,
+    channel: Option<u32>,
+    start: Option<u32>,
+    end: Option<u32>,
+    format: MessageExportFormat,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+) -> Result<String, CommandError> {
+    debug!("Called export_messages command");
+    trace!(
+        "Called with channel {:?}, start {:?}, end {:?}, format {:?}",
+        channel,
+        start,
+        end,
+        format
+    );
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let filters = MessageSearchFilters {
+        channel_id: channel,
+        start,
+        end,
+        ..Default::default()
+    };
+
+    let results = packet_api.device.search_messages(&device_key, "", &filters);
+
+    let records: Vec<MessageExportRecord> = results
+        .iter()
+        .map(|result| MessageExportRecord::new(&mut packet_api.device, result))
+        .collect();
+
+    let title = match channel {
+        Some(channel_id) => format!(
+            "Messages in {}",
+            get_channel_name(&mut packet_api.device, &channel_id)
+                .unwrap_or_else(|| format!("Channel {}", channel_id))
+        ),
+        None => format!("Messages on {}", device_key),
+    };
+
+    Ok(messages::export_messages(&records, &title, format)?)
+}
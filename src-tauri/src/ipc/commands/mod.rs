@@ -1,4 +1,11 @@
+pub mod canned_messages;
 pub mod connections;
+pub mod geofence;
 pub mod graph;
+pub mod health;
 pub mod mesh;
+pub mod messages;
 pub mod radio;
+pub mod serial;
+pub mod track;
+pub mod waypoint;
@@ -1,11 +1,16 @@
+use crate::device::ChannelPskType;
 use crate::ipc::events;
+use crate::ipc::ChannelKeyUpdateStatus;
 use crate::ipc::CommandError;
 use crate::ipc::DeviceBulkConfig;
+use crate::packet_api::MeshPacketApi;
 use crate::state;
 use crate::state::DeviceKey;
 
 use log::debug;
 use log::trace;
+use log::warn;
+use meshtastic::api::ConnectedStreamApi;
 use meshtastic::protobufs;
 
 #[tauri::command]
@@ -186,3 +191,175 @@ pub async fn update_device_config_bulk(
 
     Ok(())
 }
+
+async fn apply_channel_psk(
+    packet_api: &mut MeshPacketApi,
+    connection: &mut ConnectedStreamApi,
+    channel_index: u32,
+    psk: Vec<u8>,
+) -> Result<(), String> {
+    if packet_api.device.config_in_progress {
+        return Err("Configuration transaction already started".into());
+    }
+
+    let mut channel_config = packet_api
+        .device
+        .channels
+        .get(&channel_index)
+        .ok_or(format!("Channel {} not found on device", channel_index))?
+        .config
+        .clone();
+
+    channel_config
+        .settings
+        .as_mut()
+        .ok_or(format!("Channel {} has no settings", channel_index))?
+        .psk = psk.clone();
+
+    connection
+        .start_config_transaction()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // The transaction needs to be closed even if the update fails, otherwise
+    // the device is left waiting for a commit
+    let update_result = connection
+        .update_channel_config(packet_api, channel_config)
+        .await
+        .map_err(|e| e.to_string());
+
+    let commit_result = connection
+        .commit_config_transaction()
+        .await
+        .map_err(|e| e.to_string());
+
+    update_result?;
+    commit_result?;
+
+    packet_api.device.set_channel_psk(channel_index, psk);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_channel_psk(
+    device_key: DeviceKey,
+    channel_index: u32,
+    psk_type: ChannelPskType,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<ChannelKeyUpdateStatus, CommandError> {
+    debug!("Called set_channel_psk command");
+    trace!(
+        "Called on channel {} with key type {:?}",
+        channel_index,
+        psk_type
+    );
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut connections_guard = radio_connections.inner.lock().await;
+    let connection = connections_guard
+        .get_mut(&device_key)
+        .ok_or("Radio connection not initialized")?;
+
+    apply_channel_psk(
+        packet_api,
+        connection,
+        channel_index,
+        psk_type.generate_psk(),
+    )
+    .await?;
+
+    events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
+
+    Ok(ChannelKeyUpdateStatus {
+        device_key,
+        channel_index,
+        successful: true,
+        warnings: psk_type.get_channel_key_warnings(channel_index),
+    })
+}
+
+#[tauri::command]
+pub async fn rotate_channel_psk_all_devices(
+    channel_index: u32,
+    psk_type: ChannelPskType,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<Vec<ChannelKeyUpdateStatus>, CommandError> {
+    debug!("Called rotate_channel_psk_all_devices command");
+    trace!(
+        "Called on channel {} with key type {:?}",
+        channel_index,
+        psk_type
+    );
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let mut connections_guard = radio_connections.inner.lock().await;
+
+    // All devices need to share the same key to keep communicating
+    let psk = psk_type.generate_psk();
+    let mut statuses = vec![];
+
+    for (device_key, packet_api) in devices_guard.iter_mut() {
+        let mut warnings = psk_type.get_channel_key_warnings(channel_index);
+
+        let connection = match connections_guard.get_mut(device_key) {
+            Some(c) => c,
+            None => {
+                warnings.push("Radio connection not initialized".into());
+                statuses.push(ChannelKeyUpdateStatus {
+                    device_key: device_key.clone(),
+                    channel_index,
+                    successful: false,
+                    warnings,
+                });
+                continue;
+            }
+        };
+
+        let result = apply_channel_psk(packet_api, connection, channel_index, psk.clone()).await;
+
+        let successful = match result {
+            Ok(_) => {
+                // The key is already applied, so a failed UI update doesn't fail the rotation
+                if let Err(e) = events::dispatch_updated_device(&app_handle, &packet_api.device) {
+                    warn!("Failed to dispatch device {}: {}", device_key, e);
+                    warnings.push(format!("Failed to refresh device state: {}", e));
+                }
+
+                true
+            }
+            Err(e) => {
+                warn!("Failed to rotate key on device {}: {}", device_key, e);
+                warnings.push(format!("Failed to update key: {}", e));
+                false
+            }
+        };
+
+        statuses.push(ChannelKeyUpdateStatus {
+            device_key: device_key.clone(),
+            channel_index,
+            successful,
+            warnings,
+        });
+    }
+
+    // Devices that kept the old key can no longer talk to the rotated ones
+    if statuses.iter().any(|s| !s.successful) {
+        for status in statuses.iter_mut().filter(|s| s.successful) {
+            status.warnings.push(
+                "Key rotation failed on at least one other device, devices no longer share a key"
+                    .into(),
+            );
+        }
+    }
+
+    Ok(statuses)
+}
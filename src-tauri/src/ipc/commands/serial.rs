@@ -0,0 +1,99 @@
+use crate::ipc::helpers::spawn_serial_bridge_outbound_handler;
+use crate::ipc::CommandError;
+use crate::serial_bridge::{self, SerialBridgeInfo, SerialBridgeTarget};
+use crate::state;
+use crate::state::DeviceKey;
+
+use log::{debug, trace};
+use meshtastic::types::MeshChannel;
+
+#[tauri::command]
+pub async fn open_serial_bridge(
+    device_key: DeviceKey,
+    node_num: u32,
+    channel: u32,
+    target: SerialBridgeTarget,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<SerialBridgeInfo, CommandError> {
+    debug!("Called open_serial_bridge command");
+    trace!(
+        "Called for node {} on channel {} with target {:?}",
+        node_num,
+        channel,
+        target
+    );
+
+    let mesh_channel = MeshChannel::new(channel).map_err(|e| e.to_string())?;
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    if packet_api.serial_bridges.contains_key(&node_num) {
+        return Err(format!("Serial bridge to node {} already open", node_num).into());
+    }
+
+    let (outbound_sender, outbound_listener) = tokio::sync::mpsc::unbounded_channel();
+
+    let bridge =
+        serial_bridge::open_serial_bridge(node_num, channel, target, outbound_sender).await?;
+    let bridge_info = bridge.info.clone();
+
+    packet_api.serial_bridges.insert(node_num, bridge);
+
+    spawn_serial_bridge_outbound_handler(
+        outbound_listener,
+        mesh_devices.inner.clone(),
+        radio_connections.inner.clone(),
+        device_key,
+        node_num,
+        mesh_channel,
+    );
+
+    Ok(bridge_info)
+}
+
+#[tauri::command]
+pub async fn close_serial_bridge(
+    device_key: DeviceKey,
+    node_num: u32,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+) -> Result<(), CommandError> {
+    debug!("Called close_serial_bridge command");
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    // Dropping the bridge closes the local endpoint and stops the outbound handler
+    packet_api
+        .serial_bridges
+        .remove(&node_num)
+        .ok_or(format!("No serial bridge open to node {}", node_num))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_serial_bridges(
+    device_key: DeviceKey,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+) -> Result<Vec<SerialBridgeInfo>, CommandError> {
+    debug!("Called get_serial_bridges command");
+
+    let devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get(&device_key)
+        .ok_or("Device not connected")?;
+
+    let bridges = packet_api
+        .serial_bridges
+        .values()
+        .map(|bridge| bridge.info.clone())
+        .collect();
+
+    Ok(bridges)
+}
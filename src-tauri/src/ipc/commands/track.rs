@@ -0,0 +1,58 @@
+use crate::device::helpers::get_node_user_name;
+use crate::ipc::CommandError;
+use crate::state::{self, DeviceKey};
+use crate::track::{self, NodeTrack, TrackExportFormat};
+
+use log::{debug, trace};
+
+#[tauri::command]
+pub async fn get_node_track(
+    device_key: DeviceKey,
+    node_num: u32,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+) -> Result<Option<NodeTrack>, CommandError> {
+    debug!("Called get_node_track command");
+    trace!("Called with node {}", node_num);
+
+    let devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get(&device_key)
+        .ok_or("Device not connected")?;
+
+    Ok(packet_api.tracks.get_track(node_num).cloned())
+}
+
+#[tauri::command]
+pub async fn export_node_track(
+    device_key: DeviceKey,
+    node_num: u32,
+    start: Option<u32>,
+    end: Option<u32>,
+    format: TrackExportFormat,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+) -> Result<String, CommandError> {
+    debug!("Called export_node_track command");
+    trace!(
+        "Called with node {}, start {:?}, end {:?}, format {:?}",
+        node_num,
+        start,
+        end,
+        format
+    );
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let points = packet_api
+        .tracks
+        .get_track(node_num)
+        .ok_or("No track recorded for node")?
+        .get_points_between(start, end);
+
+    let track_name = get_node_user_name(&mut packet_api.device, &node_num)
+        .unwrap_or_else(|| format!("Node {}", node_num));
+
+    Ok(track::export_track(&points, &track_name, format))
+}
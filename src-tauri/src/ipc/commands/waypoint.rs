@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use crate::device::helpers::generate_rand_id;
+use crate::device::NormalizedWaypoint;
+use crate::ipc::helpers::spawn_waypoint_broadcast;
+use crate::ipc::{events, CommandError};
+use crate::state::{self, DeviceKey};
+use crate::waypoint::{self, WaypointFileFormat};
+
+use log::{debug, trace, warn};
+use meshtastic::types::MeshChannel;
+
+#[tauri::command]
+pub async fn import_waypoints(
+    device_key: DeviceKey,
+    file: PathBuf,
+    broadcast: Option<bool>,
+    channel: Option<u32>,
+    app_handle: tauri::AppHandle,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+    radio_connections: tauri::State<'_, state::radio_connections::RadioConnectionsState>,
+) -> Result<Vec<NormalizedWaypoint>, CommandError> {
+    debug!("Called import_waypoints command");
+    trace!(
+        "Called with file {:?}, broadcast {:?} on channel {:?}",
+        file,
+        broadcast,
+        channel
+    );
+
+    let format = WaypointFileFormat::from_path(&file).ok_or("Unsupported waypoint file type")?;
+    let contents = tokio::fs::read_to_string(&file)
+        .await
+        .map_err(|e| e.to_string())?;
+    let parsed_waypoints = waypoint::import_waypoints(&contents, format)?;
+
+    let channel = channel.unwrap_or(0);
+    MeshChannel::new(channel).map_err(|e| e.to_string())?;
+
+    let mut devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get_mut(&device_key)
+        .ok_or("Device not connected")?;
+
+    let my_node_num = packet_api.device.my_node_info.my_node_num;
+    let mut imported_waypoints = vec![];
+
+    for mut imported_waypoint in parsed_waypoints {
+        if imported_waypoint.id == 0 {
+            imported_waypoint.id = generate_rand_id();
+        }
+
+        if let Err(e) = packet_api
+            .device
+            .check_waypoint_editable(imported_waypoint.id, my_node_num)
+        {
+            warn!("Skipping imported waypoint: {}", e);
+            continue;
+        }
+
+        packet_api.device.add_waypoint(imported_waypoint.clone());
+        imported_waypoints.push(imported_waypoint);
+    }
+
+    events::dispatch_updated_device(&app_handle, &packet_api.device).map_err(|e| e.to_string())?;
+
+    if broadcast.unwrap_or(false) {
+        spawn_waypoint_broadcast(
+            app_handle.clone(),
+            mesh_devices.inner.clone(),
+            radio_connections.inner.clone(),
+            device_key,
+            imported_waypoints.clone(),
+            channel,
+        );
+    }
+
+    Ok(imported_waypoints)
+}
+
+#[tauri::command]
+pub async fn export_waypoints(
+    device_key: DeviceKey,
+    format: WaypointFileFormat,
+    mesh_devices: tauri::State<'_, state::mesh_devices::MeshDevicesState>,
+) -> Result<String, CommandError> {
+    debug!("Called export_waypoints command");
+    trace!("Called with format {:?}", format);
+
+    let devices_guard = mesh_devices.inner.lock().await;
+    let packet_api = devices_guard
+        .get(&device_key)
+        .ok_or("Device not connected")?;
+
+    let mut waypoints: Vec<NormalizedWaypoint> =
+        packet_api.device.waypoints.values().cloned().collect();
+    waypoints.sort_by_key(|waypoint| waypoint.id);
+
+    Ok(waypoint::export_waypoints(&waypoints, format))
+}
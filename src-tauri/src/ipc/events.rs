@@ -1,4 +1,10 @@
-use crate::{device, graph::ds::graph::MeshGraph};
+use crate::{
+    device,
+    geofence::GeofenceEvent,
+    graph::ds::{components::ComponentChange, graph::MeshGraph, timeout::GraphCleanResult},
+    health::{NetworkAlert, NetworkHealth},
+    track::NodeMovementAlert,
+};
 use log::{debug, trace};
 use tauri::Manager;
 
@@ -53,3 +59,75 @@ pub fn dispatch_updated_graph<R: tauri::Runtime>(
 
     Ok(())
 }
+
+pub fn dispatch_graph_stale_items<R: tauri::Runtime>(
+    handle: &tauri::AppHandle<R>,
+    clean_result: GraphCleanResult,
+) -> tauri::Result<()> {
+    debug!("Dispatching stale graph items");
+
+    handle.emit_all("graph_stale_items", clean_result)?;
+
+    Ok(())
+}
+
+pub fn dispatch_graph_component_changes<R: tauri::Runtime>(
+    handle: &tauri::AppHandle<R>,
+    changes: Vec<ComponentChange>,
+) -> tauri::Result<()> {
+    for change in changes {
+        debug!("Dispatching graph component change");
+        trace!("{:?}", change);
+
+        handle.emit_all("graph_component_change", change)?;
+    }
+
+    Ok(())
+}
+
+pub fn dispatch_network_health<R: tauri::Runtime>(
+    handle: &tauri::AppHandle<R>,
+    health: NetworkHealth,
+) -> tauri::Result<()> {
+    debug!("Dispatching network health");
+
+    handle.emit_all("network_health", health)?;
+
+    Ok(())
+}
+
+pub fn dispatch_network_alert<R: tauri::Runtime>(
+    handle: &tauri::AppHandle<R>,
+    alert: NetworkAlert,
+) -> tauri::Result<()> {
+    debug!("Dispatching network alert");
+    trace!("{:?}", alert);
+
+    handle.emit_all("network_alert", alert)?;
+
+    Ok(())
+}
+
+pub fn dispatch_node_movement<R: tauri::Runtime>(
+    handle: &tauri::AppHandle<R>,
+    alert: NodeMovementAlert,
+) -> tauri::Result<()> {
+    debug!("Dispatching node movement");
+    trace!("{:?}", alert);
+
+    handle.emit_all("node_movement", alert)?;
+
+    Ok(())
+}
+
+pub fn dispatch_geofence_event<R: tauri::Runtime>(
+    handle: &tauri::AppHandle<R>,
+    event: GeofenceEvent,
+) -> tauri::Result<()> {
+    debug!("Dispatching geofence event");
+    trace!("{:?}", event);
+
+    handle.emit_all("geofence_event", event)?;
+
+    Ok(())
+}
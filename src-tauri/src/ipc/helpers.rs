@@ -1,15 +1,21 @@
 use std::time::Duration;
 
-use log::{trace, warn};
-use meshtastic::packet::PacketRouter;
+use log::{debug, trace, warn};
+use meshtastic::packet::{PacketDestination, PacketRouter};
 use meshtastic::protobufs;
+use meshtastic::types::{EncodedMeshPacketData, MeshChannel, NodeId};
 use tokio::sync::mpsc::UnboundedReceiver;
 
-use crate::device::SerialDeviceStatus;
-use crate::ipc::events::dispatch_configuration_status;
+use crate::device::{helpers::get_current_time_u32, NormalizedWaypoint, SerialDeviceStatus};
+use crate::graph::ds::history::{write_history, GRAPH_HISTORY_FLUSH_INTERVAL};
+use crate::ipc::events::{dispatch_configuration_status, dispatch_updated_device};
 use crate::ipc::ConfigurationStatus;
+use crate::mqtt::{build_proxy_message, handle_mqtt_message, publish_proxy_message, MqttMessage};
 use crate::state::{self, DeviceKey};
 
+const WAYPOINT_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const WAYPOINT_BROADCAST_INTERVAL: Duration = Duration::from_secs(5);
+
 pub fn spawn_configuration_timeout_handler(
     handle: tauri::AppHandle,
     connected_devices_inner: state::mesh_devices::MeshDevicesStateInner,
@@ -64,6 +70,110 @@ pub fn spawn_configuration_timeout_handler(
     });
 }
 
+/// Periodically removes expired waypoints from a device until the device disconnects.
+pub fn spawn_waypoint_expiry_handler(
+    handle: tauri::AppHandle,
+    connected_devices_arc: state::mesh_devices::MeshDevicesStateInner,
+    device_key: DeviceKey,
+) {
+    trace!("Spawning waypoint expiry handler");
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(WAYPOINT_EXPIRY_CHECK_INTERVAL).await;
+
+            let mut devices_guard = connected_devices_arc.lock().await;
+            let packet_api = match devices_guard.get_mut(&device_key) {
+                Some(d) => d,
+                None => {
+                    debug!(
+                        "Device {} disconnected, stopping waypoint expiry",
+                        device_key
+                    );
+                    break;
+                }
+            };
+
+            let expired_ids = packet_api
+                .device
+                .remove_expired_waypoints(get_current_time_u32());
+
+            if expired_ids.is_empty() {
+                continue;
+            }
+
+            if let Err(e) = dispatch_updated_device(&handle, &packet_api.device) {
+                warn!("Failed to dispatch device after waypoint expiry: {}", e);
+            }
+        }
+    });
+}
+
+/// Broadcasts waypoints one at a time, waiting between each so a large batch
+/// doesn't flood the mesh. Stops early if the device disconnects.
+pub fn spawn_waypoint_broadcast(
+    handle: tauri::AppHandle,
+    connected_devices_arc: state::mesh_devices::MeshDevicesStateInner,
+    radio_connections_arc: state::radio_connections::RadioConnectionsStateInner,
+    device_key: DeviceKey,
+    waypoints: Vec<NormalizedWaypoint>,
+    channel: u32,
+) {
+    trace!("Spawning broadcast of {} waypoints", waypoints.len());
+
+    tauri::async_runtime::spawn(async move {
+        for waypoint in waypoints {
+            {
+                let mut devices_guard = connected_devices_arc.lock().await;
+                let mut connections_guard = radio_connections_arc.lock().await;
+
+                let (packet_api, connection) = match (
+                    devices_guard.get_mut(&device_key),
+                    connections_guard.get_mut(&device_key),
+                ) {
+                    (Some(p), Some(c)) => (p, c),
+                    _ => {
+                        warn!(
+                            "Device {} disconnected, stopping waypoint broadcast",
+                            device_key
+                        );
+                        break;
+                    }
+                };
+
+                let mesh_channel = match MeshChannel::new(channel) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        warn!("{}", e);
+                        break;
+                    }
+                };
+
+                if let Err(e) = connection
+                    .send_waypoint(
+                        packet_api,
+                        waypoint.into(),
+                        PacketDestination::Broadcast,
+                        true,
+                        mesh_channel,
+                    )
+                    .await
+                {
+                    warn!("Failed to broadcast waypoint: {}", e);
+                }
+
+                if let Err(e) = dispatch_updated_device(&handle, &packet_api.device) {
+                    warn!("Failed to dispatch device after waypoint broadcast: {}", e);
+                }
+            }
+
+            tokio::time::sleep(WAYPOINT_BROADCAST_INTERVAL).await;
+        }
+
+        debug!("Finished waypoint broadcast");
+    });
+}
+
 pub fn spawn_decoded_handler(
     mut decoded_listener: UnboundedReceiver<protobufs::FromRadio>,
     connected_devices_arc: state::mesh_devices::MeshDevicesStateInner,
@@ -95,3 +205,180 @@ pub fn spawn_decoded_handler(
         }
     });
 }
+
+pub fn spawn_mqtt_packet_handler(
+    mut message_listener: UnboundedReceiver<MqttMessage>,
+    connected_devices_arc: state::mesh_devices::MeshDevicesStateInner,
+    device_key: DeviceKey,
+) {
+    tauri::async_runtime::spawn(async move {
+        while let Some(message) = message_listener.recv().await {
+            let mut devices_guard = connected_devices_arc.lock().await;
+            let packet_api = match devices_guard
+                .get_mut(&device_key)
+                .ok_or("Device not initialized")
+            {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+
+            if let Err(err) = handle_mqtt_message(packet_api, &message) {
+                warn!("{}", err);
+            }
+        }
+    });
+}
+
+pub fn spawn_mqtt_proxy_uplink_handler(
+    client: rumqttc::AsyncClient,
+    mut uplink_listener: UnboundedReceiver<protobufs::MqttClientProxyMessage>,
+) {
+    tauri::async_runtime::spawn(async move {
+        while let Some(message) = uplink_listener.recv().await {
+            trace!("Publishing proxy message from radio: {:?}", message);
+
+            if let Err(e) = publish_proxy_message(&client, message).await {
+                warn!("Failed to publish proxy message: {}", e);
+            }
+        }
+
+        debug!("MQTT proxy uplink handler stopped");
+    });
+}
+
+pub fn spawn_mqtt_proxy_downlink_handler(
+    mut message_listener: UnboundedReceiver<MqttMessage>,
+    radio_connections_arc: state::radio_connections::RadioConnectionsStateInner,
+    device_key: DeviceKey,
+) {
+    tauri::async_runtime::spawn(async move {
+        while let Some(message) = message_listener.recv().await {
+            trace!(
+                "Delivering proxy message on topic {} to radio",
+                message.topic
+            );
+
+            let mut connections_guard = radio_connections_arc.lock().await;
+            let connection = match connections_guard
+                .get_mut(&device_key)
+                .ok_or("Radio connection not initialized")
+            {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+
+            let payload_variant = protobufs::to_radio::PayloadVariant::MqttClientProxyMessage(
+                build_proxy_message(message),
+            );
+
+            if let Err(e) = connection.send_to_radio_packet(Some(payload_variant)).await {
+                warn!("Failed to deliver proxy message to radio: {}", e);
+            }
+        }
+
+        debug!("MQTT proxy downlink handler stopped");
+    });
+}
+
+pub fn spawn_serial_bridge_outbound_handler(
+    mut outbound_listener: UnboundedReceiver<Vec<u8>>,
+    connected_devices_arc: state::mesh_devices::MeshDevicesStateInner,
+    radio_connections_arc: state::radio_connections::RadioConnectionsStateInner,
+    device_key: DeviceKey,
+    node_num: u32,
+    channel: MeshChannel,
+) {
+    tauri::async_runtime::spawn(async move {
+        while let Some(data) = outbound_listener.recv().await {
+            trace!("Sending {} serial bytes to node {}", data.len(), node_num);
+
+            let mut devices_guard = connected_devices_arc.lock().await;
+            let packet_api = match devices_guard
+                .get_mut(&device_key)
+                .ok_or("Device not initialized")
+            {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+
+            let mut connections_guard = radio_connections_arc.lock().await;
+            let connection = match connections_guard
+                .get_mut(&device_key)
+                .ok_or("Radio connection not initialized")
+            {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = connection
+                .send_mesh_packet(
+                    packet_api,
+                    EncodedMeshPacketData::new(data),
+                    protobufs::PortNum::SerialApp,
+                    PacketDestination::Node(NodeId::new(node_num)),
+                    channel,
+                    true,
+                    false,
+                    false,
+                    None,
+                    None,
+                )
+                .await
+            {
+                warn!("Failed to send serial data to node {}: {}", node_num, e);
+            }
+        }
+
+        debug!(
+            "Serial bridge outbound handler for node {} stopped",
+            node_num
+        );
+    });
+}
+
+/// Periodically writes recorded graph history to disk, so events aren't
+/// written while the graph is locked.
+pub fn spawn_graph_history_flush_handler(graph_arc: state::graph::GraphStateInner) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(GRAPH_HISTORY_FLUSH_INTERVAL).await;
+
+            let pending_write = match graph_arc.lock() {
+                Ok(mut graph) => graph
+                    .history
+                    .take_pending_write(chrono::Utc::now().naive_utc()),
+                Err(e) => {
+                    warn!("Failed to lock graph to flush history: {}", e);
+                    continue;
+                }
+            };
+
+            let (store_path, write) = match pending_write {
+                Some(w) => w,
+                None => continue,
+            };
+
+            let result =
+                tauri::async_runtime::spawn_blocking(move || write_history(&store_path, write))
+                    .await;
+
+            match result {
+                Ok(Ok(())) => trace!("Flushed graph history"),
+                Ok(Err(e)) => warn!("Failed to persist graph history: {}", e),
+                Err(e) => warn!("Graph history flush task failed: {}", e),
+            }
+        }
+    });
+}
@@ -52,6 +52,15 @@ pub struct ConfigurationStatus {
     pub message: Option<String>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelKeyUpdateStatus {
+    pub device_key: DeviceKey,
+    pub channel_index: u32,
+    pub successful: bool,
+    pub warnings: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct DeviceBulkConfig {
     radio: Option<protobufs::LocalConfig>,
@@ -3,14 +3,22 @@
     windows_subsystem = "windows"
 )]
 
+mod canned_messages;
 mod cli;
 mod device;
+mod geo;
+mod geofence;
 mod graph;
+mod health;
 mod ipc;
+mod mqtt;
 mod packet_api;
+mod serial_bridge;
 mod state;
+mod track;
+mod waypoint;
 
-use log::{info, LevelFilter};
+use log::{info, warn, LevelFilter};
 use specta::{
     export::ts_with_cfg,
     ts::{BigIntExportBehavior, ExportConfiguration, ModuleExportBehavior, TsExportError},
@@ -26,6 +34,68 @@ fn export_ts_types(file_path: &str) -> Result<(), TsExportError> {
     ts_with_cfg(file_path, &ts_export_config)
 }
 
+fn load_graph_history(app: &tauri::App, graph_state: &state::graph::GraphState) {
+    let store_path = match app.path_resolver().app_data_dir() {
+        Some(dir) => dir.join(graph::ds::history::GRAPH_HISTORY_FILE_NAME),
+        None => {
+            warn!("No app data directory, graph history will not be persisted");
+            return;
+        }
+    };
+
+    match graph::ds::history::GraphHistory::open(store_path) {
+        Ok(history) => {
+            if let Ok(mut mesh_graph) = graph_state.inner.lock() {
+                mesh_graph.history = history;
+            }
+
+            ipc::helpers::spawn_graph_history_flush_handler(graph_state.inner.clone());
+        }
+        Err(e) => warn!("Failed to load graph history: {}", e),
+    }
+}
+
+fn load_geofences(app: &tauri::App, geofences_state: &state::geofences::GeofencesState) {
+    let store_path = match app.path_resolver().app_data_dir() {
+        Some(dir) => dir.join(geofence::GEOFENCES_FILE_NAME),
+        None => {
+            warn!("No app data directory, geofences will not be persisted");
+            return;
+        }
+    };
+
+    match geofence::GeofenceManager::open(store_path) {
+        Ok(manager) => {
+            if let Ok(mut geofences) = geofences_state.inner.lock() {
+                *geofences = manager;
+            }
+        }
+        Err(e) => warn!("Failed to load geofences: {}", e),
+    }
+}
+
+fn load_canned_messages(
+    app: &tauri::App,
+    canned_messages_state: &state::canned_messages::CannedMessagesState,
+) {
+    let store_path = match app.path_resolver().app_data_dir() {
+        Some(dir) => dir.join(canned_messages::CANNED_MESSAGES_FILE_NAME),
+        None => {
+            warn!("No app data directory, canned message sets will not be persisted");
+            return;
+        }
+    };
+
+    match canned_messages::CannedMessageLibrary::open(store_path) {
+        Ok(library) => {
+            if let Ok(mut sets) = canned_messages_state.inner.lock() {
+                *sets = library;
+            }
+        }
+        Err(e) => warn!("Failed to load canned message sets: {}", e),
+    }
+}
+
 #[cfg(debug_assertions)]
 const LOG_LEVEL: LevelFilter = LevelFilter::Debug;
 
@@ -53,6 +123,15 @@ fn main() {
                 state::radio_connections::RadioConnectionsState::new();
             let mut inital_autoconnect_state = state::autoconnect::AutoConnectState::new();
             let initial_graph_state = state::graph::GraphState::new();
+            load_graph_history(app, &initial_graph_state);
+            let initial_mqtt_connections_state =
+                state::mqtt_connections::MqttConnectionsState::new();
+            let initial_mqtt_proxies_state = state::mqtt_proxies::MqttProxiesState::new();
+            let initial_health_monitor_state = state::health::HealthMonitorState::new();
+            let initial_geofences_state = state::geofences::GeofencesState::new();
+            load_geofences(app, &initial_geofences_state);
+            let initial_canned_messages_state = state::canned_messages::CannedMessagesState::new();
+            load_canned_messages(app, &initial_canned_messages_state);
 
             match cli::handle_cli_matches(app, &mut inital_autoconnect_state) {
                 Ok(_) => {}
@@ -63,6 +142,11 @@ fn main() {
             app.app_handle().manage(initial_radio_connections_state);
             app.app_handle().manage(inital_autoconnect_state); // Needs to be set after being mutated by CLI parser
             app.app_handle().manage(initial_graph_state);
+            app.app_handle().manage(initial_mqtt_connections_state);
+            app.app_handle().manage(initial_mqtt_proxies_state);
+            app.app_handle().manage(initial_health_monitor_state);
+            app.app_handle().manage(initial_geofences_state);
+            app.app_handle().manage(initial_canned_messages_state);
 
             Ok(())
         })
@@ -71,19 +155,61 @@ fn main() {
             ipc::commands::connections::get_all_serial_ports,
             ipc::commands::connections::connect_to_serial_port,
             ipc::commands::connections::connect_to_tcp_port,
+            ipc::commands::connections::connect_to_mqtt_broker,
+            ipc::commands::connections::enable_mqtt_client_proxy,
+            ipc::commands::connections::disable_mqtt_client_proxy,
             ipc::commands::connections::drop_device_connection,
             ipc::commands::connections::drop_all_device_connections,
             ipc::commands::mesh::send_text,
+            ipc::commands::mesh::send_reply,
+            ipc::commands::mesh::send_reaction,
             ipc::commands::mesh::send_waypoint,
+            ipc::commands::mesh::update_waypoint,
             ipc::commands::mesh::delete_waypoint,
+            ipc::commands::messages::search_messages,
+            ipc::commands::messages::export_messages,
+            ipc::commands::waypoint::import_waypoints,
+            ipc::commands::waypoint::export_waypoints,
             ipc::commands::radio::update_device_config,
             ipc::commands::radio::update_device_user,
             ipc::commands::radio::start_configuration_transaction,
             ipc::commands::radio::commit_configuration_transaction,
             ipc::commands::radio::update_device_config_bulk,
+            ipc::commands::radio::set_channel_psk,
+            ipc::commands::radio::rotate_channel_psk_all_devices,
+            ipc::commands::serial::open_serial_bridge,
+            ipc::commands::serial::close_serial_bridge,
+            ipc::commands::serial::get_serial_bridges,
+            ipc::commands::track::get_node_track,
+            ipc::commands::track::export_node_track,
             ipc::commands::graph::get_graph_state,
             ipc::commands::graph::initialize_timeout_handler,
             ipc::commands::graph::stop_timeout_handler,
+            ipc::commands::graph::get_graph_timeout_config,
+            ipc::commands::graph::update_graph_timeout_config,
+            ipc::commands::graph::get_graph_at,
+            ipc::commands::graph::diff_graphs,
+            ipc::commands::graph::compute_route,
+            ipc::commands::graph::export_graph,
+            ipc::commands::graph::get_components,
+            ipc::commands::graph::get_coverage_geojson,
+            ipc::commands::graph::get_coverage_raster,
+            ipc::commands::graph::suggest_relay_placement,
+            ipc::commands::health::initialize_health_monitor,
+            ipc::commands::health::stop_health_monitor,
+            ipc::commands::health::get_network_health,
+            ipc::commands::health::get_health_thresholds,
+            ipc::commands::health::update_health_thresholds,
+            ipc::commands::geofence::add_geofence,
+            ipc::commands::geofence::remove_geofence,
+            ipc::commands::geofence::get_geofences,
+            ipc::commands::geofence::get_geofence_history,
+            ipc::commands::canned_messages::request_canned_messages,
+            ipc::commands::canned_messages::set_canned_messages,
+            ipc::commands::canned_messages::get_canned_message_sets,
+            ipc::commands::canned_messages::save_canned_message_set,
+            ipc::commands::canned_messages::delete_canned_message_set,
+            ipc::commands::canned_messages::apply_canned_message_set,
         ])
         .run(tauri::generate_context!())
         .expect("Error while running tauri application");
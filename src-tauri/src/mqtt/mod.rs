@@ -0,0 +1,563 @@
+use std::time::Duration;
+
+use log::{debug, trace, warn};
+use meshtastic::packet::PacketRouter;
+use meshtastic::protobufs;
+use meshtastic::ts::specta::{self, Type};
+use meshtastic::Message;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::device::helpers::generate_rand_id;
+use crate::device::MeshDevice;
+use crate::packet_api::crypto::get_preset_channel_name;
+use crate::packet_api::handlers::DeviceUpdateError;
+use crate::packet_api::MeshPacketApi;
+use crate::state::DeviceKey;
+
+pub const DEFAULT_MQTT_PORT: u16 = 1883;
+pub const DEFAULT_MQTT_ROOT_TOPIC: &str = "msh/#";
+pub const DEFAULT_MQTT_PROXY_ROOT: &str = "msh";
+
+const MQTT_CHANNEL_CAPACITY: usize = 64;
+const MQTT_KEEP_ALIVE: Duration = Duration::from_secs(30);
+#[cfg(not(test))]
+const MQTT_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+#[cfg(test)]
+const MQTT_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MQTT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MqttBrokerConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub root_topic: Option<String>,
+}
+
+impl MqttBrokerConfig {
+    pub fn get_port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_MQTT_PORT)
+    }
+
+    pub fn get_root_topic(&self) -> String {
+        self.root_topic
+            .clone()
+            .unwrap_or_else(|| DEFAULT_MQTT_ROOT_TOPIC.into())
+    }
+
+    pub fn get_device_key(&self) -> DeviceKey {
+        format!(
+            "mqtt://{}:{}/{}",
+            self.host,
+            self.get_port(),
+            self.get_root_topic()
+        )
+    }
+
+    fn build_options(&self) -> MqttOptions {
+        let client_id = format!("meshtastic-nmc-{:08x}", generate_rand_id::<u32>());
+        let mut options = MqttOptions::new(client_id, self.host.clone(), self.get_port());
+
+        options.set_keep_alive(MQTT_KEEP_ALIVE);
+
+        if let Some(username) = self.username.clone() {
+            options.set_credentials(username, self.password.clone().unwrap_or_default());
+        }
+
+        options
+    }
+}
+
+/// A message received on a subscribed topic
+#[derive(Clone, Debug)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+pub struct MqttConnection {
+    pub client: AsyncClient,
+    pub event_loop_handle: JoinHandle<()>,
+}
+
+impl MqttConnection {
+    pub async fn disconnect(self) -> Result<(), String> {
+        let result = self.client.disconnect().await.map_err(|e| e.to_string());
+        self.event_loop_handle.abort();
+
+        result
+    }
+}
+
+/// Connects to an MQTT broker and subscribes to the given topics. Returns
+/// once the broker accepts the connection. The topics are subscribed to
+/// again whenever the connection is re-established.
+///
+/// # Arguments
+///
+/// * `config` - The broker to connect to.
+/// * `topics` - The topic filters to subscribe to.
+///
+/// # Returns
+///
+/// * `(MqttConnection, UnboundedReceiver<MqttMessage>)` - The connection and a
+///   listener that receives every message published on the subscribed topics.
+pub async fn connect_to_broker(
+    config: &MqttBrokerConfig,
+    topics: Vec<String>,
+) -> Result<(MqttConnection, UnboundedReceiver<MqttMessage>), String> {
+    debug!("Connecting to MQTT broker at {}", config.get_device_key());
+
+    let (client, mut event_loop) = AsyncClient::new(config.build_options(), MQTT_CHANNEL_CAPACITY);
+
+    tokio::time::timeout(MQTT_CONNECT_TIMEOUT, wait_for_connection(&mut event_loop))
+        .await
+        .map_err(|_| "Timed out connecting to MQTT broker".to_string())??;
+
+    subscribe_to_topics(&client, &topics)?;
+
+    let (message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+    let event_loop_handle = spawn_event_loop(event_loop, client.clone(), topics, message_tx);
+
+    Ok((
+        MqttConnection {
+            client,
+            event_loop_handle,
+        },
+        message_rx,
+    ))
+}
+
+/// Polls the event loop until the broker acknowledges the connection.
+async fn wait_for_connection(event_loop: &mut EventLoop) -> Result<(), String> {
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                debug!("Connected to MQTT broker");
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(e) => return Err(format!("Failed to connect to MQTT broker: {}", e)),
+        }
+    }
+}
+
+/// Queues subscriptions to the given topics. Sessions are clean, so the
+/// broker forgets subscriptions whenever the connection drops.
+fn subscribe_to_topics(client: &AsyncClient, topics: &[String]) -> Result<(), String> {
+    for topic in topics {
+        // Called from the event loop task, so this can't wait for the event
+        // loop to make room for the request
+        client
+            .try_subscribe(topic.clone(), QoS::AtMostOnce)
+            .map_err(|e| format!("Failed to subscribe to {}: {}", topic, e))?;
+    }
+
+    Ok(())
+}
+
+fn spawn_event_loop(
+    mut event_loop: EventLoop,
+    client: AsyncClient,
+    topics: Vec<String>,
+    message_tx: UnboundedSender<MqttMessage>,
+) -> JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match event_loop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    debug!("Reconnected to MQTT broker, subscribing to topics again");
+
+                    if let Err(e) = subscribe_to_topics(&client, &topics) {
+                        warn!("{}", e);
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    trace!("Received MQTT message on topic {}", publish.topic);
+
+                    let message = MqttMessage {
+                        topic: publish.topic,
+                        payload: publish.payload.to_vec(),
+                    };
+
+                    if message_tx.send(message).is_err() {
+                        debug!("MQTT message listener dropped, stopping event loop");
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // The event loop reconnects on the next poll
+                    warn!("MQTT connection error: {}", e);
+                    tokio::time::sleep(MQTT_RECONNECT_DELAY).await;
+                }
+            }
+        }
+    })
+}
+
+/// Decodes a `ServiceEnvelope` published by a Meshtastic gateway.
+///
+/// # Arguments
+///
+/// * `message` - The MQTT message to decode.
+///
+/// # Returns
+///
+/// * `Option<protobufs::ServiceEnvelope>` - The decoded envelope, `None` if the
+///   topic doesn't carry protobuf envelopes or the payload can't be decoded.
+pub fn decode_service_envelope(message: &MqttMessage) -> Option<protobufs::ServiceEnvelope> {
+    // JSON and status topics don't contain protobuf envelopes
+    if message.topic.contains("/json/") || message.topic.contains("/stat/") {
+        trace!("Skipping non-protobuf MQTT topic {}", message.topic);
+        return None;
+    }
+
+    match protobufs::ServiceEnvelope::decode(message.payload.as_slice()) {
+        Ok(envelope) => Some(envelope),
+        Err(e) => {
+            debug!(
+                "Failed to decode service envelope on topic {}: {}",
+                message.topic, e
+            );
+            None
+        }
+    }
+}
+
+/// Routes a message received from the broker through the mesh packet handlers.
+/// Messages that don't carry a mesh packet are skipped.
+pub fn handle_mqtt_message<R: tauri::Runtime>(
+    packet_api: &mut MeshPacketApi<R>,
+    message: &MqttMessage,
+) -> Result<(), DeviceUpdateError> {
+    let packet = match decode_service_envelope(message).and_then(|e| e.packet) {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+
+    trace!("Received packet from MQTT broker: {:?}", packet);
+
+    packet_api.handle_mesh_packet(packet)
+}
+
+/// Gets the topics a proxying radio expects to receive messages on, one per
+/// channel with downlink enabled.
+pub fn get_proxy_downlink_topics(device: &MeshDevice) -> Vec<String> {
+    let root = match device.module_config.mqtt.as_ref() {
+        Some(c) if !c.root.is_empty() => c.root.clone(),
+        _ => DEFAULT_MQTT_PROXY_ROOT.into(),
+    };
+
+    device
+        .channels
+        .values()
+        .filter_map(|channel| channel.config.settings.as_ref())
+        .filter(|settings| settings.downlink_enabled)
+        .map(|settings| {
+            let channel_name = if settings.name.is_empty() {
+                get_preset_channel_name(device)
+            } else {
+                settings.name.clone()
+            };
+
+            format!("{}/2/e/{}/#", root, channel_name)
+        })
+        .collect()
+}
+
+/// Wraps a message received from the broker so it can be sent to a proxying radio.
+pub fn build_proxy_message(message: MqttMessage) -> protobufs::MqttClientProxyMessage {
+    protobufs::MqttClientProxyMessage {
+        topic: message.topic,
+        payload_variant: Some(protobufs::mqtt_client_proxy_message::PayloadVariant::Data(
+            message.payload,
+        )),
+        retained: false,
+    }
+}
+
+/// Publishes a message sent by a proxying radio to the broker.
+pub async fn publish_proxy_message(
+    client: &AsyncClient,
+    message: protobufs::MqttClientProxyMessage,
+) -> Result<(), String> {
+    let payload = match message.payload_variant {
+        Some(protobufs::mqtt_client_proxy_message::PayloadVariant::Data(data)) => data,
+        Some(protobufs::mqtt_client_proxy_message::PayloadVariant::Text(text)) => text.into_bytes(),
+        None => return Err("Proxy message has no payload".into()),
+    };
+
+    client
+        .publish(message.topic, QoS::AtLeastOnce, message.retained, payload)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    const CONNECT: u8 = 0x10;
+    const SUBSCRIBE: u8 = 0x82;
+
+    // Reads an MQTT packet, returning its fixed header byte and its body
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+
+        let mut len = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            len |= usize::from(byte & 0x7F) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.unwrap();
+
+        (header, body)
+    }
+
+    /// Answers a client's CONNECT with `return_code`, then acknowledges its
+    /// subscription and publishes `payload` if the connection was accepted.
+    /// Returns the topic filter the client subscribed to.
+    async fn serve_session(
+        listener: &TcpListener,
+        return_code: u8,
+        payload: &[u8],
+    ) -> Option<String> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let (header, _) = read_packet(&mut stream).await;
+        assert_eq!(header, CONNECT);
+        stream
+            .write_all(&[0x20, 0x02, 0x00, return_code])
+            .await
+            .unwrap();
+
+        if return_code != 0 {
+            return None;
+        }
+
+        let (header, body) = read_packet(&mut stream).await;
+        assert_eq!(header, SUBSCRIBE);
+
+        let topic_len = usize::from(u16::from_be_bytes([body[2], body[3]]));
+        let topic = String::from_utf8(body[4..4 + topic_len].to_vec()).unwrap();
+
+        // Acknowledge the subscription with the packet id it was sent with
+        stream
+            .write_all(&[0x90, 0x03, body[0], body[1], 0x00])
+            .await
+            .unwrap();
+
+        let publish_topic = b"msh/US/2/e/LongFast/!1";
+        let mut publish = vec![0x30, (2 + publish_topic.len() + payload.len()) as u8];
+        publish.extend_from_slice(&(publish_topic.len() as u16).to_be_bytes());
+        publish.extend_from_slice(publish_topic);
+        publish.extend_from_slice(payload);
+        stream.write_all(&publish).await.unwrap();
+
+        // Dropping the stream simulates the broker going away
+        Some(topic)
+    }
+
+    async fn bind_broker() -> (TcpListener, MqttBrokerConfig) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = MqttBrokerConfig {
+            host: "127.0.0.1".into(),
+            port: Some(listener.local_addr().unwrap().port()),
+            username: None,
+            password: None,
+            root_topic: None,
+        };
+
+        (listener, config)
+    }
+
+    #[tokio::test]
+    async fn connects_and_subscribes() {
+        let (listener, config) = bind_broker().await;
+        let broker = tokio::spawn(async move { serve_session(&listener, 0, b"first").await });
+
+        let (connection, mut message_listener) =
+            connect_to_broker(&config, vec![config.get_root_topic()])
+                .await
+                .expect("Failed to connect to broker");
+
+        assert_eq!(broker.await.unwrap(), Some(DEFAULT_MQTT_ROOT_TOPIC.into()));
+
+        let message = message_listener.recv().await.expect("No message received");
+        assert_eq!(message.topic, "msh/US/2/e/LongFast/!1");
+        assert_eq!(message.payload, b"first");
+
+        connection.event_loop_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn subscribes_again_after_reconnecting() {
+        let (listener, config) = bind_broker().await;
+        let broker = tokio::spawn(async move {
+            let first = serve_session(&listener, 0, b"first").await;
+            let second = serve_session(&listener, 0, b"second").await;
+            (first, second)
+        });
+
+        let (connection, mut message_listener) =
+            connect_to_broker(&config, vec![config.get_root_topic()])
+                .await
+                .expect("Failed to connect to broker");
+
+        let message = message_listener.recv().await.expect("No message received");
+        assert_eq!(message.payload, b"first");
+
+        // The broker only publishes after the client subscribes again
+        let message = tokio::time::timeout(Duration::from_secs(5), message_listener.recv())
+            .await
+            .expect("Timed out waiting for the client to subscribe again")
+            .expect("No message received");
+        assert_eq!(message.payload, b"second");
+
+        let (first, second) = broker.await.unwrap();
+        assert_eq!(first, second);
+
+        connection.event_loop_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn reports_refused_connection() {
+        let (listener, config) = bind_broker().await;
+        let broker = tokio::spawn(async move { serve_session(&listener, 0x05, b"").await });
+
+        // Not authorized
+        assert!(connect_to_broker(&config, vec![config.get_root_topic()])
+            .await
+            .is_err());
+
+        broker.await.unwrap();
+    }
+
+    fn build_message(topic: &str, envelope: protobufs::ServiceEnvelope) -> MqttMessage {
+        MqttMessage {
+            topic: topic.into(),
+            payload: envelope.encode_to_vec(),
+        }
+    }
+
+    #[test]
+    fn decode_envelope_packet() {
+        let envelope = protobufs::ServiceEnvelope {
+            packet: Some(protobufs::MeshPacket {
+                id: 1234,
+                from: 5678,
+                ..Default::default()
+            }),
+            channel_id: "LongFast".into(),
+            gateway_id: "!0000162e".into(),
+        };
+
+        let message = build_message("msh/US/2/e/LongFast/!0000162e", envelope);
+        let decoded = decode_service_envelope(&message).expect("Failed to decode envelope");
+        let packet = decoded.packet.expect("Envelope has no packet");
+
+        assert_eq!(packet.id, 1234);
+        assert_eq!(packet.from, 5678);
+        assert_eq!(decoded.channel_id, "LongFast");
+    }
+
+    #[test]
+    fn proxy_downlink_topics() {
+        let mut device = MeshDevice::new();
+
+        for (index, downlink_enabled) in [(0, true), (1, false)] {
+            device.add_channel(crate::device::MeshChannel {
+                config: protobufs::Channel {
+                    index,
+                    role: protobufs::channel::Role::Secondary as i32,
+                    settings: Some(protobufs::ChannelSettings {
+                        name: format!("ch{}", index),
+                        downlink_enabled,
+                        ..Default::default()
+                    }),
+                },
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(get_proxy_downlink_topics(&device), vec!["msh/2/e/ch0/#"]);
+    }
+
+    #[test]
+    fn skip_json_topic() {
+        let message = MqttMessage {
+            topic: "msh/US/2/json/LongFast/!0000162e".into(),
+            payload: b"{}".to_vec(),
+        };
+
+        assert!(decode_service_envelope(&message).is_none());
+    }
+
+    #[test]
+    fn routes_envelopes_through_packet_handlers() {
+        let app = tauri::test::mock_app();
+        let mut packet_api = MeshPacketApi::new(
+            app.handle(),
+            "mqtt://localhost:1883/msh/#".into(),
+            MeshDevice::new(),
+            std::sync::Arc::new(std::sync::Mutex::new(
+                crate::graph::ds::graph::MeshGraph::new(),
+            )),
+            std::sync::Arc::new(std::sync::Mutex::new(
+                crate::geofence::GeofenceManager::new(),
+            )),
+        );
+
+        let user = protobufs::User {
+            long_name: "Gateway".into(),
+            ..Default::default()
+        };
+
+        let envelope = protobufs::ServiceEnvelope {
+            packet: Some(protobufs::MeshPacket {
+                id: 42,
+                from: 1,
+                payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                    protobufs::Data {
+                        portnum: protobufs::PortNum::NodeinfoApp as i32,
+                        payload: user.encode_to_vec(),
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Status topics are skipped before their payload is decoded
+        let status = build_message("msh/EU_868/2/stat/!2", envelope.clone());
+        handle_mqtt_message(&mut packet_api, &status).expect("Failed to skip status message");
+        assert!(packet_api.device.nodes.is_empty());
+
+        let message = build_message("msh/EU_868/2/e/LongFast/!1", envelope);
+        handle_mqtt_message(&mut packet_api, &message).expect("Failed to handle message");
+
+        let node = packet_api.device.nodes.get(&1).expect("Node not added");
+        assert_eq!(
+            node.user.as_ref().map(|u| u.long_name.as_str()),
+            Some("Gateway")
+        );
+    }
+}
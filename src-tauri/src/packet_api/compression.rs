@@ -0,0 +1,844 @@
+// Text compression for `TextMessageCompressedApp` packets. The bit stream
+// follows Unishox2 with its default preset, which is what the firmware links
+// against. Decompression handles the full format, while compression only
+// uses the character set, case, number and unicode codes, so its output can
+// be longer than the reference implementation's. The fixed vectors in the
+// tests pin down the encoding of each kind of code.
+
+const ALPHA: usize = 0;
+const SYM: usize = 1;
+const NUM: usize = 2;
+const DICT: usize = 3;
+const DELTA: usize = 4;
+
+const MAGIC_BIT_LEN: usize = 1; // set so compressed data can't be mistaken for ASCII
+const SWITCH_CODE: u32 = 0b00;
+const SWITCH_CODE_LEN: usize = 2;
+const UNI_SPECIAL_CODE: u32 = 0b11111;
+const UNI_SPECIAL_CODE_LEN: usize = 5;
+const UNI_SWITCH_CODE: u32 = 0b10;
+const UNI_SWITCH_CODE_LEN: usize = 2;
+
+const CONTINUOUS_DELTA_CODE: usize = 1; // upper case space
+const CRLF_CODE: usize = 8;
+const REPEAT_CODE: usize = 26;
+const TERM_CODE: usize = 27;
+const NICE_LEN: usize = 5; // back references are at least this long
+
+// Text messages are at most a couple of hundred bytes, so anything much
+// longer is a malformed or malicious packet
+const MAX_DECOMPRESSED_LEN: usize = 4096;
+
+const SETS: [[u8; 28]; 3] = [
+    [
+        0, b' ', b'e', b't', b'a', b'o', b'i', b'n', b's', b'r', b'l', b'c', b'd', b'h', b'u',
+        b'p', b'm', b'b', b'g', b'w', b'f', b'y', b'v', b'k', b'q', b'j', b'x', b'z',
+    ],
+    [
+        b'"', b'{', b'}', b'_', b'<', b'>', b':', b'\n', 0, b'[', b']', b'\\', b';', b'\'', b'\t',
+        b'@', b'*', b'&', b'?', b'!', b'^', b'|', b'\r', b'~', b'`', 0, 0, 0,
+    ],
+    [
+        0, b',', b'.', b'0', b'1', b'9', b'2', b'5', b'-', b'/', b'3', b'4', b'6', b'7', b'8',
+        b'(', b')', b' ', b'=', b'+', b'$', b'%', b'#', 0, 0, 0, 0, 0,
+    ],
+];
+
+// Codes for the position of a character within a set, MSB aligned
+const VCODES: [u8; 28] = [
+    0x00, 0x40, 0x60, 0x80, 0x90, 0xA0, 0xB0, 0xC0, 0xD0, 0xD8, 0xE0, 0xE4, 0xE8, 0xEC, 0xEE, 0xF0,
+    0xF2, 0xF4, 0xF6, 0xF7, 0xF8, 0xF9, 0xFA, 0xFB, 0xFC, 0xFD, 0xFE, 0xFF,
+];
+const VCODE_LENS: [usize; 28] = [
+    2, 3, 3, 4, 4, 4, 4, 4, 5, 5, 6, 6, 6, 7, 7, 7, 7, 7, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8,
+];
+
+// Codes for switching between sets, MSB aligned
+const HCODES: [u8; 5] = [0x00, 0x40, 0x80, 0xC0, 0xE0];
+const HCODE_LENS: [usize; 5] = [2, 2, 2, 3, 3];
+
+const FREQUENT_SEQUENCES: [&str; 6] = ["\": \"", "\": ", "</", "=\"", "\":\"", "://"];
+const TEMPLATES: [&str; 4] = [
+    "tfff-of-tfTtf:rf:rf.fffZ",
+    "tfff-of-tf",
+    "(fff) fff-ffff",
+    "tf:rf:rf",
+];
+
+const COUNT_BIT_LENS: [usize; 5] = [2, 4, 7, 11, 16];
+const COUNT_ADDERS: [u32; 5] = [4, 20, 148, 2196, 67732];
+const UNI_BIT_LENS: [usize; 5] = [6, 12, 14, 16, 21];
+const UNI_ADDERS: [u32; 5] = [0, 64, 4160, 20544, 86080];
+
+enum UnicodeCode {
+    Delta(i32),
+    Special(usize), // space, switch, comma, full stop or newline
+}
+
+struct BitReader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    len: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: MAGIC_BIT_LEN,
+            len: input.len() * 8,
+        }
+    }
+
+    fn has_bits(&self) -> bool {
+        self.pos < self.len
+    }
+
+    fn bit_at(&self, pos: usize) -> bool {
+        pos < self.len && self.input[pos / 8] & (0x80 >> (pos % 8)) != 0
+    }
+
+    // Missing bits past the end of the input read as zeros
+    fn peek_byte(&self) -> u8 {
+        (0..8).fold(0, |acc, i| (acc << 1) | u8::from(self.bit_at(self.pos + i)))
+    }
+
+    fn read_bits(&mut self, count: usize) -> Option<u32> {
+        if self.pos + count > self.len {
+            return None;
+        }
+
+        let value = (0..count).fold(0, |acc, i| {
+            (acc << 1) | u32::from(self.bit_at(self.pos + i))
+        });
+        self.pos += count;
+
+        Some(value)
+    }
+
+    fn read_prefix_code(&mut self, codes: &[u8], code_lens: &[usize]) -> Option<usize> {
+        if !self.has_bits() {
+            return None;
+        }
+
+        let byte = self.peek_byte();
+        let index = codes
+            .iter()
+            .zip(code_lens)
+            .position(|(code, len)| byte & (0xFF00_u16 >> len) as u8 == *code)?;
+
+        if self.pos + code_lens[index] > self.len {
+            return None;
+        }
+
+        self.pos += code_lens[index];
+
+        Some(index)
+    }
+
+    fn read_vcode(&mut self) -> Option<usize> {
+        self.read_prefix_code(&VCODES, &VCODE_LENS)
+    }
+
+    fn read_hcode(&mut self) -> Option<usize> {
+        self.read_prefix_code(&HCODES, &HCODE_LENS)
+    }
+
+    // Counts set bits up to a terminating zero, or up to `limit` set bits
+    fn read_step_code(&mut self, limit: usize) -> Option<usize> {
+        let mut index = 0;
+
+        while self.bit_at(self.pos) {
+            index += 1;
+            self.pos += 1;
+
+            if index == limit {
+                return Some(index);
+            }
+        }
+
+        if !self.has_bits() {
+            return None;
+        }
+
+        self.pos += 1;
+
+        Some(index)
+    }
+
+    fn read_count(&mut self) -> Option<u32> {
+        let index = self.read_step_code(4)?;
+        let value = self.read_bits(COUNT_BIT_LENS[index])?;
+
+        Some(
+            value
+                + if index > 0 {
+                    COUNT_ADDERS[index - 1]
+                } else {
+                    0
+                },
+        )
+    }
+
+    fn read_unicode(&mut self) -> Option<UnicodeCode> {
+        let index = self.read_step_code(5)?;
+
+        if index == 5 {
+            return self.read_step_code(4).map(UnicodeCode::Special);
+        }
+
+        let is_negative = self.read_bits(1)? == 1;
+        let magnitude = (self.read_bits(UNI_BIT_LENS[index])? + UNI_ADDERS[index]) as i32;
+
+        Some(UnicodeCode::Delta(if is_negative {
+            -magnitude
+        } else {
+            magnitude
+        }))
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    output: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    // Appends the lowest `count` bits of `value`, most significant first
+    fn push_bits(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len == self.output.len() * 8 {
+                self.output.push(0);
+            }
+
+            if value & (1 << i) != 0 {
+                let last = self.output.len() - 1;
+                self.output[last] |= 0x80 >> (self.len % 8);
+            }
+
+            self.len += 1;
+        }
+    }
+
+    fn push_vcode(&mut self, index: usize) {
+        let len = VCODE_LENS[index];
+        self.push_bits(u32::from(VCODES[index]) >> (8 - len), len);
+    }
+
+    fn push_hcode(&mut self, index: usize) {
+        let len = HCODE_LENS[index];
+        self.push_bits(u32::from(HCODES[index]) >> (8 - len), len);
+    }
+
+    fn push_switch(&mut self, set: usize) {
+        self.push_bits(SWITCH_CODE, SWITCH_CODE_LEN);
+        self.push_hcode(set);
+    }
+
+    fn push_step_code(&mut self, index: usize, limit: usize) {
+        self.push_bits((1 << index) - 1, index);
+
+        if index < limit {
+            self.push_bits(0, 1);
+        }
+    }
+
+    fn push_count(&mut self, count: u32) {
+        let index = COUNT_ADDERS
+            .iter()
+            .position(|adder| count < *adder)
+            .unwrap_or(COUNT_ADDERS.len() - 1);
+
+        self.push_step_code(index, 4);
+        self.push_bits(
+            count
+                - if index > 0 {
+                    COUNT_ADDERS[index - 1]
+                } else {
+                    0
+                },
+            COUNT_BIT_LENS[index],
+        );
+    }
+
+    fn push_unicode(&mut self, code: u32, previous: u32) {
+        let magnitude = code.abs_diff(previous);
+        let index = UNI_ADDERS
+            .iter()
+            .rposition(|adder| magnitude >= *adder)
+            .unwrap_or(0);
+
+        self.push_step_code(index, 5);
+        self.push_bits(u32::from(code < previous), 1);
+        self.push_bits(magnitude - UNI_ADDERS[index], UNI_BIT_LENS[index]);
+    }
+
+    fn push_unicode_special(&mut self, index: usize) {
+        self.push_bits(UNI_SPECIAL_CODE, UNI_SPECIAL_CODE_LEN);
+        self.push_step_code(index, 4);
+    }
+}
+
+fn push_code_point(output: &mut Vec<u8>, code: i32) -> Result<(), String> {
+    let c = u32::try_from(code)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(format!("Invalid code point {}", code))?;
+
+    output.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+
+    Ok(())
+}
+
+fn hex_digit(nibble: u32, upper_case: bool) -> u8 {
+    match nibble {
+        0..=9 => b'0' + nibble as u8,
+        _ if upper_case => b'A' + nibble as u8 - 10,
+        _ => b'a' + nibble as u8 - 10,
+    }
+}
+
+// Copies text that already appeared earlier in the output
+fn decode_back_reference(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<bool, String> {
+    let (len, distance) = match (reader.read_count(), reader.read_count()) {
+        (Some(len), Some(distance)) => (len as usize + NICE_LEN, distance as usize + NICE_LEN - 1),
+        _ => return Ok(false),
+    };
+
+    // References never overlap the text they're copied to
+    let start = output
+        .len()
+        .checked_sub(distance)
+        .filter(|_| len <= distance)
+        .ok_or("Invalid back reference")?;
+
+    output.extend_from_within(start..start + len);
+
+    Ok(true)
+}
+
+// Decodes templates, hex strings and raw bytes, which follow a switch to the
+// number set and its switch code
+fn decode_number_special(reader: &mut BitReader, output: &mut Vec<u8>) -> Option<()> {
+    match reader.read_step_code(5)? {
+        0 => {
+            let template = TEMPLATES.get(reader.read_step_code(4)?)?;
+            let omitted = reader.read_count()? as usize;
+            let kept = template.len().checked_sub(omitted)?;
+
+            for c in template.bytes().take(kept) {
+                let nibble_len = match c {
+                    b'f' | b'F' => 4,
+                    b'r' => 3,
+                    b't' => 2,
+                    b'o' => 1,
+                    _ => {
+                        output.push(c);
+                        continue;
+                    }
+                };
+
+                output.push(hex_digit(reader.read_bits(nibble_len)?, c == b'F'));
+            }
+        }
+        5 => {
+            let count = reader.read_count().filter(|count| *count > 0)?;
+
+            for _ in 0..count {
+                output.push(reader.read_bits(8)? as u8);
+            }
+        }
+        index => {
+            let is_uuid = index == 2 || index == 4;
+            let upper_case = index >= 3;
+            let count = if is_uuid {
+                32
+            } else {
+                reader.read_count().filter(|count| *count > 0)?
+            };
+
+            for i in 0..count {
+                output.push(hex_digit(reader.read_bits(4)?, upper_case));
+
+                if is_uuid && matches!(i, 7 | 11 | 15 | 19) {
+                    output.push(b'-');
+                }
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Decompresses a Unishox2 compressed payload.
+///
+/// # Arguments
+///
+/// * `input` - The compressed payload.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, String>` - The decompressed payload, or an error if it
+///   refers to text that doesn't exist or decompresses to more than
+///   `MAX_DECOMPRESSED_LEN` bytes.
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader::new(input);
+    let mut output = vec![];
+
+    let mut state = ALPHA; // set characters are read from until the next switch
+    let mut set = ALPHA; // set the next character is read from
+    let mut is_all_upper = false;
+    let mut previous_code_point = 0;
+
+    while reader.has_bits() {
+        if output.len() > MAX_DECOMPRESSED_LEN {
+            return Err(format!(
+                "Decompressed text is longer than {} bytes",
+                MAX_DECOMPRESSED_LEN
+            ));
+        }
+
+        if state == DELTA || set == DELTA {
+            if state != DELTA {
+                set = state;
+            }
+
+            match reader.read_unicode() {
+                Some(UnicodeCode::Delta(delta)) => {
+                    previous_code_point += delta;
+                    push_code_point(&mut output, previous_code_point)?;
+                }
+                Some(UnicodeCode::Special(1)) => {
+                    set = match reader.read_hcode() {
+                        Some(set) => set,
+                        None => break,
+                    };
+
+                    if set == DELTA || set == ALPHA {
+                        state = set;
+                        continue;
+                    }
+
+                    if set == DICT {
+                        if !decode_back_reference(&mut reader, &mut output)? {
+                            break;
+                        }
+
+                        set = state;
+                        continue;
+                    }
+                }
+                Some(UnicodeCode::Special(index)) => {
+                    output.push([b' ', b' ', b',', b'.', b'\n'][index]);
+                    continue;
+                }
+                None => break,
+            }
+
+            if state == DELTA && set == DELTA {
+                continue;
+            }
+        } else {
+            set = state;
+        }
+
+        let mut is_upper = is_all_upper;
+        let mut code = match reader.read_vcode() {
+            Some(code) => code,
+            None => break,
+        };
+
+        // The first code of the alpha and number sets switches sets
+        if code == 0 && set != SYM {
+            if !reader.has_bits() {
+                break;
+            }
+
+            if set != NUM || state != DELTA {
+                set = match reader.read_hcode() {
+                    Some(set) if reader.has_bits() => set,
+                    _ => break,
+                };
+            }
+
+            match set {
+                ALPHA if state != ALPHA => {
+                    state = ALPHA;
+                    continue;
+                }
+                ALPHA if is_all_upper => {
+                    is_all_upper = false;
+                    continue;
+                }
+                ALPHA => {
+                    code = match reader.read_vcode() {
+                        Some(code) => code,
+                        None => break,
+                    };
+
+                    if code == 0 {
+                        code = match reader.read_vcode() {
+                            Some(code) => code,
+                            None => break,
+                        };
+
+                        if code == 0 {
+                            is_all_upper = true;
+                            continue;
+                        }
+                    }
+
+                    is_upper = true;
+                }
+                DICT => {
+                    if !decode_back_reference(&mut reader, &mut output)? {
+                        break;
+                    }
+
+                    continue;
+                }
+                DELTA => continue,
+                _ => {
+                    if set != NUM || state != DELTA {
+                        code = match reader.read_vcode() {
+                            Some(code) => code,
+                            None => break,
+                        };
+                    }
+
+                    if set == NUM && code == 0 {
+                        if decode_number_special(&mut reader, &mut output).is_none() {
+                            break;
+                        }
+
+                        continue;
+                    }
+                }
+            }
+        }
+
+        if is_upper && code == CONTINUOUS_DELTA_CODE {
+            state = DELTA;
+            set = DELTA;
+            continue;
+        }
+
+        let c = SETS[set][code];
+
+        if c == 0 {
+            match (set, code) {
+                (SYM, CRLF_CODE) => output.extend_from_slice(b"\r\n"),
+                (SYM, 25..=27) => {
+                    output.extend_from_slice(FREQUENT_SEQUENCES[code - 25].as_bytes())
+                }
+                (NUM, 23..=25) => {
+                    output.extend_from_slice(FREQUENT_SEQUENCES[code - 20].as_bytes())
+                }
+                (NUM, REPEAT_CODE) => {
+                    let count = match reader.read_count() {
+                        Some(count) => count + 4,
+                        None => break,
+                    };
+                    let repeated = *output.last().ok_or("Repeat without a previous character")?;
+
+                    // Checked up front, since a single repeat can be tens of thousands long
+                    if output.len() + count as usize > MAX_DECOMPRESSED_LEN {
+                        return Err(format!(
+                            "Decompressed text is longer than {} bytes",
+                            MAX_DECOMPRESSED_LEN
+                        ));
+                    }
+
+                    output.resize(output.len() + count as usize, repeated);
+                }
+                _ => break, // terminator
+            }
+        } else if c.is_ascii_lowercase() {
+            state = ALPHA;
+            output.push(if is_upper { c.to_ascii_uppercase() } else { c });
+        } else {
+            if c.is_ascii_digit() && state != DELTA {
+                state = NUM;
+            }
+
+            output.push(c);
+        }
+
+        if state == DELTA {
+            set = DELTA;
+        }
+    }
+
+    Ok(output)
+}
+
+fn find_in_set(set: usize, c: u8) -> Option<usize> {
+    SETS[set].iter().position(|s| *s == c && c != 0)
+}
+
+/// Compresses text with Unishox2. Short or unusual text can come out longer
+/// than it went in, so callers should compare the lengths.
+pub fn compress(text: &str) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    let mut state = ALPHA;
+    let mut previous_code_point = 0;
+
+    writer.push_bits(1, MAGIC_BIT_LEN);
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+
+        if state == DELTA {
+            if !c.is_ascii() {
+                writer.push_unicode(c as u32, previous_code_point);
+                previous_code_point = c as u32;
+                continue;
+            }
+
+            let special = match c {
+                ' ' => Some(0),
+                ',' => Some(2),
+                '.' => Some(3),
+                '\n' => Some(4),
+                _ => None,
+            };
+
+            if let Some(index) = special {
+                writer.push_unicode_special(index);
+                continue;
+            }
+
+            writer.push_bits(UNI_SPECIAL_CODE, UNI_SPECIAL_CODE_LEN);
+            writer.push_bits(UNI_SWITCH_CODE, UNI_SWITCH_CODE_LEN);
+            writer.push_hcode(ALPHA);
+            state = ALPHA;
+        }
+
+        if !c.is_ascii() {
+            // Runs of non-ASCII text stay in delta coding instead of
+            // switching back after every character
+            if chars.get(i).map_or(false, |next| !next.is_ascii()) {
+                if state != ALPHA {
+                    writer.push_switch(ALPHA);
+                }
+
+                writer.push_switch(ALPHA);
+                writer.push_vcode(CONTINUOUS_DELTA_CODE);
+                state = DELTA;
+            } else {
+                writer.push_switch(DELTA);
+            }
+
+            writer.push_unicode(c as u32, previous_code_point);
+            previous_code_point = c as u32;
+            continue;
+        }
+
+        let c = c as u8;
+
+        if c == b'\r' && chars.get(i) == Some(&'\n') {
+            writer.push_switch(SYM);
+            writer.push_vcode(CRLF_CODE);
+            i += 1;
+        } else if c.is_ascii_uppercase() {
+            if state != ALPHA {
+                writer.push_switch(ALPHA);
+                state = ALPHA;
+            }
+
+            writer.push_switch(ALPHA);
+            writer.push_vcode(find_in_set(ALPHA, c.to_ascii_lowercase()).unwrap_or_default());
+        } else if let Some(code) = find_in_set(state, c) {
+            writer.push_vcode(code);
+        } else if let Some(code) = find_in_set(ALPHA, c) {
+            writer.push_switch(ALPHA);
+            writer.push_vcode(code);
+            state = ALPHA;
+        } else if let Some(code) = find_in_set(NUM, c) {
+            writer.push_switch(NUM);
+            writer.push_vcode(code);
+
+            if c.is_ascii_digit() {
+                state = NUM;
+            }
+        } else if let Some(code) = find_in_set(SYM, c) {
+            writer.push_switch(SYM);
+            writer.push_vcode(code);
+        } else {
+            // Control characters are sent as raw bytes
+            writer.push_switch(NUM);
+            writer.push_vcode(0);
+            writer.push_step_code(5, 5);
+            writer.push_count(1);
+            writer.push_bits(u32::from(c), 8);
+        }
+    }
+
+    // Only the part of the terminator that fits in the last byte is kept,
+    // the end of the input terminates the text otherwise
+    let len = writer.output.len();
+
+    if writer.len < len * 8 {
+        if state == DELTA {
+            writer.push_bits(UNI_SPECIAL_CODE, UNI_SPECIAL_CODE_LEN);
+            writer.push_bits(UNI_SWITCH_CODE, UNI_SWITCH_CODE_LEN);
+            writer.push_hcode(NUM);
+        } else if state != NUM {
+            writer.push_switch(NUM);
+        }
+
+        writer.push_vcode(TERM_CODE);
+    }
+
+    writer.output.truncate(len);
+    writer.output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_text() {
+        let texts = [
+            "",
+            "Hello, World!",
+            "Meet at 10:30 by the north gate, bring 2 radios",
+            "ALL CAPS and MixedCase",
+            "Line one\r\nLine two\n\tindented \"quoted\" {json: [1, 2]}",
+            "Temperature 21.5°C",
+            "Καλημέρα κόσμε, 日本語のテキスト.",
+            "Thumbs up 👍🙂 done",
+            "bell\u{7}",
+        ];
+
+        for text in texts {
+            let compressed = compress(text);
+            let decompressed = decompress(&compressed).unwrap();
+
+            assert_eq!(String::from_utf8(decompressed).unwrap(), text);
+        }
+    }
+
+    // Hand-encoded from the default preset's code tables. Bits past the end
+    // of the text hold as much of the terminator as fits in the last byte.
+    const VECTORS: [(&str, &[u8]); 4] = [
+        // magic, h, e, l, l, o, switch to numbers, terminator
+        ("hello", &[0xF6, 0x7C, 0x71, 0x45]),
+        // magic, switch to upper case, h, i
+        ("Hi", &[0x87, 0x6B]),
+        // magic, switch to numbers, 4, 2, terminator
+        ("42", &[0x97, 0x37]),
+        // magic, switch to unicode, delta of 233, switch to numbers, terminator
+        ("é", &[0x9E, 0x05, 0x49]),
+    ];
+
+    #[test]
+    fn matches_fixed_vectors() {
+        for (text, compressed) in VECTORS {
+            assert_eq!(
+                compress(text),
+                compressed.to_vec(),
+                "compressing {:?}",
+                text
+            );
+            assert_eq!(
+                decompress(compressed).unwrap(),
+                text.as_bytes().to_vec(),
+                "decompressing {:?}",
+                text
+            );
+        }
+    }
+
+    // Hand-encoded like `VECTORS`, but with back references, which the
+    // compressor here doesn't produce, so these only decompress
+    const BACK_REFERENCE_VECTORS: [(&str, &[u8]); 2] = [
+        // magic, "hello ", copy 5 characters from 6 back, switch to numbers, terminator
+        ("hello hello", &[0xF6, 0x7C, 0x71, 0x48, 0xC1, 0x17]),
+        // magic, "hello world ", copy 5 characters from 12 back, switch to numbers
+        (
+            "hello world hello",
+            &[0xF6, 0x7C, 0x71, 0x4B, 0xDE, 0xB7, 0xC7, 0x48, 0xC2, 0x42],
+        ),
+    ];
+
+    #[test]
+    fn decompresses_back_reference_vectors() {
+        for (text, compressed) in BACK_REFERENCE_VECTORS {
+            assert_eq!(
+                decompress(compressed).unwrap(),
+                text.as_bytes().to_vec(),
+                "decompressing {:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_output() {
+        let mut writer = BitWriter::default();
+        writer.push_bits(1, MAGIC_BIT_LEN);
+        writer.push_vcode(find_in_set(ALPHA, b'a').unwrap());
+
+        // A single repeat code that would expand to tens of kilobytes
+        writer.push_switch(NUM);
+        writer.push_vcode(REPEAT_CODE);
+        writer.push_count(60_000);
+
+        assert!(decompress(&writer.output).is_err());
+
+        // Back references can't grow the text past the limit either
+        let mut writer = BitWriter::default();
+        writer.push_bits(1, MAGIC_BIT_LEN);
+
+        for c in "abcdefgh ".bytes() {
+            writer.push_vcode(find_in_set(ALPHA, c).unwrap());
+        }
+
+        for _ in 0..1_000 {
+            writer.push_switch(DICT);
+            writer.push_count(8 - NICE_LEN as u32);
+            writer.push_count(9 - NICE_LEN as u32 + 1);
+        }
+
+        assert!(decompress(&writer.output).is_err());
+    }
+
+    #[test]
+    fn shortens_english_text() {
+        let text = "heading back to the trailhead now, see you at camp";
+
+        assert!(compress(text).len() < text.len());
+    }
+
+    #[test]
+    fn decodes_back_references_and_repeats() {
+        let mut writer = BitWriter::default();
+        writer.push_bits(1, MAGIC_BIT_LEN);
+
+        for c in "abcdefgh ".bytes() {
+            writer.push_vcode(find_in_set(ALPHA, c).unwrap());
+        }
+
+        // Copy the 8 characters that start 9 characters back
+        writer.push_switch(DICT);
+        writer.push_count(8 - NICE_LEN as u32);
+        writer.push_count(9 - NICE_LEN as u32 + 1);
+
+        // Repeat the last character 5 more times
+        writer.push_switch(NUM);
+        writer.push_vcode(REPEAT_CODE);
+        writer.push_count(5 - 4);
+
+        assert_eq!(
+            decompress(&writer.output).unwrap(),
+            b"abcdefgh abcdefghhhhhh".to_vec()
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use aes::{Aes128, Aes256};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use meshtastic::protobufs;
+use meshtastic::Message;
+
+use crate::device::MeshDevice;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+/// The firmware's built-in default channel key, selected by single-byte PSKs
+pub const DEFAULT_CHANNEL_KEY: [u8; 16] = [
+    0xd4, 0xf1, 0xbb, 0x3a, 0x20, 0x29, 0x07, 0x59, 0xf0, 0xbc, 0xff, 0xab, 0xcf, 0x4e, 0x69, 0x01,
+];
+
+#[derive(Clone, Debug)]
+pub struct ChannelKey {
+    pub channel_index: u32,
+    pub channel_hash: u32,
+    pub key: Vec<u8>, // empty if the channel is unencrypted
+}
+
+/// Expands a channel PSK as stored in the channel settings into the AES key
+/// used on the air, mirroring the firmware's key expansion rules.
+///
+/// # Arguments
+///
+/// * `psk` - The PSK stored in the channel settings.
+///
+/// # Returns
+///
+/// * `Vec<u8>` - The expanded key, empty if the channel is unencrypted.
+pub fn expand_channel_psk(psk: &[u8]) -> Vec<u8> {
+    match psk.len() {
+        0 => vec![],
+        1 => {
+            let index = psk[0];
+
+            if index == 0 {
+                return vec![];
+            }
+
+            let mut key = DEFAULT_CHANNEL_KEY.to_vec();
+            let last = key.len() - 1;
+            key[last] = key[last].wrapping_add(index - 1);
+            key
+        }
+        len if len <= 16 => {
+            let mut key = psk.to_vec();
+            key.resize(16, 0);
+            key
+        }
+        _ => {
+            let mut key = psk.to_vec();
+            key.resize(32, 0);
+            key
+        }
+    }
+}
+
+/// Computes the one-byte channel hash sent in the `channel` field of
+/// encrypted packets.
+///
+/// # Arguments
+///
+/// * `channel_name` - The name of the channel, or its preset name if unnamed.
+/// * `key` - The expanded channel key.
+///
+/// # Returns
+///
+/// * `u32` - The channel hash.
+pub fn compute_channel_hash(channel_name: &str, key: &[u8]) -> u32 {
+    let name_hash = channel_name.bytes().fold(0u8, |acc, b| acc ^ b);
+    let key_hash = key.iter().fold(0u8, |acc, b| acc ^ b);
+
+    (name_hash ^ key_hash).into()
+}
+
+/// Encrypts or decrypts a packet payload. AES-CTR is symmetric, so the same
+/// operation is used in both directions.
+///
+/// # Arguments
+///
+/// * `key` - The expanded channel key, empty for unencrypted channels.
+/// * `packet_id` - The id of the packet, used in the nonce.
+/// * `from` - The node number of the packet sender, used in the nonce.
+/// * `payload` - The payload bytes to transform.
+///
+/// # Returns
+///
+/// * `Option<Vec<u8>>` - The transformed payload, `None` if the key length is invalid.
+pub fn apply_channel_cipher(
+    key: &[u8],
+    packet_id: u32,
+    from: u32,
+    payload: &[u8],
+) -> Option<Vec<u8>> {
+    let mut nonce = [0u8; 16];
+    nonce[0..8].copy_from_slice(&(packet_id as u64).to_le_bytes());
+    nonce[8..12].copy_from_slice(&from.to_le_bytes());
+
+    let mut buffer = payload.to_vec();
+
+    match key.len() {
+        0 => {}
+        16 => Aes128Ctr::new_from_slices(key, &nonce)
+            .ok()?
+            .apply_keystream(&mut buffer),
+        32 => Aes256Ctr::new_from_slices(key, &nonce)
+            .ok()?
+            .apply_keystream(&mut buffer),
+        _ => return None,
+    }
+
+    Some(buffer)
+}
+
+/// Gets the name the firmware uses for unnamed channels, based on the LoRa modem preset.
+pub fn get_preset_channel_name(device: &MeshDevice) -> String {
+    let lora_config = match device.config.lora.as_ref() {
+        Some(c) => c,
+        None => return "LongFast".into(),
+    };
+
+    if !lora_config.use_preset {
+        return "Custom".into();
+    }
+
+    let preset_name =
+        protobufs::config::lo_ra_config::ModemPreset::from_i32(lora_config.modem_preset)
+            .map(|p| p.as_str_name())
+            .unwrap_or_default();
+
+    match preset_name {
+        "LONG_FAST" => "LongFast",
+        "LONG_SLOW" => "LongSlow",
+        "VERY_LONG_SLOW" => "VLongSlow",
+        "MEDIUM_SLOW" => "MediumSlow",
+        "MEDIUM_FAST" => "MediumFast",
+        "SHORT_SLOW" => "ShortSlow",
+        "SHORT_FAST" => "ShortFast",
+        "LONG_MODERATE" => "LongMod",
+        _ => "Invalid",
+    }
+    .into()
+}
+
+/// Collects the keys of all configured channels on a device along with the
+/// channel hashes they produce on the air.
+pub fn get_device_channel_keys(device: &MeshDevice) -> Vec<ChannelKey> {
+    let mut channel_keys = vec![];
+
+    for (channel_index, channel) in device.channels.iter() {
+        if channel.config.role == protobufs::channel::Role::Disabled as i32 {
+            continue;
+        }
+
+        let settings = match channel.config.settings.as_ref() {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let channel_name = if settings.name.is_empty() {
+            get_preset_channel_name(device)
+        } else {
+            settings.name.clone()
+        };
+
+        let key = expand_channel_psk(&settings.psk);
+
+        channel_keys.push(ChannelKey {
+            channel_index: *channel_index,
+            channel_hash: compute_channel_hash(&channel_name, &key),
+            key,
+        });
+    }
+
+    channel_keys
+}
+
+/// Attempts to decrypt an encrypted packet payload with any known channel
+/// key whose hash matches the packet's channel hash.
+///
+/// # Returns
+///
+/// * `Option<(u32, protobufs::Data)>` - The local channel index and decoded data,
+///   `None` if no known key produces a valid payload.
+pub fn decrypt_packet_payload(
+    channel_keys: &[ChannelKey],
+    packet: &protobufs::MeshPacket,
+    encrypted: &[u8],
+) -> Option<(u32, protobufs::Data)> {
+    for channel_key in channel_keys
+        .iter()
+        .filter(|k| k.channel_hash == packet.channel)
+    {
+        let decrypted =
+            match apply_channel_cipher(&channel_key.key, packet.id, packet.from, encrypted) {
+                Some(d) => d,
+                None => continue,
+            };
+
+        let data = match protobufs::Data::decode(decrypted.as_slice()) {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+
+        // A wrong key can still produce a decodable protobuf, so also require a known port
+        match protobufs::PortNum::from_i32(data.portnum) {
+            Some(protobufs::PortNum::UnknownApp) | None => continue,
+            Some(_) => return Some((channel_key.channel_index, data)),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_key_expansion() {
+        assert_eq!(expand_channel_psk(&[1]), DEFAULT_CHANNEL_KEY.to_vec());
+        assert_eq!(expand_channel_psk(&[2])[15], 0x02);
+        assert!(expand_channel_psk(&[0]).is_empty());
+        assert!(expand_channel_psk(&[]).is_empty());
+    }
+
+    #[test]
+    fn long_fast_channel_hash() {
+        let key = expand_channel_psk(&[1]);
+        assert_eq!(compute_channel_hash("LongFast", &key), 8);
+    }
+
+    #[test]
+    fn decrypt_default_key_text_packet() {
+        let encrypted = [0xab, 0xa2, 0x29, 0x94, 0xee, 0x11, 0x05, 0x52, 0xc6];
+        let channel_keys = vec![ChannelKey {
+            channel_index: 0,
+            channel_hash: 8,
+            key: DEFAULT_CHANNEL_KEY.to_vec(),
+        }];
+
+        let packet = protobufs::MeshPacket {
+            id: 0x12345678,
+            from: 0xdeadbeef,
+            channel: 8,
+            ..Default::default()
+        };
+
+        let (channel_index, data) =
+            decrypt_packet_payload(&channel_keys, &packet, &encrypted).expect("Failed to decrypt");
+
+        assert_eq!(channel_index, 0);
+        assert_eq!(data.portnum(), protobufs::PortNum::TextMessageApp);
+        assert_eq!(data.payload, b"hello".to_vec());
+    }
+}
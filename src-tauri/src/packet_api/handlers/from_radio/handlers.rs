@@ -103,17 +103,42 @@ pub fn handle_node_info_packet<R: tauri::Runtime>(
         .get_locked_graph()
         .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?;
 
-    graph.update_from_node_info(node_info);
+    graph.update_from_node_info(node_info, &packet_api.device_key);
 
     events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
+    events::dispatch_graph_component_changes(
+        &packet_api.app_handle,
+        graph.take_component_changes(),
+    )
+    .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
     events::dispatch_updated_graph(&packet_api.app_handle, graph.clone())
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
     Ok(())
 }
 
+pub fn handle_mqtt_client_proxy_packet<R: tauri::Runtime>(
+    packet_api: &mut MeshPacketApi<R>,
+    message: protobufs::MqttClientProxyMessage,
+) -> Result<(), DeviceUpdateError> {
+    let sender = packet_api.mqtt_proxy_sender.as_ref().ok_or_else(|| {
+        DeviceUpdateError::RadioMessageNotSupported(
+            "mqtt client proxy message without enabled proxy".into(),
+        )
+    })?;
+
+    debug!("Forwarding MQTT proxy message on topic {}", message.topic);
+
+    sender
+        .send(message)
+        .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     // * Integration test converage within `mod.rs`
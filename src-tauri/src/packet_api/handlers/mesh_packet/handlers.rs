@@ -1,16 +1,20 @@
-use log::debug;
+use log::{debug, warn};
 use meshtastic::protobufs;
 use tauri::api::notification::Notification;
 
 use crate::{
+    canned_messages,
     device::{
-        helpers::{get_channel_name, get_node_user_name},
-        ChannelMessageState, NeighborInfoPacket, NormalizedWaypoint, PositionPacket,
-        TelemetryPacket, TextPacket, UserPacket, WaypointPacket,
+        helpers::{get_channel_name, get_current_time_u32, get_node_user_name},
+        ChannelMessageState, NeighborInfoPacket, NormalizedPosition, NormalizedWaypoint,
+        PositionPacket, TelemetryPacket, TextPacket, UserPacket, WaypointPacket,
     },
+    geo::GeoPoint,
+    geofence::GeofenceEventKind,
     ipc::events,
-    packet_api::{handlers::DeviceUpdateError, MeshPacketApi},
+    packet_api::{compression, crypto, handlers::DeviceUpdateError, MeshPacketApi},
 };
+use meshtastic::packet::PacketRouter;
 use meshtastic::Message;
 
 pub fn handle_user_mesh_packet<R: tauri::Runtime>(
@@ -42,21 +46,149 @@ pub fn handle_position_mesh_packet<R: tauri::Runtime>(
         data: data.clone(),
     });
 
+    let position = NormalizedPosition::from(data.clone());
+    let movement_alert = packet_api.tracks.add_fix(packet.from, &position);
+
+    // Positions without a fix are reported at null island
+    let geofence_events = if position.latitude != 0.0 || position.longitude != 0.0 {
+        let time = if position.time != 0 {
+            position.time
+        } else {
+            get_current_time_u32()
+        };
+
+        packet_api
+            .geofences_arc
+            .lock()
+            .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?
+            .evaluate(packet.from, GeoPoint::from(&position), time)
+    } else {
+        vec![]
+    };
+
     let mut graph = packet_api
         .get_locked_graph()
         .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?;
 
-    graph.update_from_position(packet, data);
+    graph.update_from_position(packet, data, &packet_api.device_key);
 
     events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
+    if let Some(alert) = movement_alert {
+        let node_user_name = get_node_user_name(&mut packet_api.device, &alert.node_num)
+            .unwrap_or_else(|| alert.node_num.to_string());
+
+        let notification = Notification::new(
+            packet_api
+                .app_handle
+                .config()
+                .tauri
+                .bundle
+                .identifier
+                .clone(),
+        )
+        .title(format!("{} has moved", node_user_name))
+        .body(format!(
+            "Moved {:.0} m from its usual location to {}, {}",
+            alert.distance_meters, alert.to.latitude, alert.to.longitude
+        ));
+
+        events::dispatch_node_movement(&packet_api.app_handle, alert)
+            .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
+        if let Err(e) = notification.notify(&packet_api.app_handle) {
+            warn!("Failed to notify of node movement: {}", e);
+        }
+    }
+
+    for geofence_event in geofence_events {
+        let node_user_name = get_node_user_name(&mut packet_api.device, &geofence_event.node_num)
+            .unwrap_or_else(|| geofence_event.node_num.to_string());
+
+        let action = match geofence_event.kind {
+            GeofenceEventKind::Enter => "entered",
+            GeofenceEventKind::Exit => "left",
+            GeofenceEventKind::Dwell => "is staying in",
+        };
+
+        let notification = Notification::new(
+            packet_api
+                .app_handle
+                .config()
+                .tauri
+                .bundle
+                .identifier
+                .clone(),
+        )
+        .title(format!(
+            "{} {} {}",
+            node_user_name, action, geofence_event.geofence_name
+        ))
+        .body(format!(
+            "Reported position {}, {}",
+            geofence_event.location.latitude, geofence_event.location.longitude
+        ));
+
+        events::dispatch_geofence_event(&packet_api.app_handle, geofence_event)
+            .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
+        // The UI already has the event, so a failed notification isn't fatal
+        if let Err(e) = notification.notify(&packet_api.app_handle) {
+            warn!("Failed to notify of geofence event: {}", e);
+        }
+    }
+
+    events::dispatch_graph_component_changes(
+        &packet_api.app_handle,
+        graph.take_component_changes(),
+    )
+    .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
     events::dispatch_updated_graph(&packet_api.app_handle, graph.clone())
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
     Ok(())
 }
 
+pub fn handle_admin_mesh_packet<R: tauri::Runtime>(
+    packet_api: &mut MeshPacketApi<R>,
+    packet: protobufs::MeshPacket,
+    data: protobufs::Data,
+) -> Result<(), DeviceUpdateError> {
+    let admin_data = protobufs::AdminMessage::decode(data.payload.as_slice())
+        .map_err(|e| DeviceUpdateError::DecodeFailure(e.to_string()))?;
+
+    match admin_data.payload_variant {
+        Some(protobufs::admin_message::PayloadVariant::GetCannedMessageModuleMessagesResponse(
+            messages,
+        )) => {
+            packet_api.device.set_canned_messages(
+                packet.from,
+                canned_messages::split_canned_messages(&messages),
+            );
+
+            events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
+                .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+        }
+        // Our own write, echoed back so it can be matched with its acknowledgement
+        Some(protobufs::admin_message::PayloadVariant::SetCannedMessageModuleMessages(
+            messages,
+        )) if packet.from == packet_api.device.my_node_info.my_node_num => {
+            packet_api.device.add_pending_canned_messages(
+                packet.id,
+                packet.to,
+                canned_messages::split_canned_messages(&messages),
+            );
+        }
+        _ => {
+            return Err(DeviceUpdateError::PacketNotSupported("admin".into()));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn handle_routing_mesh_packet<R: tauri::Runtime>(
     packet_api: &mut MeshPacketApi<R>,
     packet: protobufs::MeshPacket,
@@ -69,6 +201,11 @@ pub fn handle_routing_mesh_packet<R: tauri::Runtime>(
         match variant {
             protobufs::routing::Variant::ErrorReason(e) => {
                 if let Some(r) = protobufs::routing::Error::from_i32(e) {
+                    packet_api.device.resolve_pending_canned_messages(
+                        data.request_id,
+                        r == protobufs::routing::Error::None,
+                    );
+
                     match r {
                         protobufs::routing::Error::None => {
                             packet_api.device.set_message_state(
@@ -153,13 +290,35 @@ pub fn handle_text_message_mesh_packet<R: tauri::Runtime>(
     packet: protobufs::MeshPacket,
     data: protobufs::Data,
 ) -> Result<(), DeviceUpdateError> {
+    let reply_to = (data.reply_id != 0).then_some(data.reply_id);
+    let is_reaction = data.emoji != 0;
+
     let data = String::from_utf8(data.payload)
         .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?;
 
-    packet_api.device.add_text_message(TextPacket {
-        packet: packet.clone(),
-        data: data.clone(),
-    });
+    // Reactions are aggregated onto the message they react to instead of
+    // being shown as messages of their own
+    if let Some(reply_id) = reply_to.filter(|_| is_reaction) {
+        let found = packet_api.device.add_reaction(&packet, reply_id, data);
+
+        if !found {
+            debug!("Dropping reaction to unknown message {}", reply_id);
+            return Ok(());
+        }
+
+        events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
+            .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
+        return Ok(());
+    }
+
+    let is_new_message = packet_api.device.add_text_message(
+        TextPacket {
+            packet: packet.clone(),
+            data: data.clone(),
+        },
+        reply_to,
+    );
 
     let from_user_name = get_node_user_name(&mut packet_api.device, &packet.from)
         .unwrap_or_else(|| packet.from.to_string());
@@ -171,7 +330,8 @@ pub fn handle_text_message_mesh_packet<R: tauri::Runtime>(
     events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
-    if packet.from != packet_api.device.my_node_info.my_node_num {
+    // Repeats of a message only update its heard count
+    if packet.from != packet_api.device.my_node_info.my_node_num && is_new_message {
         Notification::new(
             packet_api
                 .app_handle
@@ -190,6 +350,18 @@ pub fn handle_text_message_mesh_packet<R: tauri::Runtime>(
     Ok(())
 }
 
+pub fn handle_compressed_text_message_mesh_packet<R: tauri::Runtime>(
+    packet_api: &mut MeshPacketApi<R>,
+    packet: protobufs::MeshPacket,
+    mut data: protobufs::Data,
+) -> Result<(), DeviceUpdateError> {
+    // Once decompressed, these are handled exactly like plain text messages
+    data.payload =
+        compression::decompress(&data.payload).map_err(DeviceUpdateError::DecodeFailure)?;
+
+    handle_text_message_mesh_packet(packet_api, packet, data)
+}
+
 pub fn handle_waypoint_mesh_packet<R: tauri::Runtime>(
     packet_api: &mut MeshPacketApi<R>,
     packet: protobufs::MeshPacket,
@@ -200,11 +372,27 @@ pub fn handle_waypoint_mesh_packet<R: tauri::Runtime>(
 
     let converted_data: NormalizedWaypoint = data.into();
 
+    // Nodes can't edit or delete waypoints locked to another node
+    if let Err(e) = packet_api
+        .device
+        .check_waypoint_editable(converted_data.id, packet.from)
+    {
+        debug!("Ignoring waypoint from node {}: {}", packet.from, e);
+        return Ok(());
+    }
+
+    let is_new_waypoint = !packet_api.device.waypoints.contains_key(&converted_data.id);
+    let is_deletion = converted_data.is_expired(get_current_time_u32());
+
+    // Edits replace the existing waypoint rather than adding a new message
     packet_api.device.add_waypoint(converted_data.clone());
-    packet_api.device.add_waypoint_message(WaypointPacket {
-        packet: packet.clone(),
-        data: converted_data.clone(),
-    });
+
+    if is_new_waypoint && !is_deletion {
+        packet_api.device.add_waypoint_message(WaypointPacket {
+            packet: packet.clone(),
+            data: converted_data.clone(),
+        });
+    }
 
     let from_user_name = get_node_user_name(&mut packet_api.device, &packet.from)
         .unwrap_or_else(|| packet.from.to_string());
@@ -215,7 +403,8 @@ pub fn handle_waypoint_mesh_packet<R: tauri::Runtime>(
     events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
-    if packet.from != packet_api.device.my_node_info.my_node_num {
+    if packet.from != packet_api.device.my_node_info.my_node_num && is_new_waypoint && !is_deletion
+    {
         Notification::new(
             packet_api
                 .app_handle
@@ -254,17 +443,144 @@ pub fn handle_neighbor_info_mesh_packet<R: tauri::Runtime>(
         .get_locked_graph()
         .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?;
 
-    graph.update_from_neighbor_info(packet, data);
+    graph.update_from_neighbor_info(packet, data, &packet_api.device_key);
+
+    events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
+        .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
+    events::dispatch_graph_component_changes(
+        &packet_api.app_handle,
+        graph.take_component_changes(),
+    )
+    .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
+    events::dispatch_updated_graph(&packet_api.app_handle, graph.clone())
+        .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
+    Ok(())
+}
+
+pub fn handle_serial_mesh_packet<R: tauri::Runtime>(
+    packet_api: &mut MeshPacketApi<R>,
+    packet: protobufs::MeshPacket,
+    data: protobufs::Data,
+) -> Result<(), DeviceUpdateError> {
+    let bridge = packet_api
+        .serial_bridges
+        .get(&packet.from)
+        .ok_or_else(|| DeviceUpdateError::PacketNotSupported("serial without bridge".into()))?;
+
+    debug!(
+        "Forwarding {} serial bytes from node {} to {}",
+        data.payload.len(),
+        packet.from,
+        bridge.info.local_address
+    );
+
+    bridge
+        .inbound_sender
+        .send(data.payload)
+        .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?;
+
+    Ok(())
+}
+
+pub fn handle_map_report_mesh_packet<R: tauri::Runtime>(
+    packet_api: &mut MeshPacketApi<R>,
+    packet: protobufs::MeshPacket,
+    data: protobufs::Data,
+) -> Result<(), DeviceUpdateError> {
+    let data = protobufs::MapReport::decode(data.payload.as_slice())
+        .map_err(|e| DeviceUpdateError::DecodeFailure(e.to_string()))?;
+
+    // Map reports are only published over MQTT and summarize the user and position of a node
+    packet_api.device.add_user(UserPacket {
+        packet: packet.clone(),
+        data: protobufs::User {
+            id: format!("!{:08x}", packet.from),
+            long_name: data.long_name,
+            short_name: data.short_name,
+            hw_model: data.hw_model,
+            role: data.role,
+            ..Default::default()
+        },
+    });
+
+    let position = protobufs::Position {
+        latitude_i: data.latitude_i,
+        longitude_i: data.longitude_i,
+        altitude: data.altitude,
+        ..Default::default()
+    };
+
+    packet_api.device.add_position(PositionPacket {
+        packet: packet.clone(),
+        data: position.clone(),
+    });
+
+    let mut graph = packet_api
+        .get_locked_graph()
+        .map_err(|e| DeviceUpdateError::GeneralFailure(e.to_string()))?;
+
+    graph.update_from_position(packet, position, &packet_api.device_key);
 
     events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
+    events::dispatch_graph_component_changes(
+        &packet_api.app_handle,
+        graph.take_component_changes(),
+    )
+    .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
     events::dispatch_updated_graph(&packet_api.app_handle, graph.clone())
         .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
 
     Ok(())
 }
 
+pub fn handle_encrypted_mesh_packet<R: tauri::Runtime>(
+    packet_api: &mut MeshPacketApi<R>,
+    packet: protobufs::MeshPacket,
+    encrypted: Vec<u8>,
+) -> Result<(), DeviceUpdateError> {
+    let channel_keys = crypto::get_device_channel_keys(&packet_api.device);
+
+    let (channel_index, data) =
+        match crypto::decrypt_packet_payload(&channel_keys, &packet, &encrypted) {
+            Some(decrypted) => decrypted,
+            None => {
+                debug!(
+                    "No known key for encrypted packet {} on channel hash {}",
+                    packet.id, packet.channel
+                );
+
+                packet_api
+                    .device
+                    .increment_encrypted_packet_count(packet.channel);
+
+                events::dispatch_updated_device(&packet_api.app_handle, &packet_api.device)
+                    .map_err(|e| DeviceUpdateError::EventDispatchFailure(e.to_string()))?;
+
+                return Ok(());
+            }
+        };
+
+    debug!(
+        "Decrypted packet {} from node {} on channel {}",
+        packet.id, packet.from, channel_index
+    );
+
+    // Route as if the radio had decoded the packet on the local channel
+    let decoded_packet = protobufs::MeshPacket {
+        channel: channel_index,
+        payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)),
+        ..packet
+    };
+
+    packet_api.handle_mesh_packet(decoded_packet)
+}
+
 #[cfg(test)]
 mod tests {
     // * Integration test converage within `mod.rs`
@@ -1,9 +1,18 @@
+use std::collections::HashMap;
 use std::sync::{Arc, LockResult, Mutex};
 
+use meshtastic::protobufs;
+use tokio::sync::mpsc::UnboundedSender;
+
 // use meshtastic::connections::stream_api::{state::Configured, StreamApi};
 
-use crate::{device::MeshDevice, graph::ds::graph::MeshGraph, state::DeviceKey};
+use crate::{
+    device::MeshDevice, geofence::GeofenceManager, graph::ds::graph::MeshGraph,
+    serial_bridge::SerialBridge, state::DeviceKey, track::TrackStore,
+};
 
+pub mod compression;
+pub mod crypto;
 pub mod handlers;
 pub mod router;
 
@@ -12,6 +21,10 @@ pub struct MeshPacketApi<R: tauri::Runtime = tauri::Wry> {
     pub device_key: DeviceKey,
     pub device: MeshDevice,
     pub graph_arc: Arc<Mutex<MeshGraph>>,
+    pub geofences_arc: Arc<Mutex<GeofenceManager>>,
+    pub mqtt_proxy_sender: Option<UnboundedSender<protobufs::MqttClientProxyMessage>>,
+    pub serial_bridges: HashMap<u32, SerialBridge>, // open serial module bridges by remote node
+    pub tracks: TrackStore,
 }
 
 impl<R: tauri::Runtime> MeshPacketApi<R> {
@@ -20,12 +33,17 @@ impl<R: tauri::Runtime> MeshPacketApi<R> {
         device_key: DeviceKey,
         device: MeshDevice,
         graph_arc: Arc<Mutex<MeshGraph>>,
+        geofences_arc: Arc<Mutex<GeofenceManager>>,
     ) -> Self {
         Self {
             app_handle,
             device_key,
             device,
             graph_arc,
+            geofences_arc,
+            mqtt_proxy_sender: None,
+            serial_bridges: HashMap::new(),
+            tracks: TrackStore::new(),
         }
     }
 
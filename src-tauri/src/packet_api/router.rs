@@ -74,10 +74,8 @@ impl<R: tauri::Runtime> PacketRouter<(), DeviceUpdateError> for MeshPacketApi<R>
             protobufs::from_radio::PayloadVariant::XmodemPacket(_) => {
                 return Err(DeviceUpdateError::RadioMessageNotSupported("xmodem".into()));
             }
-            protobufs::from_radio::PayloadVariant::MqttClientProxyMessage(_) => {
-                return Err(DeviceUpdateError::RadioMessageNotSupported(
-                    "mqtt client proxy message".into(),
-                ));
+            protobufs::from_radio::PayloadVariant::MqttClientProxyMessage(message) => {
+                from_radio_handlers::handle_mqtt_client_proxy_packet(self, message)?;
             }
         };
 
@@ -97,7 +95,7 @@ impl<R: tauri::Runtime> PacketRouter<(), DeviceUpdateError> for MeshPacketApi<R>
         match variant {
             protobufs::mesh_packet::PayloadVariant::Decoded(data) => match data.portnum() {
                 protobufs::PortNum::AdminApp => {
-                    return Err(DeviceUpdateError::PacketNotSupported("admin".into()));
+                    mesh_packet_handlers::handle_admin_mesh_packet(self, packet, data)?;
                 }
                 protobufs::PortNum::AtakForwarder => {
                     return Err(DeviceUpdateError::PacketNotSupported(
@@ -134,7 +132,7 @@ impl<R: tauri::Runtime> PacketRouter<(), DeviceUpdateError> for MeshPacketApi<R>
                     mesh_packet_handlers::handle_routing_mesh_packet(self, packet, data)?;
                 }
                 protobufs::PortNum::SerialApp => {
-                    return Err(DeviceUpdateError::PacketNotSupported("serial".into()));
+                    mesh_packet_handlers::handle_serial_mesh_packet(self, packet, data)?;
                 }
                 protobufs::PortNum::SimulatorApp => {
                     return Err(DeviceUpdateError::PacketNotSupported("simulator".into()));
@@ -151,9 +149,9 @@ impl<R: tauri::Runtime> PacketRouter<(), DeviceUpdateError> for MeshPacketApi<R>
                     mesh_packet_handlers::handle_text_message_mesh_packet(self, packet, data)?;
                 }
                 protobufs::PortNum::TextMessageCompressedApp => {
-                    return Err(DeviceUpdateError::PacketNotSupported(
-                        "compressed text".into(),
-                    ));
+                    mesh_packet_handlers::handle_compressed_text_message_mesh_packet(
+                        self, packet, data,
+                    )?;
                 }
                 protobufs::PortNum::WaypointApp => {
                     mesh_packet_handlers::handle_waypoint_mesh_packet(self, packet, data)?;
@@ -189,11 +187,11 @@ impl<R: tauri::Runtime> PacketRouter<(), DeviceUpdateError> for MeshPacketApi<R>
                     return Err(DeviceUpdateError::PacketNotSupported("atakplugin".into()));
                 }
                 protobufs::PortNum::MapReportApp => {
-                    return Err(DeviceUpdateError::PacketNotSupported("mapreport".into()));
+                    mesh_packet_handlers::handle_map_report_mesh_packet(self, packet, data)?;
                 }
             },
-            protobufs::mesh_packet::PayloadVariant::Encrypted(_e) => {
-                return Err(DeviceUpdateError::PacketNotSupported("encrypted".into()));
+            protobufs::mesh_packet::PayloadVariant::Encrypted(encrypted) => {
+                mesh_packet_handlers::handle_encrypted_mesh_packet(self, packet, encrypted)?;
             }
         }
 
@@ -0,0 +1,250 @@
+use log::{debug, info, warn};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+/// Largest chunk of local data sent in a single mesh packet
+pub const SERIAL_BRIDGE_CHUNK_SIZE: usize = 200;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type")]
+pub enum SerialBridgeTarget {
+    Tcp { port: u16 }, // listen on a local TCP port
+    Pty,               // create a pseudo-terminal, unix only
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SerialBridgeInfo {
+    pub node_num: u32,
+    pub channel: u32,
+    pub local_address: String, // TCP address or PTY path tools should connect to
+}
+
+pub struct SerialBridge {
+    pub info: SerialBridgeInfo,
+    pub inbound_sender: UnboundedSender<Vec<u8>>,
+    bridge_handle: JoinHandle<()>,
+}
+
+impl SerialBridge {
+    pub fn close(&self) {
+        debug!("Closing serial bridge to node {}", self.info.node_num);
+        self.bridge_handle.abort();
+    }
+}
+
+impl Drop for SerialBridge {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Opens a local endpoint for a remote node's serial module.
+///
+/// # Arguments
+///
+/// * `node_num` - The remote node whose serial module is bridged.
+/// * `channel` - The channel to send serial data on.
+/// * `target` - The kind of local endpoint to open.
+/// * `outbound_sender` - Receives data written to the local endpoint, to be sent to the node.
+///
+/// # Returns
+///
+/// * `SerialBridge` - The open bridge, which accepts data received from the node.
+pub async fn open_serial_bridge(
+    node_num: u32,
+    channel: u32,
+    target: SerialBridgeTarget,
+    outbound_sender: UnboundedSender<Vec<u8>>,
+) -> Result<SerialBridge, String> {
+    let (inbound_sender, inbound_listener) = tokio::sync::mpsc::unbounded_channel();
+
+    let (local_address, bridge_handle) = match target {
+        SerialBridgeTarget::Tcp { port } => {
+            let listener = TcpListener::bind(("127.0.0.1", port))
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let local_address = listener
+                .local_addr()
+                .map_err(|e| e.to_string())?
+                .to_string();
+
+            let handle = tauri::async_runtime::spawn(run_tcp_bridge(
+                listener,
+                inbound_listener,
+                outbound_sender,
+            ));
+
+            (local_address, handle)
+        }
+        SerialBridgeTarget::Pty => open_pty_bridge(inbound_listener, outbound_sender)?,
+    };
+
+    info!(
+        "Bridging serial module of node {} to {}",
+        node_num, local_address
+    );
+
+    Ok(SerialBridge {
+        info: SerialBridgeInfo {
+            node_num,
+            channel,
+            local_address,
+        },
+        inbound_sender,
+        bridge_handle,
+    })
+}
+
+#[cfg(unix)]
+fn open_pty_bridge(
+    inbound_listener: UnboundedReceiver<Vec<u8>>,
+    outbound_sender: UnboundedSender<Vec<u8>>,
+) -> Result<(String, JoinHandle<()>), String> {
+    use tokio_serial::SerialPort;
+
+    let (master, slave) = tokio_serial::SerialStream::pair().map_err(|e| e.to_string())?;
+    let local_address = slave.name().ok_or("Failed to get pseudo-terminal name")?;
+
+    let handle = tauri::async_runtime::spawn(async move {
+        // Reads from the master fail once the slave is closed, so keep it open
+        let _slave = slave;
+        let mut inbound_listener = inbound_listener;
+
+        run_bridge(master, &mut inbound_listener, &outbound_sender).await;
+    });
+
+    Ok((local_address, handle))
+}
+
+#[cfg(not(unix))]
+fn open_pty_bridge(
+    _inbound_listener: UnboundedReceiver<Vec<u8>>,
+    _outbound_sender: UnboundedSender<Vec<u8>>,
+) -> Result<(String, JoinHandle<()>), String> {
+    Err("Pseudo-terminals are not supported on this platform".into())
+}
+
+async fn run_tcp_bridge(
+    listener: TcpListener,
+    mut inbound_listener: UnboundedReceiver<Vec<u8>>,
+    outbound_sender: UnboundedSender<Vec<u8>>,
+) {
+    loop {
+        // Data received while no client is connected has nowhere to go
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, address)) => {
+                    debug!("Serial bridge client connected from {}", address);
+                    stream
+                }
+                Err(e) => {
+                    warn!("Failed to accept serial bridge client: {}", e);
+                    continue;
+                }
+            },
+            data = inbound_listener.recv() => match data {
+                Some(data) => {
+                    debug!("Dropping {} serial bytes, no client connected", data.len());
+                    continue;
+                }
+                None => break,
+            },
+        };
+
+        if !run_bridge(stream, &mut inbound_listener, &outbound_sender).await {
+            break;
+        }
+
+        debug!("Serial bridge client disconnected");
+    }
+}
+
+/// Copies data between a local stream and the mesh until the stream closes.
+/// Returns `false` if the bridge itself has been closed.
+async fn run_bridge<S>(
+    mut stream: S,
+    inbound_listener: &mut UnboundedReceiver<Vec<u8>>,
+    outbound_sender: &UnboundedSender<Vec<u8>>,
+) -> bool
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buffer = [0u8; SERIAL_BRIDGE_CHUNK_SIZE];
+
+    loop {
+        tokio::select! {
+            read = stream.read(&mut buffer) => match read {
+                Ok(0) => return true,
+                Ok(len) => {
+                    if outbound_sender.send(buffer[..len].to_vec()).is_err() {
+                        return false;
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to read from serial bridge: {}", e);
+                    return true;
+                }
+            },
+            data = inbound_listener.recv() => match data {
+                Some(data) => {
+                    if let Err(e) = stream.write_all(&data).await {
+                        warn!("Failed to write to serial bridge: {}", e);
+                        return true;
+                    }
+                }
+                None => return false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn copies_data_in_both_directions() {
+        let (local, bridge_side) = tokio::io::duplex(SERIAL_BRIDGE_CHUNK_SIZE);
+        let (inbound_sender, mut inbound_listener) = tokio::sync::mpsc::unbounded_channel();
+        let (outbound_sender, mut outbound_listener) = tokio::sync::mpsc::unbounded_channel();
+
+        let bridge = tokio::spawn(async move {
+            run_bridge(bridge_side, &mut inbound_listener, &outbound_sender).await
+        });
+
+        let (mut local_reader, mut local_writer) = tokio::io::split(local);
+
+        // Payloads received from the node are written to the local side
+        inbound_sender.send(b"from node".to_vec()).unwrap();
+
+        let mut received = [0u8; 9];
+        local_reader.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"from node");
+
+        // Data read from the local side is forwarded to the node
+        local_writer.write_all(b"to node").await.unwrap();
+        assert_eq!(outbound_listener.recv().await.unwrap(), b"to node".to_vec());
+
+        // Closing the bridge ends the copy loop
+        drop(inbound_sender);
+        assert!(!bridge.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn returns_when_local_side_closes() {
+        let (local, bridge_side) = tokio::io::duplex(SERIAL_BRIDGE_CHUNK_SIZE);
+        let (_inbound_sender, mut inbound_listener) = tokio::sync::mpsc::unbounded_channel();
+        let (outbound_sender, _outbound_listener) = tokio::sync::mpsc::unbounded_channel();
+
+        drop(local);
+
+        assert!(run_bridge(bridge_side, &mut inbound_listener, &outbound_sender).await);
+    }
+}
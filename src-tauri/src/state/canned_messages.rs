@@ -0,0 +1,17 @@
+use std::sync::{Arc, Mutex};
+
+use crate::canned_messages::CannedMessageLibrary;
+
+pub type CannedMessagesStateInner = Arc<Mutex<CannedMessageLibrary>>;
+
+pub struct CannedMessagesState {
+    pub inner: CannedMessagesStateInner,
+}
+
+impl CannedMessagesState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(CannedMessageLibrary::new())),
+        }
+    }
+}
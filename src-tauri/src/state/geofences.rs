@@ -0,0 +1,17 @@
+use std::sync::{Arc, Mutex};
+
+use crate::geofence::GeofenceManager;
+
+pub type GeofencesStateInner = Arc<Mutex<GeofenceManager>>;
+
+pub struct GeofencesState {
+    pub inner: GeofencesStateInner,
+}
+
+impl GeofencesState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(GeofenceManager::new())),
+        }
+    }
+}
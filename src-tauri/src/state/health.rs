@@ -0,0 +1,18 @@
+use std::sync::Arc;
+use tauri::async_runtime;
+
+use crate::health::HealthMonitor;
+
+pub type HealthMonitorStateInner = Arc<async_runtime::Mutex<HealthMonitor>>;
+
+pub struct HealthMonitorState {
+    pub inner: HealthMonitorStateInner,
+}
+
+impl HealthMonitorState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(async_runtime::Mutex::new(HealthMonitor::new())),
+        }
+    }
+}
@@ -1,6 +1,11 @@
 pub mod autoconnect;
+pub mod canned_messages;
+pub mod geofences;
 pub mod graph;
+pub mod health;
 pub mod mesh_devices;
+pub mod mqtt_connections;
+pub mod mqtt_proxies;
 pub mod radio_connections;
 
 pub type DeviceKey = String;
@@ -0,0 +1,20 @@
+use std::{collections::HashMap, sync::Arc};
+use tauri::async_runtime;
+
+use crate::mqtt::MqttConnection;
+
+use super::DeviceKey;
+
+pub type MqttConnectionsStateInner = Arc<async_runtime::Mutex<HashMap<DeviceKey, MqttConnection>>>;
+
+pub struct MqttConnectionsState {
+    pub inner: MqttConnectionsStateInner,
+}
+
+impl MqttConnectionsState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(async_runtime::Mutex::new(HashMap::new())),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use std::{collections::HashMap, sync::Arc};
+use tauri::async_runtime;
+
+use crate::mqtt::MqttConnection;
+
+use super::DeviceKey;
+
+/// Broker connections proxying MQTT traffic for connected radios, keyed by
+/// the key of the radio they proxy for
+pub type MqttProxiesStateInner = Arc<async_runtime::Mutex<HashMap<DeviceKey, MqttConnection>>>;
+
+pub struct MqttProxiesState {
+    pub inner: MqttProxiesStateInner,
+}
+
+impl MqttProxiesState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(async_runtime::Mutex::new(HashMap::new())),
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+
+use log::{debug, trace};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use crate::device::{
+    helpers::{format_utc_time, get_current_time_u32},
+    NormalizedPosition,
+};
+use crate::geo::{bearing_degrees, distance_meters, GeoPoint};
+use crate::graph::api::export::escape_xml;
+
+const GPX_CREATOR: &str = "Meshtastic Network Management Client";
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackConfig {
+    pub dedup_radius_meters: f64, // fixes closer than this to the last point are merged into it
+    pub movement_threshold_meters: f64, // distance a static node must move to be flagged
+    pub static_after_secs: u32,   // time a node must stay put before it counts as static
+    pub max_points: u32,          // oldest points are dropped past this many per node
+}
+
+impl Default for TrackConfig {
+    fn default() -> Self {
+        Self {
+            dedup_radius_meters: 25.0,
+            movement_threshold_meters: 200.0,
+            static_after_secs: 6 * 60 * 60,
+            max_points: 10_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackPoint {
+    pub location: GeoPoint,
+    pub altitude: i32,  // meters
+    pub time: u32,      // secs, time of the first fix at this point
+    pub last_time: u32, // secs, time of the latest fix merged into this point
+    pub fix_count: u32,
+    pub distance_meters: f64, // from the previous point
+    pub speed_mps: f64,       // average speed since the previous point
+    pub heading_degrees: f64, // from the previous point, clockwise from north
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeTrack {
+    pub node_num: u32,
+    pub points: Vec<TrackPoint>,
+    pub total_distance_meters: f64,
+    pub anchor: Option<TrackPoint>, // where the node has stayed since it last moved
+    pub is_static: bool,            // whether the node normally stays at its anchor
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeMovementAlert {
+    pub node_num: u32,
+    pub from: GeoPoint,
+    pub to: GeoPoint,
+    pub distance_meters: f64,
+    pub time: u32, // secs
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum TrackExportFormat {
+    Gpx,
+    Kml,
+}
+
+impl NodeTrack {
+    fn new(node_num: u32) -> Self {
+        Self {
+            node_num,
+            points: vec![],
+            total_distance_meters: 0.0,
+            anchor: None,
+            is_static: false,
+        }
+    }
+
+    /// Gets the points with fixes between `start` and `end`, inclusive.
+    pub fn get_points_between(&self, start: Option<u32>, end: Option<u32>) -> Vec<TrackPoint> {
+        self.points
+            .iter()
+            .filter(|point| start.map_or(true, |start| point.last_time >= start))
+            .filter(|point| end.map_or(true, |end| point.time <= end))
+            .cloned()
+            .collect()
+    }
+
+    fn add_fix(
+        &mut self,
+        location: GeoPoint,
+        altitude: i32,
+        time: u32,
+        config: &TrackConfig,
+    ) -> Option<NodeMovementAlert> {
+        let new_point = match self.points.last_mut() {
+            Some(last_point) => {
+                let distance = distance_meters(&last_point.location, &location);
+
+                // Stationary fixes only extend the time spent at the last point
+                if distance < config.dedup_radius_meters {
+                    trace!("Merging stationary fix of node {}", self.node_num);
+
+                    last_point.last_time = last_point.last_time.max(time);
+                    last_point.fix_count += 1;
+
+                    return self.update_anchor(location, time, config);
+                }
+
+                let elapsed_secs = time.saturating_sub(last_point.last_time);
+
+                TrackPoint {
+                    location,
+                    altitude,
+                    time,
+                    last_time: time,
+                    fix_count: 1,
+                    distance_meters: distance,
+                    speed_mps: if elapsed_secs > 0 {
+                        distance / f64::from(elapsed_secs)
+                    } else {
+                        0.0
+                    },
+                    heading_degrees: bearing_degrees(&last_point.location, &location),
+                }
+            }
+            None => TrackPoint {
+                location,
+                altitude,
+                time,
+                last_time: time,
+                fix_count: 1,
+                distance_meters: 0.0,
+                speed_mps: 0.0,
+                heading_degrees: 0.0,
+            },
+        };
+
+        self.total_distance_meters += new_point.distance_meters;
+        self.points.push(new_point);
+
+        if self.points.len() > config.max_points as usize {
+            let excess = self.points.len() - config.max_points as usize;
+            self.points.drain(..excess);
+        }
+
+        self.update_anchor(location, time, config)
+    }
+
+    fn update_anchor(
+        &mut self,
+        location: GeoPoint,
+        time: u32,
+        config: &TrackConfig,
+    ) -> Option<NodeMovementAlert> {
+        let anchor = match self.anchor.as_mut() {
+            Some(anchor) => anchor,
+            None => {
+                self.anchor = self.points.last().cloned();
+                return None;
+            }
+        };
+
+        let distance = distance_meters(&anchor.location, &location);
+
+        if distance < config.movement_threshold_meters {
+            anchor.last_time = anchor.last_time.max(time);
+
+            if !self.is_static
+                && anchor.last_time.saturating_sub(anchor.time) >= config.static_after_secs
+            {
+                debug!("Node {} is now considered static", self.node_num);
+                self.is_static = true;
+            }
+
+            return None;
+        }
+
+        let alert = if self.is_static {
+            Some(NodeMovementAlert {
+                node_num: self.node_num,
+                from: anchor.location,
+                to: location,
+                distance_meters: distance,
+                time,
+            })
+        } else {
+            None
+        };
+
+        // Start waiting for the node to settle at its new location
+        self.is_static = false;
+        self.anchor = self.points.last().cloned();
+
+        alert
+    }
+}
+
+/// Tracks of every node a device has received positions from
+#[derive(Clone, Debug, Default)]
+pub struct TrackStore {
+    pub config: TrackConfig,
+    tracks: HashMap<u32, NodeTrack>,
+}
+
+impl TrackStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_track(&self, node_num: u32) -> Option<&NodeTrack> {
+        self.tracks.get(&node_num)
+    }
+
+    /// Adds a position fix to a node's track.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_num` - The node that reported the position.
+    /// * `position` - The reported position.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<NodeMovementAlert>` - An alert if a normally static node has moved.
+    pub fn add_fix(
+        &mut self,
+        node_num: u32,
+        position: &NormalizedPosition,
+    ) -> Option<NodeMovementAlert> {
+        // Positions without a fix are reported at null island
+        if position.latitude == 0.0 && position.longitude == 0.0 {
+            return None;
+        }
+
+        let time = if position.time != 0 {
+            position.time
+        } else {
+            get_current_time_u32()
+        };
+
+        self.tracks
+            .entry(node_num)
+            .or_insert_with(|| NodeTrack::new(node_num))
+            .add_fix(
+                GeoPoint::from(position),
+                position.altitude,
+                time,
+                &self.config,
+            )
+    }
+}
+
+/// Exports the points of a track as GPX or KML.
+pub fn export_track(points: &[TrackPoint], track_name: &str, format: TrackExportFormat) -> String {
+    match format {
+        TrackExportFormat::Gpx => to_gpx(points, track_name),
+        TrackExportFormat::Kml => to_kml(points, track_name),
+    }
+}
+
+fn to_gpx(points: &[TrackPoint], track_name: &str) -> String {
+    let mut output = String::new();
+
+    output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    output.push_str(&format!(
+        "<gpx version=\"1.1\" creator=\"{}\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+        GPX_CREATOR
+    ));
+    output.push_str("  <trk>\n");
+    output.push_str(&format!("    <name>{}</name>\n", escape_xml(track_name)));
+    output.push_str("    <trkseg>\n");
+
+    for point in points {
+        output.push_str(&format!(
+            "      <trkpt lat=\"{}\" lon=\"{}\">\n",
+            point.location.latitude, point.location.longitude
+        ));
+        output.push_str(&format!("        <ele>{}</ele>\n", point.altitude));
+        output.push_str(&format!(
+            "        <time>{}</time>\n",
+            format_utc_time(point.time)
+        ));
+        output.push_str("      </trkpt>\n");
+    }
+
+    output.push_str("    </trkseg>\n");
+    output.push_str("  </trk>\n");
+    output.push_str("</gpx>\n");
+
+    output
+}
+
+fn to_kml(points: &[TrackPoint], track_name: &str) -> String {
+    let mut output = String::new();
+
+    output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    output.push_str(
+        "<kml xmlns=\"http://www.opengis.net/kml/2.2\" xmlns:gx=\"http://www.google.com/kml/ext/2.2\">\n",
+    );
+    output.push_str("  <Document>\n");
+    output.push_str(&format!("    <name>{}</name>\n", escape_xml(track_name)));
+    output.push_str("    <Placemark>\n");
+    output.push_str(&format!("      <name>{}</name>\n", escape_xml(track_name)));
+    output.push_str("      <gx:Track>\n");
+    output.push_str("        <altitudeMode>absolute</altitudeMode>\n");
+
+    // Times and coordinates are listed separately, in the same order
+    for point in points {
+        output.push_str(&format!(
+            "        <when>{}</when>\n",
+            format_utc_time(point.time)
+        ));
+    }
+
+    for point in points {
+        output.push_str(&format!(
+            "        <gx:coord>{} {} {}</gx:coord>\n",
+            point.location.longitude, point.location.latitude, point.altitude
+        ));
+    }
+
+    output.push_str("      </gx:Track>\n");
+    output.push_str("    </Placemark>\n");
+    output.push_str("  </Document>\n");
+    output.push_str("</kml>\n");
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(latitude: f64) -> GeoPoint {
+        GeoPoint {
+            latitude,
+            longitude: 0.0,
+        }
+    }
+
+    #[test]
+    fn merges_stationary_fixes() {
+        let config = TrackConfig::default();
+        let mut track = NodeTrack::new(1);
+
+        track.add_fix(point(10.0), 0, 100, &config);
+        track.add_fix(point(10.0001), 0, 200, &config); // about 11 m away
+        track.add_fix(point(10.01), 0, 300, &config); // about 1.1 km away
+
+        assert_eq!(track.points.len(), 2);
+        assert_eq!(track.points[0].fix_count, 2);
+        assert_eq!(track.points[0].last_time, 200);
+        assert!((track.points[1].speed_mps - track.points[1].distance_meters / 100.0).abs() < 1e-9);
+        assert!(track.points[1].heading_degrees.abs() < 1e-6);
+    }
+
+    #[test]
+    fn flags_static_node_movement() {
+        let config = TrackConfig::default();
+        let mut track = NodeTrack::new(1);
+
+        assert!(track.add_fix(point(10.0), 0, 0, &config).is_none());
+        assert!(track
+            .add_fix(point(10.0), 0, config.static_after_secs, &config)
+            .is_none());
+        assert!(track.is_static);
+
+        let alert = track
+            .add_fix(point(10.01), 0, config.static_after_secs + 60, &config)
+            .expect("Expected a movement alert");
+
+        assert_eq!(alert.node_num, 1);
+        assert!(!track.is_static);
+
+        // A node that is already moving isn't flagged again
+        assert!(track
+            .add_fix(point(10.02), 0, config.static_after_secs + 120, &config)
+            .is_none());
+    }
+
+    #[test]
+    fn gpx_export() {
+        let config = TrackConfig::default();
+        let mut track = NodeTrack::new(1);
+
+        track.add_fix(point(10.0), 5, 0, &config);
+        track.add_fix(point(10.01), 5, 60, &config);
+
+        let gpx = export_track(&track.points, "Node <1>", TrackExportFormat::Gpx);
+
+        assert!(gpx.contains("<name>Node &lt;1&gt;</name>"));
+        assert_eq!(gpx.matches("<trkpt").count(), 2);
+        assert!(gpx.contains("<time>1970-01-01T00:01:00Z</time>"));
+    }
+}
@@ -0,0 +1,480 @@
+use std::path::Path;
+
+use geojson::{Feature, FeatureCollection, GeoJson, Geometry, JsonObject, Value};
+use log::{debug, warn};
+use meshtastic::ts::specta::{self, Type};
+use serde::{Deserialize, Serialize};
+
+use crate::device::NormalizedWaypoint;
+use crate::graph::api::export::escape_xml;
+
+// Firmware limits on waypoint strings, in UTF-8 bytes. The protobuf field
+// sizes include the NUL terminator, so one byte less is usable.
+pub const MAX_WAYPOINT_NAME_LENGTH: usize = 29;
+pub const MAX_WAYPOINT_DESCRIPTION_LENGTH: usize = 99;
+
+const GPX_CREATOR: &str = "Meshtastic Network Management Client";
+
+// Waypoint ids are kept in GPX extensions, so re-importing a file updates
+// the same waypoints instead of creating copies
+const GPX_EXTENSIONS_PREFIX: &str = "meshtastic";
+const GPX_EXTENSIONS_NAMESPACE: &str = "https://meshtastic.org/gpx/1";
+
+// Waypoint icons are emoji, while GPX and most mapping tools use named symbols
+const WAYPOINT_SYMBOLS: &[(u32, &str)] = &[
+    (0x1F4CD, "Pin"),              // 📍
+    (0x1F6A9, "Flag"),             // 🚩
+    (0x1F3E0, "Residence"),        // 🏠
+    (0x26FA, "Campground"),        // ⛺
+    (0x1F697, "Car"),              // 🚗
+    (0x1F17F, "Parking Area"),     // 🅿
+    (0x26FD, "Gas Station"),       // ⛽
+    (0x1F374, "Restaurant"),       // 🍴
+    (0x1F4A7, "Drinking Water"),   // 💧
+    (0x1F3E5, "Medical Facility"), // 🏥
+    (0x26A0, "Danger Area"),       // ⚠
+    (0x26F0, "Summit"),            // ⛰
+    (0x2693, "Anchor"),            // ⚓
+    (0x1F6BB, "Restroom"),         // 🚻
+    (0x1F4F7, "Scenic Area"),      // 📷
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum WaypointFileFormat {
+    Gpx,
+    GeoJson,
+}
+
+impl WaypointFileFormat {
+    /// Infers the format of a waypoint file from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "gpx" => Some(WaypointFileFormat::Gpx),
+            "geojson" | "json" => Some(WaypointFileFormat::GeoJson),
+            _ => None,
+        }
+    }
+}
+
+/// Truncates to at most `max_bytes` bytes without splitting a character.
+fn truncate_bytes(value: &str, max_bytes: usize) -> String {
+    let value = value.trim();
+    let mut end = value.len().min(max_bytes);
+
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    value[..end].to_string()
+}
+
+fn icon_to_symbol(icon: u32) -> Option<String> {
+    if let Some((_, symbol)) = WAYPOINT_SYMBOLS.iter().find(|(i, _)| *i == icon) {
+        return Some(symbol.to_string());
+    }
+
+    char::from_u32(icon)
+        .filter(|_| icon != 0)
+        .map(|c| c.to_string())
+}
+
+fn symbol_to_icon(symbol: &str) -> u32 {
+    let symbol = symbol.trim();
+
+    if let Some((icon, _)) = WAYPOINT_SYMBOLS
+        .iter()
+        .find(|(_, s)| s.eq_ignore_ascii_case(symbol))
+    {
+        return *icon;
+    }
+
+    // Symbols that are already a single emoji are used as is
+    let mut chars = symbol.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_ascii() => c as u32,
+        _ => 0,
+    }
+}
+
+fn is_valid_location(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+}
+
+fn build_waypoint(
+    id: u32,
+    latitude: f64,
+    longitude: f64,
+    name: &str,
+    description: &str,
+    icon: u32,
+) -> NormalizedWaypoint {
+    NormalizedWaypoint {
+        id,
+        latitude,
+        longitude,
+        expire: 0,
+        locked_to: 0,
+        name: truncate_bytes(name, MAX_WAYPOINT_NAME_LENGTH),
+        description: truncate_bytes(description, MAX_WAYPOINT_DESCRIPTION_LENGTH),
+        icon,
+    }
+}
+
+/// Exports waypoints as GPX `<wpt>` elements or GeoJSON point features.
+pub fn export_waypoints(waypoints: &[NormalizedWaypoint], format: WaypointFileFormat) -> String {
+    match format {
+        WaypointFileFormat::Gpx => to_gpx(waypoints),
+        WaypointFileFormat::GeoJson => to_geojson(waypoints).to_string(),
+    }
+}
+
+/// Parses waypoints from the contents of a GPX or GeoJSON file.
+///
+/// # Arguments
+///
+/// * `contents` - The contents of the file.
+/// * `format` - The format of the file.
+///
+/// # Returns
+///
+/// * `Result<Vec<NormalizedWaypoint>, String>` - The parsed waypoints, with
+///   names and descriptions truncated to firmware limits. Waypoints without
+///   an id in the file have an id of 0.
+pub fn import_waypoints(
+    contents: &str,
+    format: WaypointFileFormat,
+) -> Result<Vec<NormalizedWaypoint>, String> {
+    let waypoints = match format {
+        WaypointFileFormat::Gpx => from_gpx(contents),
+        WaypointFileFormat::GeoJson => from_geojson(contents)?,
+    };
+
+    debug!("Parsed {} waypoints from {:?}", waypoints.len(), format);
+
+    Ok(waypoints)
+}
+
+fn to_gpx(waypoints: &[NormalizedWaypoint]) -> String {
+    let mut output = String::new();
+
+    output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    output.push_str(&format!(
+        "<gpx version=\"1.1\" creator=\"{}\" xmlns=\"http://www.topografix.com/GPX/1/1\" xmlns:{}=\"{}\">\n",
+        GPX_CREATOR, GPX_EXTENSIONS_PREFIX, GPX_EXTENSIONS_NAMESPACE
+    ));
+
+    for waypoint in waypoints {
+        output.push_str(&format!(
+            "  <wpt lat=\"{}\" lon=\"{}\">\n",
+            waypoint.latitude, waypoint.longitude
+        ));
+        output.push_str(&format!(
+            "    <name>{}</name>\n",
+            escape_xml(&waypoint.name)
+        ));
+
+        if !waypoint.description.is_empty() {
+            output.push_str(&format!(
+                "    <desc>{}</desc>\n",
+                escape_xml(&waypoint.description)
+            ));
+        }
+
+        if let Some(symbol) = icon_to_symbol(waypoint.icon) {
+            output.push_str(&format!("    <sym>{}</sym>\n", escape_xml(&symbol)));
+        }
+
+        output.push_str(&format!(
+            "    <extensions><{prefix}:id>{}</{prefix}:id></extensions>\n",
+            waypoint.id,
+            prefix = GPX_EXTENSIONS_PREFIX
+        ));
+
+        output.push_str("  </wpt>\n");
+    }
+
+    output.push_str("</gpx>\n");
+
+    output
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn get_xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!("{}=", name);
+    let start = tag
+        .match_indices(&pattern)
+        .find(|(i, _)| tag[..*i].ends_with(char::is_whitespace))?
+        .0
+        + pattern.len();
+    let quote = tag[start..].chars().next()?;
+    let value_start = start + quote.len_utf8();
+    let value_end = value_start + tag[value_start..].find(quote)?;
+
+    Some(unescape_xml(&tag[value_start..value_end]))
+}
+
+fn get_xml_element_text(content: &str, name: &str) -> Option<String> {
+    let start = content.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = start + content[start..].find(&format!("</{}>", name))?;
+    let text = content[start..end].trim();
+
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+        .map_or_else(|| unescape_xml(text), |t| t.to_string());
+
+    Some(text)
+}
+
+/// Reads `<wpt>` elements from a GPX document. This only understands the
+/// subset of GPX needed for waypoints, so tracks and routes are ignored.
+fn from_gpx(contents: &str) -> Vec<NormalizedWaypoint> {
+    let mut waypoints = vec![];
+    let mut remaining = contents;
+
+    while let Some(start) = remaining.find("<wpt") {
+        remaining = &remaining[start..];
+
+        let tag_end = match remaining.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+
+        let tag = &remaining[..tag_end];
+
+        // Self-closing waypoints have no child elements
+        let (content, next) = if tag.ends_with('/') {
+            ("", tag_end + 1)
+        } else {
+            match remaining.find("</wpt>") {
+                Some(end) => (&remaining[tag_end + 1..end], end + "</wpt>".len()),
+                None => break,
+            }
+        };
+
+        let location = get_xml_attribute(tag, "lat")
+            .zip(get_xml_attribute(tag, "lon"))
+            .and_then(|(lat, lon)| Some((lat.parse::<f64>().ok()?, lon.parse::<f64>().ok()?)));
+
+        match location {
+            Some((latitude, longitude)) if is_valid_location(latitude, longitude) => {
+                let name = get_xml_element_text(content, "name").unwrap_or_default();
+                let description = get_xml_element_text(content, "desc")
+                    .or_else(|| get_xml_element_text(content, "cmt"))
+                    .unwrap_or_default();
+                let icon = get_xml_element_text(content, "sym")
+                    .map_or(0, |symbol| symbol_to_icon(&symbol));
+                let id = get_xml_element_text(content, &format!("{}:id", GPX_EXTENSIONS_PREFIX))
+                    .and_then(|id| id.parse::<u32>().ok())
+                    .unwrap_or(0);
+
+                waypoints.push(build_waypoint(
+                    id,
+                    latitude,
+                    longitude,
+                    &name,
+                    &description,
+                    icon,
+                ));
+            }
+            _ => warn!("Skipping GPX waypoint with invalid location: {}", tag),
+        }
+
+        remaining = &remaining[next..];
+    }
+
+    waypoints
+}
+
+fn to_geojson(waypoints: &[NormalizedWaypoint]) -> FeatureCollection {
+    let features = waypoints
+        .iter()
+        .map(|waypoint| {
+            let mut properties = JsonObject::new();
+            properties.insert("id".into(), waypoint.id.into());
+            properties.insert("name".into(), waypoint.name.clone().into());
+            properties.insert("description".into(), waypoint.description.clone().into());
+            properties.insert("expire".into(), waypoint.expire.into());
+            properties.insert("lockedTo".into(), waypoint.locked_to.into());
+
+            if let Some(icon) = char::from_u32(waypoint.icon).filter(|_| waypoint.icon != 0) {
+                properties.insert("icon".into(), icon.to_string().into());
+            }
+
+            if let Some((_, symbol)) = WAYPOINT_SYMBOLS.iter().find(|(i, _)| *i == waypoint.icon) {
+                properties.insert("marker-symbol".into(), symbol.to_string().into());
+            }
+
+            Feature {
+                bbox: None,
+                geometry: Some(Geometry::new(Value::Point(vec![
+                    waypoint.longitude,
+                    waypoint.latitude,
+                ]))),
+                id: None,
+                properties: Some(properties),
+                foreign_members: None,
+            }
+        })
+        .collect();
+
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+fn from_geojson(contents: &str) -> Result<Vec<NormalizedWaypoint>, String> {
+    let features = match contents.parse::<GeoJson>().map_err(|e| e.to_string())? {
+        GeoJson::FeatureCollection(collection) => collection.features,
+        GeoJson::Feature(feature) => vec![feature],
+        GeoJson::Geometry(_) => return Err("GeoJSON must contain features".into()),
+    };
+
+    let mut waypoints = vec![];
+
+    for feature in features {
+        let coordinates = match feature.geometry.as_ref().map(|g| &g.value) {
+            Some(Value::Point(coordinates)) if coordinates.len() >= 2 => coordinates,
+            _ => {
+                debug!("Skipping GeoJSON feature that isn't a point");
+                continue;
+            }
+        };
+
+        let (longitude, latitude) = (coordinates[0], coordinates[1]);
+
+        if !is_valid_location(latitude, longitude) {
+            warn!(
+                "Skipping GeoJSON waypoint with invalid location: {}, {}",
+                latitude, longitude
+            );
+            continue;
+        }
+
+        let get_string = |key: &str| -> Option<String> {
+            feature
+                .property(key)
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string())
+        };
+
+        let id = feature
+            .property("id")
+            .and_then(|value| value.as_u64())
+            .and_then(|id| u32::try_from(id).ok())
+            .unwrap_or(0);
+
+        let icon = get_string("icon")
+            .or_else(|| get_string("marker-symbol"))
+            .or_else(|| get_string("symbol"))
+            .map_or(0, |symbol| symbol_to_icon(&symbol));
+
+        waypoints.push(build_waypoint(
+            id,
+            latitude,
+            longitude,
+            &get_string("name").unwrap_or_default(),
+            &get_string("description")
+                .or_else(|| get_string("desc"))
+                .unwrap_or_default(),
+            icon,
+        ));
+    }
+
+    Ok(waypoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gpx_round_trip() {
+        let waypoints = vec![NormalizedWaypoint {
+            id: 1,
+            latitude: 45.5,
+            longitude: -122.25,
+            expire: 0,
+            locked_to: 0,
+            name: "Camp & \"base\"".into(),
+            description: "Near the <river>".into(),
+            icon: 0x26FA,
+        }];
+
+        let gpx = export_waypoints(&waypoints, WaypointFileFormat::Gpx);
+        assert!(gpx.contains("<sym>Campground</sym>"));
+
+        let imported = import_waypoints(&gpx, WaypointFileFormat::Gpx).unwrap();
+
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].id, 1);
+        assert_eq!(imported[0].name, waypoints[0].name);
+        assert_eq!(imported[0].description, waypoints[0].description);
+        assert_eq!(imported[0].icon, 0x26FA);
+        assert_eq!(imported[0].latitude, 45.5);
+    }
+
+    #[test]
+    fn truncates_imported_strings() {
+        let geojson = format!(
+            r#"{{"type":"FeatureCollection","features":[
+                {{"type":"Feature","geometry":{{"type":"Point","coordinates":[10.0,20.0]}},
+                  "properties":{{"name":"{}","description":"{}","icon":"🚗"}}}},
+                {{"type":"Feature","geometry":{{"type":"LineString","coordinates":[[0,0],[1,1]]}},
+                  "properties":{{}}}}
+            ]}}"#,
+            "n".repeat(40),
+            "d".repeat(150)
+        );
+
+        let imported = import_waypoints(&geojson, WaypointFileFormat::GeoJson).unwrap();
+
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].name.len(), MAX_WAYPOINT_NAME_LENGTH);
+        assert_eq!(
+            imported[0].description.len(),
+            MAX_WAYPOINT_DESCRIPTION_LENGTH
+        );
+        assert_eq!(imported[0].icon, 0x1F697);
+        assert_eq!(imported[0].longitude, 10.0);
+    }
+
+    #[test]
+    fn truncates_multi_byte_names_at_char_boundary() {
+        // Each "é" is two bytes, so 20 of them are 40 bytes
+        let name = "é".repeat(20);
+        let truncated = truncate_bytes(&name, MAX_WAYPOINT_NAME_LENGTH);
+
+        assert_eq!(truncated, "é".repeat(14));
+
+        // A three byte character that would straddle the limit is dropped
+        let truncated = truncate_bytes(&format!("{}€", "a".repeat(27)), MAX_WAYPOINT_NAME_LENGTH);
+
+        assert_eq!(truncated, "a".repeat(27));
+    }
+
+    #[test]
+    fn gpx_without_ids_imports_as_new_waypoints() {
+        let gpx = r#"<gpx><wpt lat="1.5" lon="2.5"><name>Spring</name></wpt></gpx>"#;
+
+        let imported = import_waypoints(gpx, WaypointFileFormat::Gpx).unwrap();
+
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].id, 0);
+        assert_eq!(imported[0].name, "Spring");
+    }
+}
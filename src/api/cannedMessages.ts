@@ -0,0 +1,70 @@
+import { invoke } from "@tauri-apps/api";
+
+import { app_canned_messages_CannedMessageSet } from "@bindings/index";
+import { DeviceKey } from "@utils/connections";
+
+export const requestCannedMessages = async (
+  deviceKey: DeviceKey,
+  nodeNum?: number,
+) => {
+  const response = (await invoke("request_canned_messages", {
+    deviceKey,
+    nodeNum,
+  })) as undefined;
+
+  return response;
+};
+
+export const setCannedMessages = async (
+  deviceKey: DeviceKey,
+  messages: string[],
+  nodeNum?: number,
+) => {
+  const response = (await invoke("set_canned_messages", {
+    deviceKey,
+    messages,
+    nodeNum,
+  })) as undefined;
+
+  return response;
+};
+
+export const getCannedMessageSets = async () => {
+  const response = (await invoke(
+    "get_canned_message_sets",
+  )) as app_canned_messages_CannedMessageSet[];
+
+  return response;
+};
+
+export const saveCannedMessageSet = async (
+  set: app_canned_messages_CannedMessageSet,
+) => {
+  const response = (await invoke("save_canned_message_set", {
+    set,
+  })) as app_canned_messages_CannedMessageSet;
+
+  return response;
+};
+
+export const deleteCannedMessageSet = async (setId: number) => {
+  const response = (await invoke("delete_canned_message_set", {
+    setId,
+  })) as undefined;
+
+  return response;
+};
+
+export const applyCannedMessageSet = async (
+  deviceKey: DeviceKey,
+  setId: number,
+  nodeNum?: number,
+) => {
+  const response = (await invoke("apply_canned_message_set", {
+    deviceKey,
+    setId,
+    nodeNum,
+  })) as undefined;
+
+  return response;
+};
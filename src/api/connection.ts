@@ -1,5 +1,8 @@
 import { invoke } from "@tauri-apps/api";
-import { app_ipc_DeviceBulkConfig } from "@bindings/index";
+import {
+  app_ipc_DeviceBulkConfig,
+  app_mqtt_MqttBrokerConfig,
+} from "@bindings/index";
 import { DeviceKey } from "@utils/connections";
 
 export const updateDeviceConfigBulk = async (
@@ -63,3 +66,33 @@ export const dropAllDeviceConnections = async () => {
 
   return response;
 };
+
+export const connectToMqttBroker = async (
+  config: app_mqtt_MqttBrokerConfig,
+) => {
+  const response = (await invoke("connect_to_mqtt_broker", {
+    config,
+  })) as undefined;
+
+  return response;
+};
+
+export const enableMqttClientProxy = async (
+  deviceKey: DeviceKey,
+  config: app_mqtt_MqttBrokerConfig,
+) => {
+  const response = (await invoke("enable_mqtt_client_proxy", {
+    deviceKey,
+    config,
+  })) as undefined;
+
+  return response;
+};
+
+export const disableMqttClientProxy = async (deviceKey: DeviceKey) => {
+  const response = (await invoke("disable_mqtt_client_proxy", {
+    deviceKey,
+  })) as undefined;
+
+  return response;
+};
@@ -0,0 +1,39 @@
+import { invoke } from "@tauri-apps/api";
+
+import {
+  app_geofence_Geofence,
+  app_geofence_GeofenceEvent,
+  app_geofence_GeofenceHistoryFilter,
+} from "@bindings/index";
+
+export const addGeofence = async (geofence: app_geofence_Geofence) => {
+  const response = (await invoke("add_geofence", {
+    geofence,
+  })) as app_geofence_Geofence;
+
+  return response;
+};
+
+export const removeGeofence = async (geofenceId: number) => {
+  const response = (await invoke("remove_geofence", {
+    geofenceId,
+  })) as undefined;
+
+  return response;
+};
+
+export const getGeofences = async () => {
+  const response = (await invoke("get_geofences")) as app_geofence_Geofence[];
+
+  return response;
+};
+
+export const getGeofenceHistory = async (
+  filter?: app_geofence_GeofenceHistoryFilter,
+) => {
+  const response = (await invoke("get_geofence_history", {
+    filter,
+  })) as app_geofence_GeofenceEvent[];
+
+  return response;
+};
@@ -1,4 +1,18 @@
 import { invoke } from "@tauri-apps/api";
+import {
+  app_geo_GeoBounds,
+  app_geo_GeoPoint,
+  app_graph_api_coverage_CoverageConfig,
+  app_graph_api_coverage_CoverageRaster,
+  app_graph_api_export_GraphExportFormat,
+  app_graph_api_relay_RelayPlacementConfig,
+  app_graph_api_relay_RelayPlacementResult,
+  app_graph_api_route_RouteMetric,
+  app_graph_api_route_RouteResult,
+  app_graph_ds_components_GraphComponents,
+  app_graph_ds_history_GraphDiff,
+  app_graph_ds_timeout_GraphTimeoutConfig,
+} from "@bindings/index";
 import { MeshGraph } from "@app/types/graph";
 
 export const fetchGraph = async () => {
@@ -21,3 +35,110 @@ export const stopTimeoutHandler = async () => {
 
   return response;
 };
+
+export const getGraphTimeoutConfig = async () => {
+  const response = (await invoke(
+    "get_graph_timeout_config",
+  )) as app_graph_ds_timeout_GraphTimeoutConfig;
+
+  return response;
+};
+
+export const updateGraphTimeoutConfig = async (
+  config: app_graph_ds_timeout_GraphTimeoutConfig,
+) => {
+  const response = (await invoke("update_graph_timeout_config", {
+    config,
+  })) as undefined;
+
+  return response;
+};
+
+export const getGraphAt = async (timestamp: string) => {
+  const response = (await invoke("get_graph_at", {
+    timestamp,
+  })) as MeshGraph;
+
+  return response;
+};
+
+export const diffGraphs = async (start: string, end: string) => {
+  const response = (await invoke("diff_graphs", {
+    start,
+    end,
+  })) as app_graph_ds_history_GraphDiff;
+
+  return response;
+};
+
+export const computeRoute = async (
+  from: number,
+  to: number,
+  metric: app_graph_api_route_RouteMetric,
+  maxRoutes?: number,
+) => {
+  const response = (await invoke("compute_route", {
+    from,
+    to,
+    metric,
+    maxRoutes,
+  })) as app_graph_api_route_RouteResult;
+
+  return response;
+};
+
+export const exportGraph = async (
+  format: app_graph_api_export_GraphExportFormat,
+) => {
+  const response = (await invoke("export_graph", {
+    format,
+  })) as string;
+
+  return response;
+};
+
+export const getComponents = async () => {
+  const response = (await invoke(
+    "get_components",
+  )) as app_graph_ds_components_GraphComponents;
+
+  return response;
+};
+
+export const getCoverageGeojson = async (
+  config?: app_graph_api_coverage_CoverageConfig,
+) => {
+  const response = (await invoke("get_coverage_geojson", {
+    config,
+  })) as string;
+
+  return response;
+};
+
+export const getCoverageRaster = async (
+  cellSizeMeters: number,
+  config?: app_graph_api_coverage_CoverageConfig,
+) => {
+  const response = (await invoke("get_coverage_raster", {
+    cellSizeMeters,
+    config,
+  })) as app_graph_api_coverage_CoverageRaster;
+
+  return response;
+};
+
+export const suggestRelayPlacement = async (
+  k: number,
+  bounds: app_geo_GeoBounds,
+  candidates?: app_geo_GeoPoint[],
+  config?: app_graph_api_relay_RelayPlacementConfig,
+) => {
+  const response = (await invoke("suggest_relay_placement", {
+    k,
+    bounds,
+    candidates,
+    config,
+  })) as app_graph_api_relay_RelayPlacementResult;
+
+  return response;
+};
@@ -0,0 +1,44 @@
+import { invoke } from "@tauri-apps/api";
+
+import {
+  app_health_HealthThresholds,
+  app_health_NetworkHealth,
+} from "@bindings/index";
+
+export const initializeHealthMonitor = async () => {
+  const response = (await invoke("initialize_health_monitor")) as undefined;
+
+  return response;
+};
+
+export const stopHealthMonitor = async () => {
+  const response = (await invoke("stop_health_monitor")) as undefined;
+
+  return response;
+};
+
+export const getNetworkHealth = async () => {
+  const response = (await invoke(
+    "get_network_health",
+  )) as app_health_NetworkHealth | null;
+
+  return response;
+};
+
+export const getHealthThresholds = async () => {
+  const response = (await invoke(
+    "get_health_thresholds",
+  )) as app_health_HealthThresholds;
+
+  return response;
+};
+
+export const updateHealthThresholds = async (
+  thresholds: app_health_HealthThresholds,
+) => {
+  const response = (await invoke("update_health_thresholds", {
+    thresholds,
+  })) as undefined;
+
+  return response;
+};
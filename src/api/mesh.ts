@@ -41,3 +41,49 @@ export const deleteWaypoint = async (
 
   return response;
 };
+
+export const sendReply = async (
+  deviceKey: DeviceKey,
+  text: string,
+  channel: number,
+  replyId: number,
+) => {
+  const response = (await invoke("send_reply", {
+    deviceKey,
+    text,
+    channel,
+    replyId,
+  })) as undefined;
+
+  return response;
+};
+
+export const sendReaction = async (
+  deviceKey: DeviceKey,
+  emoji: string,
+  channel: number,
+  replyId: number,
+) => {
+  const response = (await invoke("send_reaction", {
+    deviceKey,
+    emoji,
+    channel,
+    replyId,
+  })) as undefined;
+
+  return response;
+};
+
+export const updateWaypoint = async (
+  deviceKey: DeviceKey,
+  waypoint: app_device_NormalizedWaypoint,
+  channel: number,
+) => {
+  const response = (await invoke("update_waypoint", {
+    deviceKey,
+    waypoint,
+    channel,
+  })) as undefined;
+
+  return response;
+};
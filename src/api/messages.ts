@@ -0,0 +1,38 @@
+import { invoke } from "@tauri-apps/api";
+
+import {
+  app_device_messages_MessageExportFormat,
+  app_device_messages_MessageSearchFilters,
+  app_device_messages_MessageSearchResult,
+} from "@bindings/index";
+import { DeviceKey } from "@utils/connections";
+
+export const searchMessages = async (
+  query: string,
+  filters?: app_device_messages_MessageSearchFilters,
+) => {
+  const response = (await invoke("search_messages", {
+    query,
+    filters,
+  })) as app_device_messages_MessageSearchResult[];
+
+  return response;
+};
+
+export const exportMessages = async (
+  deviceKey: DeviceKey,
+  format: app_device_messages_MessageExportFormat,
+  channel?: number,
+  start?: number,
+  end?: number,
+) => {
+  const response = (await invoke("export_messages", {
+    deviceKey,
+    format,
+    channel,
+    start,
+    end,
+  })) as string;
+
+  return response;
+};
@@ -1,6 +1,8 @@
 import { invoke } from "@tauri-apps/api";
 
 import {
+  app_device_ChannelPskType,
+  app_ipc_ChannelKeyUpdateStatus,
   app_ipc_DeviceBulkConfig,
   meshtastic_protobufs_Config,
   meshtastic_protobufs_User,
@@ -58,3 +60,29 @@ export const updateDeviceConfigBulk = async (
 
   return response;
 };
+
+export const setChannelPsk = async (
+  deviceKey: DeviceKey,
+  channelIndex: number,
+  pskType: app_device_ChannelPskType,
+) => {
+  const response = (await invoke("set_channel_psk", {
+    deviceKey,
+    channelIndex,
+    pskType,
+  })) as app_ipc_ChannelKeyUpdateStatus;
+
+  return response;
+};
+
+export const rotateChannelPskAllDevices = async (
+  channelIndex: number,
+  pskType: app_device_ChannelPskType,
+) => {
+  const response = (await invoke("rotate_channel_psk_all_devices", {
+    channelIndex,
+    pskType,
+  })) as app_ipc_ChannelKeyUpdateStatus[];
+
+  return response;
+};
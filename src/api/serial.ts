@@ -0,0 +1,43 @@
+import { invoke } from "@tauri-apps/api";
+
+import {
+  app_serial_bridge_SerialBridgeInfo,
+  app_serial_bridge_SerialBridgeTarget,
+} from "@bindings/index";
+import { DeviceKey } from "@utils/connections";
+
+export const openSerialBridge = async (
+  deviceKey: DeviceKey,
+  nodeNum: number,
+  channel: number,
+  target: app_serial_bridge_SerialBridgeTarget,
+) => {
+  const response = (await invoke("open_serial_bridge", {
+    deviceKey,
+    nodeNum,
+    channel,
+    target,
+  })) as app_serial_bridge_SerialBridgeInfo;
+
+  return response;
+};
+
+export const closeSerialBridge = async (
+  deviceKey: DeviceKey,
+  nodeNum: number,
+) => {
+  const response = (await invoke("close_serial_bridge", {
+    deviceKey,
+    nodeNum,
+  })) as undefined;
+
+  return response;
+};
+
+export const getSerialBridges = async (deviceKey: DeviceKey) => {
+  const response = (await invoke("get_serial_bridges", {
+    deviceKey,
+  })) as app_serial_bridge_SerialBridgeInfo[];
+
+  return response;
+};
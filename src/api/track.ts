@@ -0,0 +1,34 @@
+import { invoke } from "@tauri-apps/api";
+
+import {
+  app_track_NodeTrack,
+  app_track_TrackExportFormat,
+} from "@bindings/index";
+import { DeviceKey } from "@utils/connections";
+
+export const getNodeTrack = async (deviceKey: DeviceKey, nodeNum: number) => {
+  const response = (await invoke("get_node_track", {
+    deviceKey,
+    nodeNum,
+  })) as app_track_NodeTrack | null;
+
+  return response;
+};
+
+export const exportNodeTrack = async (
+  deviceKey: DeviceKey,
+  nodeNum: number,
+  format: app_track_TrackExportFormat,
+  start?: number,
+  end?: number,
+) => {
+  const response = (await invoke("export_node_track", {
+    deviceKey,
+    nodeNum,
+    format,
+    start,
+    end,
+  })) as string;
+
+  return response;
+};
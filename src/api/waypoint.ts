@@ -0,0 +1,35 @@
+import { invoke } from "@tauri-apps/api";
+
+import {
+  app_device_NormalizedWaypoint,
+  app_waypoint_WaypointFileFormat,
+} from "@bindings/index";
+import { DeviceKey } from "@utils/connections";
+
+export const importWaypoints = async (
+  deviceKey: DeviceKey,
+  file: string,
+  broadcast?: boolean,
+  channel?: number,
+) => {
+  const response = (await invoke("import_waypoints", {
+    deviceKey,
+    file,
+    broadcast,
+    channel,
+  })) as app_device_NormalizedWaypoint[];
+
+  return response;
+};
+
+export const exportWaypoints = async (
+  deviceKey: DeviceKey,
+  format: app_waypoint_WaypointFileFormat,
+) => {
+  const response = (await invoke("export_waypoints", {
+    deviceKey,
+    format,
+  })) as string;
+
+  return response;
+};
@@ -86,7 +86,7 @@ export type meshtastic_protobufs_DeviceMetadata = { firmwareVersion: string; dev
  */
 export type meshtastic_protobufs_NetworkConnectionStatus = { ipAddress: number; isConnected: boolean; isMqttConnected: boolean; isSyslogConnected: boolean }
 
-export type app_graph_ds_edge_GraphEdge = { snr: number; from: number; to: number; lastHeard: string; timeoutDuration: string }
+export type app_graph_ds_edge_GraphEdge = { snr: number; from: number; to: number; lastHeard: string; timeoutDuration: string; stale: boolean; observers: { [key: string]: app_graph_ds_observation_EdgeObservation } }
 
 /**
  * 
@@ -300,7 +300,7 @@ export type meshtastic_protobufs_NodeRemoteHardwarePinsResponse = { nodeRemoteHa
  */
 export type meshtastic_protobufs_HardwareMessage = { type: number; gpioMask: string; gpioValue: string }
 
-export type app_device_MeshDevice = { configId: number; ready: boolean; status: app_device_SerialDeviceStatus; channels: { [key: number]: app_device_MeshChannel }; config: meshtastic_protobufs_LocalConfig; moduleConfig: meshtastic_protobufs_LocalModuleConfig; myNodeInfo: meshtastic_protobufs_MyNodeInfo; nodes: { [key: number]: app_device_MeshNode }; regionUnset: boolean; deviceMetrics: meshtastic_protobufs_DeviceMetrics; waypoints: { [key: number]: app_device_NormalizedWaypoint }; neighbors: { [key: number]: app_device_NeighborInfoPacket }; configInProgress: boolean }
+export type app_device_MeshDevice = { configId: number; ready: boolean; status: app_device_SerialDeviceStatus; channels: { [key: number]: app_device_MeshChannel }; config: meshtastic_protobufs_LocalConfig; moduleConfig: meshtastic_protobufs_LocalModuleConfig; myNodeInfo: meshtastic_protobufs_MyNodeInfo; nodes: { [key: number]: app_device_MeshNode }; regionUnset: boolean; deviceMetrics: meshtastic_protobufs_DeviceMetrics; waypoints: { [key: number]: app_device_NormalizedWaypoint }; neighbors: { [key: number]: app_device_NeighborInfoPacket }; configInProgress: boolean; encryptedPacketCounts: { [key: number]: number }; cannedMessages: { [key: number]: string[] } }
 
 /**
  * 
@@ -445,7 +445,7 @@ export type meshtastic_protobufs_module_config_CannedMessageConfig = { rotary1En
  */
 export type meshtastic_protobufs_User = { id: string; longName: string; shortName: string; macaddr: number[]; hwModel: number; isLicensed: boolean }
 
-export type app_device_ChannelMessageWithState = { payload: app_device_ChannelMessagePayload; state: app_device_ChannelMessageState }
+export type app_device_ChannelMessageWithState = { payload: app_device_ChannelMessagePayload; state: app_device_ChannelMessageState; replyTo: number | null; replyIds: number[]; reactions: app_device_MessageReaction[]; heardCount: number; bestRxSnr: number | null }
 
 export type app_device_MeshChannel = { config: meshtastic_protobufs_Channel; lastInteraction: number; messages: app_device_ChannelMessageWithState[] }
 
@@ -752,7 +752,7 @@ export type meshtastic_protobufs_AdminMessage = { payloadVariant: meshtastic_pro
  */
 export type meshtastic_protobufs_Routing = { variant: meshtastic_protobufs_routing_Variant | null }
 
-export type app_graph_ds_node_GraphNode = { nodeNum: number; lastHeard: string; timeoutDuration: string }
+export type app_graph_ds_node_GraphNode = { nodeNum: number; lastHeard: string; timeoutDuration: string; stale: boolean }
 
 /**
  * 
@@ -828,3 +828,129 @@ export type meshtastic_protobufs_Data = { portnum: number; payload: number[]; wa
  */
 export type meshtastic_protobufs_module_config_RemoteHardwareConfig = { enabled: boolean; allowUndefinedPinAccess: boolean; availablePins: meshtastic_protobufs_RemoteHardwarePin[] }
 
+export type app_canned_messages_CannedMessageSet = { id: number; name: string; messages: string[] }
+
+export type app_device_ChannelPskType = "aes128" | "aes256" | "default" | "none"
+
+export type app_device_MessageReaction = { emoji: string; nodeNums: number[] }
+
+export type app_device_messages_MessageExportFormat = "csv" | "jsonLines" | "markdown"
+
+export type app_device_messages_MessageSearchFilters = { deviceKey: string | null; channelId: number | null; from: number | null; start: number | null; end: number | null; state: app_device_ChannelMessageState | null; limit: number | null }
+
+export type app_device_messages_MessageSearchResult = { deviceKey: string; channelId: number; time: number; message: app_device_ChannelMessageWithState }
+
+/**
+ * A rectangular area between two corners
+ */
+export type app_geo_GeoBounds = { minLatitude: number; minLongitude: number; maxLatitude: number; maxLongitude: number }
+
+export type app_geo_GeoPoint = { latitude: number; longitude: number }
+
+export type app_geofence_Geofence = { id: number; name: string; shape: app_geofence_GeofenceShape; dwellSecs: number | null; nodeNums: number[] | null }
+
+export type app_geofence_GeofenceEvent = { geofenceId: number; geofenceName: string; nodeNum: number; kind: app_geofence_GeofenceEventKind; location: app_geo_GeoPoint; timestamp: number }
+
+export type app_geofence_GeofenceEventKind = "enter" | "exit" | "dwell"
+
+export type app_geofence_GeofenceHistoryFilter = { geofenceId: number | null; nodeNum: number | null; start: number | null; end: number | null }
+
+export type app_geofence_GeofenceShape = { type: "circle"; center: app_geo_GeoPoint; radius_meters: number } | { type: "polygon"; vertices: app_geo_GeoPoint[] }
+
+export type app_graph_api_coverage_CoverageConfig = { minSnr: number; defaultPathLossExponent: number; maxRangeMeters: number }
+
+/**
+ * Best predicted SNR from any node, sampled on a grid. Cells are stored row by
+ * row from the south-west corner, and are `None` where no node is predicted to reach.
+ */
+export type app_graph_api_coverage_CoverageRaster = { bounds: app_geo_GeoBounds; rows: number; columns: number; cellSizeMeters: number; cells: (number | null)[] }
+
+/**
+ * Log-distance path loss model fitted to the links of a single node, where
+ * `snr(d) = reference_snr - 10 * path_loss_exponent * log10(d / 1 km)`
+ */
+export type app_graph_api_coverage_NodeCoverageModel = { nodeNum: number; location: app_geo_GeoPoint; referenceSnr: number; pathLossExponent: number; linkCount: number; rangeMeters: number }
+
+export type app_graph_api_export_GraphExportFormat = "graphMl" | "dot" | "geoJson"
+
+export type app_graph_api_relay_ConnectivityMetrics = { componentCount: number; articulationPointCount: number; worstCaseReliability: number }
+
+export type app_graph_api_relay_RelayPlacementConfig = { referenceSnr: number; pathLossExponent: number; minSnr: number; gridSpacingMeters: number }
+
+export type app_graph_api_relay_RelayPlacementResult = { baseline: app_graph_api_relay_ConnectivityMetrics; suggestions: app_graph_api_relay_RelaySuggestion[] }
+
+export type app_graph_api_relay_RelaySuggestion = { location: app_geo_GeoPoint; linkedNodeNums: number[]; metrics: app_graph_api_relay_ConnectivityMetrics }
+
+export type app_graph_api_route_Route = { nodeNums: number[]; hops: number; cost: number; deliveryProbability: number }
+
+export type app_graph_api_route_RouteMetric = "hopCount" | "reliability"
+
+export type app_graph_api_route_RouteResult = { from: number; to: number; metric: app_graph_api_route_RouteMetric; routes: app_graph_api_route_Route[] }
+
+export type app_graph_ds_components_ComponentChange = { kind: app_graph_ds_components_ComponentChangeKind; nodeGroups: number[][] }
+
+export type app_graph_ds_components_ComponentChangeKind = "split" | "merge"
+
+export type app_graph_ds_components_GraphComponent = { nodeNums: number[]; size: number }
+
+/**
+ * Groups of nodes that can reach each other, largest first
+ */
+export type app_graph_ds_components_GraphComponents = { weak: app_graph_ds_components_GraphComponent[]; strong: app_graph_ds_components_GraphComponent[] }
+
+export type app_graph_ds_history_GraphChange = { type: "nodeAdded"; node_num: number } | { type: "nodeRemoved"; node_num: number } | { type: "edgeAdded"; from: number; to: number; snr: number } | { type: "edgeRemoved"; from: number; to: number }
+
+/**
+ * Nodes and links that appeared or disappeared between two points in time
+ */
+export type app_graph_ds_history_GraphDiff = { addedNodes: number[]; removedNodes: number[]; addedEdges: ([number, number])[]; removedEdges: ([number, number])[] }
+
+export type app_graph_ds_history_GraphHistoryEvent = { timestamp: string; change: app_graph_ds_history_GraphChange }
+
+/**
+ * A single radio's view of an edge
+ */
+export type app_graph_ds_observation_EdgeObservation = { snr: number; lastHeard: string }
+
+/**
+ * A single radio's view of a node
+ */
+export type app_graph_ds_observation_NodeObservation = { lastHeard: string }
+
+/**
+ * Items that changed during a single graph clean
+ */
+export type app_graph_ds_timeout_GraphCleanResult = { staleNodes: number[]; staleEdges: ([number, number])[]; removedNodes: number[]; removedEdges: ([number, number])[] }
+
+export type app_graph_ds_timeout_GraphTimeoutConfig = { cleanIntervalSecs: number; nodeTimeoutSecs: number | null; edgeTimeoutSecs: number | null; staleGracePeriodSecs: number | null }
+
+export type app_health_HealthThresholds = { checkIntervalSecs: number; silentNodeSecs: number; lowBatteryLevel: number; batteryDropLevel: number; channelUtilizationLimit: number; airUtilTxLimit: number; notificationsEnabled: boolean }
+
+export type app_health_NetworkAlert = { kind: app_health_NetworkAlertKind; nodeNum: number | null; message: string; timestamp: number }
+
+export type app_health_NetworkAlertKind = "nodeSilent" | "batteryCollapse" | "channelUtilizationSpike" | "airUtilTxSpike" | "partitionFormed"
+
+export type app_health_NetworkHealth = { timestamp: number; score: number; nodeCount: number; silentNodeCount: number; partitionCount: number; nodes: app_health_NodeHealth[] }
+
+export type app_health_NodeHealth = { nodeNum: number; score: number; silent: boolean; secondsSinceHeard: number | null; batteryLevel: number | null; channelUtilization: number | null; airUtilTx: number | null; neighborCount: number }
+
+export type app_ipc_ChannelKeyUpdateStatus = { deviceKey: string; channelIndex: number; successful: boolean; warnings: string[] }
+
+export type app_mqtt_MqttBrokerConfig = { host: string; port: number | null; username: string | null; password: string | null; rootTopic: string | null }
+
+export type app_serial_bridge_SerialBridgeInfo = { nodeNum: number; channel: number; localAddress: string }
+
+export type app_serial_bridge_SerialBridgeTarget = { type: "tcp"; port: number } | { type: "pty" }
+
+export type app_track_NodeMovementAlert = { nodeNum: number; from: app_geo_GeoPoint; to: app_geo_GeoPoint; distanceMeters: number; time: number }
+
+export type app_track_NodeTrack = { nodeNum: number; points: app_track_TrackPoint[]; totalDistanceMeters: number; anchor: app_track_TrackPoint | null; isStatic: boolean }
+
+export type app_track_TrackConfig = { dedupRadiusMeters: number; movementThresholdMeters: number; staticAfterSecs: number; maxPoints: number }
+
+export type app_track_TrackExportFormat = "gpx" | "kml"
+
+export type app_track_TrackPoint = { location: app_geo_GeoPoint; altitude: number; time: number; lastTime: number; fixCount: number; distanceMeters: number; speedMps: number; headingDegrees: number }
+
+export type app_waypoint_WaypointFileFormat = "gpx" | "geoJson"
+
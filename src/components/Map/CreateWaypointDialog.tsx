@@ -49,8 +49,8 @@ import { getChannelName } from "@utils/messaging";
 
 import "@components/Map/MapView.css";
 
-const WAYPOINT_NAME_MAX_LEN = 30;
-const WAYPOINT_DESC_MAX_LEN = 100;
+const WAYPOINT_NAME_MAX_LEN = 29;
+const WAYPOINT_DESC_MAX_LEN = 99;
 
 // TODO follow this: https://github.com/missive/emoji-mart/issues/576
 export type Emoji = {
@@ -1,6 +1,7 @@
 import {
   app_graph_ds_edge_GraphEdge,
   app_graph_ds_node_GraphNode,
+  app_graph_ds_observation_NodeObservation,
 } from "@bindings/index";
 
 export interface StableGraph {
@@ -13,4 +14,8 @@ export interface StableGraph {
 export interface MeshGraph {
   graph: StableGraph;
   nodesLookup: Record<number, app_graph_ds_node_GraphNode>;
+  node_observations: Record<
+    number,
+    Record<string, app_graph_ds_observation_NodeObservation>
+  >; // radios that have heard each node
 }